@@ -1,8 +1,23 @@
 pub mod application_builder;
 pub mod application_context;
+pub mod clock;
+pub mod color;
 pub mod drawables;
 pub mod errors;
+pub mod fixed_timestep;
+pub mod frame_capture;
+pub mod frame_timer;
 pub mod game;
 pub mod gl;
 pub mod helpers;
+pub mod input;
+pub mod mesh;
+pub mod render_state;
+pub mod render_target;
+pub mod rgba_image;
+pub mod shader_preprocessor;
+pub mod text;
+pub mod texture;
+pub mod uniform;
+pub mod vertex_layout;
 pub mod wrappers;
@@ -1,15 +1,29 @@
 use bumpalo::Bump;
 use glutin::config::ConfigTemplateBuilder;
 use glutin_winit::DisplayBuilder;
+use tracing::warn;
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize, Position, Size},
     event_loop::EventLoop,
     window::Window,
 };
 
+#[cfg(free_unix)]
+use crate::internal::headless::run_headless;
 use crate::internal::internal_game_loop::InnerApplication;
 
-use super::{errors::DuendeError, game::Game};
+#[cfg(free_unix)]
+use super::rgba_image::RgbaImage;
+use super::{
+    clock::{Clock, SystemClock},
+    errors::DuendeError,
+    fixed_timestep::{DEFAULT_MAX_STEPS_PER_FRAME, DEFAULT_UPDATE_RATE},
+    game::Game,
+    helpers::set_shader_hot_reload,
+    input::gamepad::{self, GamepadBackend},
+};
+#[cfg(free_unix)]
+use std::time::Duration;
 
 pub struct ApplicationBuilder {
     title: String,
@@ -17,6 +31,11 @@ pub struct ApplicationBuilder {
     window_position: Option<(i32, i32)>,
     pub(crate) grab_mouse: bool,
     pub(crate) mouse_cursor_visible: bool,
+    pub(crate) fixed_update_rate: u32,
+    pub(crate) max_fixed_updates_per_frame: u32,
+    pub(crate) clock: Box<dyn Clock>,
+    pub(crate) gamepad_backend: Box<dyn GamepadBackend>,
+    pub(crate) hot_reload_shaders: bool,
 }
 
 impl Default for ApplicationBuilder {
@@ -27,6 +46,11 @@ impl Default for ApplicationBuilder {
             window_position: None,
             grab_mouse: false,
             mouse_cursor_visible: true,
+            fixed_update_rate: DEFAULT_UPDATE_RATE,
+            max_fixed_updates_per_frame: DEFAULT_MAX_STEPS_PER_FRAME,
+            clock: Box::new(SystemClock::new()),
+            gamepad_backend: gamepad::default_backend(),
+            hot_reload_shaders: false,
         }
     }
 }
@@ -64,9 +88,63 @@ impl ApplicationBuilder {
         self
     }
 
+    /// Fixed updates per second. A rate of `0` is ignored.
+    pub fn fixed_update_rate(mut self, updates_per_second: u32) -> Self {
+        if updates_per_second == 0 {
+            warn!(
+                "Ignoring a fixed update rate of 0, keeping {}",
+                self.fixed_update_rate
+            );
+        } else {
+            self.fixed_update_rate = updates_per_second;
+        }
+        self
+    }
+
+    pub fn max_fixed_updates_per_frame(mut self, max_updates: u32) -> Self {
+        self.max_fixed_updates_per_frame = max_updates;
+        self
+    }
+
+    pub fn clock<C>(mut self, clock: C) -> Self
+    where
+        C: Clock + 'static,
+    {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    pub fn gamepad_backend<B>(mut self, backend: B) -> Self
+    where
+        B: GamepadBackend + 'static,
+    {
+        self.gamepad_backend = Box::new(backend);
+        self
+    }
+
+    /// Watches the files of shaders that have a path and relinks the
+    /// programs using them when they change. Meant for development.
+    pub fn hot_reload_shaders(mut self, enable: bool) -> Self {
+        self.hot_reload_shaders = enable;
+        self
+    }
+
     pub fn build(self) -> Application {
         Application::new(self)
     }
+
+    /// Builds an application without a window that renders `width` by
+    /// `height` frames offscreen through EGL, e.g. with Mesa's llvmpipe in CI.
+    #[cfg(free_unix)]
+    pub fn headless(self, width: u32, height: u32) -> HeadlessApplication {
+        let frame_interval = Duration::from_secs(1) / self.fixed_update_rate;
+        HeadlessApplication {
+            builder: self,
+            size: (width, height),
+            frames: 1,
+            frame_interval,
+        }
+    }
 }
 
 pub struct Application {
@@ -86,6 +164,7 @@ impl Application {
     where
         G: Game,
     {
+        set_shader_hot_reload(self.builder.hot_reload_shaders);
         let event_loop = EventLoop::new().unwrap();
         let mut window_attributes =
             Window::default_attributes().with_title(self.builder.title.clone());
@@ -97,7 +176,7 @@ impl Application {
             window_attributes =
                 window_attributes.with_position(Position::Physical(PhysicalPosition::new(x, y)));
         }
-        let template = ConfigTemplateBuilder::new();
+        let template = ConfigTemplateBuilder::new().with_depth_size(24);
         let display_builder =
             DisplayBuilder::new().with_window_attributes(Some(window_attributes.clone()));
         let bump = Bump::new();
@@ -113,3 +192,44 @@ impl Application {
         app.exit_state
     }
 }
+
+/// A windowless application, see [`ApplicationBuilder::headless`]. Time is
+/// simulated: every frame advances it by a fixed interval, so runs are
+/// deterministic and the builder's clock is not used.
+#[cfg(free_unix)]
+pub struct HeadlessApplication {
+    builder: ApplicationBuilder,
+    size: (u32, u32),
+    frames: usize,
+    frame_interval: Duration,
+}
+
+#[cfg(free_unix)]
+impl HeadlessApplication {
+    /// Number of frames to render, `1` by default. Rendering stops earlier
+    /// if the game exits.
+    pub fn frames(mut self, frames: usize) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    /// Simulated time between frames, one fixed update by default.
+    pub fn frame_interval(mut self, frame_interval: Duration) -> Self {
+        self.frame_interval = frame_interval;
+        self
+    }
+
+    /// Runs the game and returns the image of every rendered frame.
+    pub fn render<G>(self, game_loop: G) -> Result<Vec<RgbaImage>, DuendeError>
+    where
+        G: Game,
+    {
+        run_headless(
+            self.builder,
+            game_loop,
+            self.size,
+            self.frames,
+            self.frame_interval,
+        )
+    }
+}
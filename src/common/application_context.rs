@@ -1 +1,178 @@
-pub trait ApplicationContext {}
+use super::{
+    color::Color,
+    frame_timer::FrameTimer,
+    gl,
+    input::{
+        gamepad::GamepadState,
+        input_map::{InputMap, InputSources},
+        keyboard::{KeyboardKey, KeyboardState},
+        mouse::MouseState,
+    },
+};
+use crate::{internal::context_backend::Command, utils::mut_cell::MutCell};
+use bumpalo::Bump;
+use std::time::Duration;
+use winit::event::MouseButton;
+
+/// What every application context offers, whether the game is 2D or 3D.
+pub trait ApplicationContext {
+    fn exit(&mut self);
+    fn set_cursor_grab(&mut self, enable: bool);
+    fn set_cursor_visible(&mut self, enable: bool);
+    fn set_background_color(&mut self, red: u8, green: u8, blue: u8, alpha: u8);
+
+    fn keyboard(&self) -> &KeyboardState;
+    fn keyboard_mut(&mut self) -> &mut KeyboardState;
+    fn mouse(&self) -> &MouseState;
+    fn mouse_mut(&mut self) -> &mut MouseState;
+    fn gamepads(&self) -> &GamepadState;
+    fn gamepads_mut(&mut self) -> &mut GamepadState;
+    fn input_map(&self) -> &InputMap;
+    fn input_map_mut(&mut self) -> &mut InputMap;
+
+    fn delta_time(&self) -> Duration;
+    fn elapsed(&self) -> Duration;
+    fn frame_index(&self) -> u64;
+    fn fps(&self) -> f32;
+
+    fn set_input_map(&mut self, input_map: InputMap) {
+        *self.input_map_mut() = input_map;
+    }
+
+    fn action_down(&self, action: &str) -> bool {
+        self.input_map().is_down(action, input_sources(self))
+    }
+
+    fn action_pressed(&self, action: &str) -> bool {
+        self.input_map()
+            .is_just_pressed(action, input_sources(self))
+    }
+
+    fn action_released(&self, action: &str) -> bool {
+        self.input_map()
+            .is_just_released(action, input_sources(self))
+    }
+
+    fn action_value(&self, action: &str) -> f32 {
+        self.input_map().value(action, input_sources(self))
+    }
+
+    /// Whether `key` went down this frame, like
+    /// [`is_key_just_pressed`](ApplicationContext::is_key_just_pressed). Use
+    /// [`is_key_down`](ApplicationContext::is_key_down) for held keys.
+    fn is_key_pressed<K>(&self, key: K) -> bool
+    where
+        K: Into<KeyboardKey>,
+    {
+        self.keyboard().is_key_just_pressed(key)
+    }
+
+    fn is_key_down<K>(&self, key: K) -> bool
+    where
+        K: Into<KeyboardKey>,
+    {
+        self.keyboard().is_key_down(key)
+    }
+
+    fn is_key_just_pressed<K>(&self, key: K) -> bool
+    where
+        K: Into<KeyboardKey>,
+    {
+        self.keyboard().is_key_just_pressed(key)
+    }
+
+    fn is_key_just_released<K>(&self, key: K) -> bool
+    where
+        K: Into<KeyboardKey>,
+    {
+        self.keyboard().is_key_just_released(key)
+    }
+
+    fn is_mouse_button_down(&self, button: MouseButton) -> bool {
+        self.mouse().is_button_down(button)
+    }
+
+    fn is_mouse_button_just_pressed(&self, button: MouseButton) -> bool {
+        self.mouse().is_button_just_pressed(button)
+    }
+
+    fn is_mouse_button_just_released(&self, button: MouseButton) -> bool {
+        self.mouse().is_button_just_released(button)
+    }
+}
+
+fn input_sources<C>(context: &C) -> InputSources<'_>
+where
+    C: ApplicationContext + ?Sized,
+{
+    InputSources {
+        keyboard: context.keyboard(),
+        mouse: context.mouse(),
+        gamepads: context.gamepads(),
+    }
+}
+
+/// State shared by the 2D and 3D contexts: input, timing, the background
+/// color and the commands for the windowing loop.
+pub(crate) struct ContextCore<'a> {
+    pub(crate) keyboard: KeyboardState,
+    pub(crate) mouse: MouseState,
+    pub(crate) gamepads: GamepadState,
+    pub(crate) input_map: InputMap,
+    pub(crate) timer: FrameTimer,
+    output_commands: Vec<Command, &'a Bump>,
+    background_color: MutCell<Color>,
+    bump: &'a Bump,
+}
+
+impl<'a> ContextCore<'a> {
+    pub(crate) fn new(bump: &'a Bump) -> Self {
+        Self {
+            keyboard: KeyboardState::new(),
+            mouse: MouseState::new(),
+            gamepads: GamepadState::new(),
+            input_map: InputMap::new(),
+            timer: FrameTimer::new(),
+            output_commands: Vec::new_in(bump),
+            background_color: MutCell::new(Color::rgb(0.1, 0.1, 0.1)),
+            bump,
+        }
+    }
+
+    pub(crate) fn push_command(&mut self, command: Command) {
+        self.output_commands.push(command);
+    }
+
+    pub(crate) fn pop_all_commands(&mut self) -> Vec<Command, &'a Bump> {
+        let mut output = Vec::new_in(self.bump);
+        std::mem::swap(&mut self.output_commands, &mut output);
+        output
+    }
+
+    pub(crate) fn set_background_color(&mut self, color: Color) {
+        self.background_color.set(color);
+    }
+
+    pub(crate) unsafe fn apply_background_color(&self) {
+        self.background_color.execute_on_change(|new_value| {
+            gl::ClearColor(
+                new_value.red,
+                new_value.green,
+                new_value.blue,
+                new_value.alpha,
+            );
+        });
+    }
+
+    /// Clears the per-frame input state once the frame has been drawn.
+    pub(crate) fn end_frame(&mut self) {
+        self.input_map.end_frame(InputSources {
+            keyboard: &self.keyboard,
+            mouse: &self.mouse,
+            gamepads: &self.gamepads,
+        });
+        self.keyboard.end_frame();
+        self.mouse.end_frame();
+        self.gamepads.end_frame();
+    }
+}
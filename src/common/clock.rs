@@ -0,0 +1,60 @@
+use std::{
+    cell::Cell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+pub trait Clock {
+    fn now(&self) -> Duration;
+}
+
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time, so a test
+/// can keep one handle while the engine owns another.
+#[derive(Clone, Default)]
+pub struct ManualClock {
+    now: Rc<Cell<Duration>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+
+    pub fn set(&self, now: Duration) {
+        self.now.set(now);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
@@ -0,0 +1,75 @@
+/// A linear RGBA color with components in `[0, 1]`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Color {
+    pub red: f32,
+    pub green: f32,
+    pub blue: f32,
+    pub alpha: f32,
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Self::WHITE
+    }
+}
+
+impl Color {
+    pub const WHITE: Color = Color::rgb(1.0, 1.0, 1.0);
+    pub const BLACK: Color = Color::rgb(0.0, 0.0, 0.0);
+    pub const RED: Color = Color::rgb(1.0, 0.0, 0.0);
+    pub const GREEN: Color = Color::rgb(0.0, 1.0, 0.0);
+    pub const BLUE: Color = Color::rgb(0.0, 0.0, 1.0);
+    pub const YELLOW: Color = Color::rgb(1.0, 1.0, 0.0);
+    pub const CYAN: Color = Color::rgb(0.0, 1.0, 1.0);
+    pub const MAGENTA: Color = Color::rgb(1.0, 0.0, 1.0);
+    pub const TRANSPARENT: Color = Color::rgba(0.0, 0.0, 0.0, 0.0);
+
+    pub const fn rgba(red: f32, green: f32, blue: f32, alpha: f32) -> Self {
+        Self {
+            red,
+            green,
+            blue,
+            alpha,
+        }
+    }
+
+    pub const fn rgb(red: f32, green: f32, blue: f32) -> Self {
+        Self::rgba(red, green, blue, 1.0)
+    }
+
+    pub fn from_rgba8(red: u8, green: u8, blue: u8, alpha: u8) -> Self {
+        Self::rgba(
+            red as f32 / u8::MAX as f32,
+            green as f32 / u8::MAX as f32,
+            blue as f32 / u8::MAX as f32,
+            alpha as f32 / u8::MAX as f32,
+        )
+    }
+
+    pub const fn with_alpha(mut self, alpha: f32) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    pub const fn to_array(self) -> [f32; 4] {
+        [self.red, self.green, self.blue, self.alpha]
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from([red, green, blue, alpha]: [f32; 4]) -> Self {
+        Self::rgba(red, green, blue, alpha)
+    }
+}
+
+impl From<[f32; 3]> for Color {
+    fn from([red, green, blue]: [f32; 3]) -> Self {
+        Self::rgb(red, green, blue)
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        color.to_array()
+    }
+}
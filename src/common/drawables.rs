@@ -1,5 +1,7 @@
-use super::errors::GlError;
+use super::{errors::GlError, render_state::GlStateTracker, render_target::RenderTargetBinding};
 use bumpalo::Bump;
+use nalgebra::Matrix4;
+use std::{cell::Cell, rc::Rc};
 
 pub trait Drawable {
     fn draw(&self, ctx: &mut RendererContext) -> Result<(), GlError>;
@@ -8,6 +10,16 @@ pub trait Drawable {
 pub struct RendererContext<'a> {
     pub(crate) bump: &'a Bump,
     pub(crate) command_queue: Vec<Box<dyn FnOnce(), &'a Bump>>,
+    pub(crate) view_matrix: Matrix4<f32>,
+    pub(crate) projection_matrix: Matrix4<f32>,
+    pub(crate) model_matrix: Matrix4<f32>,
+    pub(crate) gl_state: GlStateTracker,
+    pub(crate) viewport: (i32, i32),
+    pub(crate) current_target: Option<RenderTargetBinding>,
+    /// Where the frame goes when no render target is active: `None` for the
+    /// window, or the post-processing scene target. Only known once the
+    /// commands run.
+    pub(crate) frame_target: Rc<Cell<Option<RenderTargetBinding>>>,
 }
 
 impl<'a> RendererContext<'a> {
@@ -15,9 +27,38 @@ impl<'a> RendererContext<'a> {
         Self {
             bump,
             command_queue: Vec::new(),
+            view_matrix: Matrix4::identity(),
+            projection_matrix: Matrix4::identity(),
+            model_matrix: Matrix4::identity(),
+            gl_state: GlStateTracker::new(),
+            viewport: (0, 0),
+            current_target: None,
+            frame_target: Rc::new(Cell::new(None)),
         }
     }
 
+    pub fn view_matrix(&self) -> Matrix4<f32> {
+        self.view_matrix
+    }
+
+    pub fn projection_matrix(&self) -> Matrix4<f32> {
+        self.projection_matrix
+    }
+
+    pub fn model_matrix(&self) -> Matrix4<f32> {
+        self.model_matrix
+    }
+
+    /// Shared handle to the render state cache, to be moved into commands.
+    pub fn gl_state(&self) -> GlStateTracker {
+        self.gl_state.clone()
+    }
+
+    /// The render target being drawn into, `None` for the window.
+    pub fn current_target(&self) -> Option<RenderTargetBinding> {
+        self.current_target
+    }
+
     pub fn add_commands<F>(&mut self, queue: F)
     where
         F: FnOnce() + 'static,
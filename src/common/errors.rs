@@ -14,6 +14,9 @@ pub enum DuendeError {
 pub enum UnsupportedDevice {
     #[error("cursor grab error")]
     CursorGrab,
+
+    #[error("no EGL device available for headless rendering")]
+    HeadlessDevice,
 }
 
 #[derive(thiserror::Error, Debug, Clone)]
@@ -24,9 +27,46 @@ pub enum GlError {
     #[error("program link error: {0}")]
     ProgramLink(String),
 
+    #[error("unable to read shader source: {0}")]
+    ShaderSource(String),
+
+    #[error("shader preprocessing error: {0}")]
+    ShaderPreprocess(String),
+
     #[error("shader code must not contain a null byte")]
     NullByte,
 
     #[error("unable to find variable: {0}")]
     NonexistantVariableName(&'static str),
+
+    #[error("unable to find uniform: {0}")]
+    NonexistantUniformName(&'static str),
+
+    #[error("incomplete framebuffer, status {0:#x}")]
+    IncompleteFramebuffer(u32),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TextureError {
+    #[error("expected {expected} bytes of pixel data, got {actual}")]
+    SizeMismatch { expected: usize, actual: usize },
+
+    #[error("{0:?} is not a float pixel format")]
+    NotFloatFormat(crate::common::texture::PixelFormat),
+
+    #[error("unable to read image: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[cfg(feature = "image")]
+    #[error("unable to decode image: {0}")]
+    Decode(#[from] image::ImageError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum FontError {
+    #[error("invalid font data: {0}")]
+    InvalidFont(#[from] ab_glyph::InvalidFont),
+
+    #[error("unable to read font: {0}")]
+    Io(#[from] std::io::Error),
 }
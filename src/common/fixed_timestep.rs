@@ -0,0 +1,158 @@
+use std::time::Duration;
+
+pub const DEFAULT_UPDATE_RATE: u32 = 60;
+pub const DEFAULT_MAX_STEPS_PER_FRAME: u32 = 8;
+
+/// Accumulates frame time and hands it out in fixed-size steps.
+pub struct FixedTimestep {
+    step: Duration,
+    max_steps_per_frame: u32,
+    accumulator: Duration,
+    last_time: Option<Duration>,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(DEFAULT_UPDATE_RATE)
+    }
+}
+
+impl FixedTimestep {
+    pub fn new(updates_per_second: u32) -> Self {
+        assert!(updates_per_second > 0, "update rate must be non-zero");
+        Self {
+            step: Duration::from_secs(1) / updates_per_second,
+            max_steps_per_frame: DEFAULT_MAX_STEPS_PER_FRAME,
+            accumulator: Duration::ZERO,
+            last_time: None,
+        }
+    }
+
+    pub fn with_max_steps_per_frame(mut self, max_steps_per_frame: u32) -> Self {
+        self.max_steps_per_frame = max_steps_per_frame.max(1);
+        self
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    pub fn step_seconds(&self) -> f32 {
+        self.step.as_secs_f32()
+    }
+
+    /// Feeds the current time and returns how many fixed steps should run this
+    /// frame. The first call only records the starting point. If more than
+    /// `max_steps_per_frame` steps are owed, the excess time is dropped so a
+    /// slow frame cannot snowball into ever slower ones.
+    pub fn advance(&mut self, now: Duration) -> u32 {
+        let Some(last_time) = self.last_time.replace(now) else {
+            return 0;
+        };
+        self.accumulator += now.saturating_sub(last_time);
+        let owed = self.accumulator.as_nanos() / self.step.as_nanos();
+        let steps = owed.min(self.max_steps_per_frame as u128) as u32;
+        self.accumulator -= self.step * steps;
+        if owed > steps as u128 {
+            let remainder = self.accumulator.as_nanos() % self.step.as_nanos();
+            self.accumulator = Duration::from_nanos(remainder as u64);
+        }
+        steps
+    }
+
+    /// How far the simulation is into the next step, in `[0, 1)`. Use it to
+    /// blend between the previous and current fixed-update state when rendering.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator.as_secs_f64() / self.step.as_secs_f64()).min(1.0) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::clock::{Clock, ManualClock};
+
+    fn started(timestep: &mut FixedTimestep, clock: &ManualClock) {
+        assert_eq!(timestep.advance(clock.now()), 0);
+    }
+
+    #[test]
+    fn first_advance_only_records_the_time() {
+        let clock = ManualClock::new();
+        clock.set(Duration::from_secs(10));
+        let mut timestep = FixedTimestep::new(60);
+        started(&mut timestep, &clock);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn advance_runs_one_step_per_elapsed_step() {
+        let clock = ManualClock::new();
+        let mut timestep = FixedTimestep::new(60);
+        started(&mut timestep, &clock);
+        clock.advance(timestep.step());
+        assert_eq!(timestep.advance(clock.now()), 1);
+        clock.advance(timestep.step() * 3);
+        assert_eq!(timestep.advance(clock.now()), 3);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn alpha_is_the_fraction_of_the_next_step() {
+        let clock = ManualClock::new();
+        let mut timestep = FixedTimestep::new(50);
+        started(&mut timestep, &clock);
+        clock.advance(Duration::from_millis(10));
+        assert_eq!(timestep.advance(clock.now()), 0);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-6);
+        clock.advance(Duration::from_millis(25));
+        assert_eq!(timestep.advance(clock.now()), 1);
+        assert!((timestep.alpha() - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn partial_steps_accumulate_until_caught_up() {
+        let clock = ManualClock::new();
+        let mut timestep = FixedTimestep::new(50);
+        started(&mut timestep, &clock);
+        let steps = [30, 30, 30, 30].map(|milliseconds| {
+            clock.advance(Duration::from_millis(milliseconds));
+            timestep.advance(clock.now())
+        });
+        assert_eq!(steps, [1, 2, 1, 2]);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn steps_are_clamped_and_the_excess_dropped() {
+        let clock = ManualClock::new();
+        let mut timestep = FixedTimestep::new(50).with_max_steps_per_frame(4);
+        started(&mut timestep, &clock);
+        clock.advance(Duration::from_millis(1_010));
+        assert_eq!(timestep.advance(clock.now()), 4);
+        // Only the time into the current step is kept.
+        assert!((timestep.alpha() - 0.5).abs() < 1e-6);
+        clock.advance(Duration::from_millis(10));
+        assert_eq!(timestep.advance(clock.now()), 1);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn max_steps_per_frame_is_at_least_one() {
+        let clock = ManualClock::new();
+        let mut timestep = FixedTimestep::new(50).with_max_steps_per_frame(0);
+        started(&mut timestep, &clock);
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(timestep.advance(clock.now()), 1);
+    }
+
+    #[test]
+    fn time_going_backwards_runs_no_steps() {
+        let clock = ManualClock::new();
+        clock.set(Duration::from_secs(1));
+        let mut timestep = FixedTimestep::new(50);
+        started(&mut timestep, &clock);
+        clock.set(Duration::ZERO);
+        assert_eq!(timestep.advance(clock.now()), 0);
+    }
+}
@@ -0,0 +1,372 @@
+use super::{errors::GlError, gl, render_target::RenderTarget, rgba_image::RgbaImage};
+use std::{
+    cell::{OnceCell, RefCell},
+    rc::Rc,
+};
+#[cfg(feature = "image")]
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, SyncSender},
+    thread::JoinHandle,
+};
+#[cfg(feature = "image")]
+use tracing::error;
+
+/// A frame requested with `capture_frame`. It becomes ready once the frame
+/// has been drawn, or one frame later when captures go through a pixel
+/// buffer.
+#[derive(Clone, Debug, Default)]
+pub struct FrameCapture {
+    image: Rc<RefCell<Option<RgbaImage>>>,
+}
+
+impl FrameCapture {
+    pub fn is_ready(&self) -> bool {
+        self.image.borrow().is_some()
+    }
+
+    pub fn take(&self) -> Option<RgbaImage> {
+        self.image.borrow_mut().take()
+    }
+}
+
+/// Records a sequence of frames, e.g. for trailers or bug reports.
+pub struct FrameRecording {
+    sink: RecordingSink,
+    every_nth_frame: u64,
+    max_frames: Option<u64>,
+    seen_frames: u64,
+    recorded_frames: u64,
+}
+
+enum RecordingSink {
+    #[cfg(feature = "image")]
+    Directory(PathBuf),
+    Callback(Box<dyn FnMut(u64, RgbaImage)>),
+}
+
+impl FrameRecording {
+    /// Writes frames as numbered PNG files into `directory`. Encoding
+    /// happens on a background thread.
+    #[cfg(feature = "image")]
+    pub fn to_directory<P>(directory: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self::with_sink(RecordingSink::Directory(directory.into()))
+    }
+
+    /// Hands every recorded frame, with its index in the recording, to
+    /// `callback`.
+    pub fn with_callback<F>(callback: F) -> Self
+    where
+        F: FnMut(u64, RgbaImage) + 'static,
+    {
+        Self::with_sink(RecordingSink::Callback(Box::new(callback)))
+    }
+
+    fn with_sink(sink: RecordingSink) -> Self {
+        Self {
+            sink,
+            every_nth_frame: 1,
+            max_frames: None,
+            seen_frames: 0,
+            recorded_frames: 0,
+        }
+    }
+
+    pub fn every_nth_frame(mut self, every_nth_frame: u64) -> Self {
+        self.every_nth_frame = every_nth_frame.max(1);
+        self
+    }
+
+    /// Stops recording after `max_frames` frames.
+    pub fn max_frames(mut self, max_frames: u64) -> Self {
+        self.max_frames = Some(max_frames);
+        self
+    }
+
+    pub fn recorded_frames(&self) -> u64 {
+        self.recorded_frames
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.max_frames
+            .is_some_and(|max_frames| self.recorded_frames >= max_frames)
+    }
+
+    fn wants_frame(&mut self) -> bool {
+        let wanted = !self.is_finished() && self.seen_frames.is_multiple_of(self.every_nth_frame);
+        self.seen_frames += 1;
+        wanted
+    }
+
+    fn record(&mut self, image: RgbaImage, #[cfg(feature = "image")] saver: &mut ImageSaver) {
+        let index = self.recorded_frames;
+        self.recorded_frames += 1;
+        match &mut self.sink {
+            #[cfg(feature = "image")]
+            RecordingSink::Directory(directory) => {
+                saver.save(image, directory.join(format!("frame_{index:06}.png")))
+            }
+            RecordingSink::Callback(callback) => callback(index, image),
+        }
+    }
+}
+
+enum CaptureSink {
+    Handle(Rc<RefCell<Option<RgbaImage>>>),
+    #[cfg(feature = "image")]
+    File(PathBuf),
+    Recording,
+}
+
+struct PendingReadback {
+    width: u32,
+    height: u32,
+    sinks: Vec<CaptureSink>,
+}
+
+/// Reads frames back after they have been drawn. The framebuffer is first
+/// resolved into a single-sampled target, since multisampled framebuffers
+/// cannot be read directly.
+pub(crate) struct FrameCapturer {
+    use_pixel_buffer: bool,
+    requests: Vec<CaptureSink>,
+    recording: Option<FrameRecording>,
+    resolve_target: Option<RenderTarget>,
+    pixel_buffer: OnceCell<u32>,
+    pending: Option<PendingReadback>,
+    #[cfg(feature = "image")]
+    saver: ImageSaver,
+}
+
+impl FrameCapturer {
+    pub(crate) fn new() -> Self {
+        Self {
+            use_pixel_buffer: false,
+            requests: Vec::new(),
+            recording: None,
+            resolve_target: None,
+            pixel_buffer: OnceCell::new(),
+            pending: None,
+            #[cfg(feature = "image")]
+            saver: ImageSaver::default(),
+        }
+    }
+
+    pub(crate) fn set_use_pixel_buffer(&mut self, use_pixel_buffer: bool) {
+        self.use_pixel_buffer = use_pixel_buffer;
+    }
+
+    pub(crate) fn capture(&mut self) -> FrameCapture {
+        let capture = FrameCapture::default();
+        self.requests
+            .push(CaptureSink::Handle(Rc::clone(&capture.image)));
+        capture
+    }
+
+    #[cfg(feature = "image")]
+    pub(crate) fn capture_to_file(&mut self, path: PathBuf) {
+        self.requests.push(CaptureSink::File(path));
+    }
+
+    pub(crate) fn start_recording(&mut self, recording: FrameRecording) {
+        #[cfg(feature = "image")]
+        if let RecordingSink::Directory(directory) = &recording.sink {
+            if let Err(e) = std::fs::create_dir_all(directory) {
+                error!("Unable to create {}: {e}", directory.display());
+            }
+        }
+        self.recording = Some(recording);
+    }
+
+    pub(crate) fn stop_recording(&mut self) -> Option<FrameRecording> {
+        self.recording.take()
+    }
+
+    pub(crate) fn recording(&self) -> Option<&FrameRecording> {
+        self.recording.as_ref()
+    }
+
+    /// Captures the contents of `framebuffer` for every pending request.
+    pub(crate) unsafe fn capture_framebuffer(
+        &mut self,
+        framebuffer: u32,
+        width: i32,
+        height: i32,
+    ) -> Result<(), GlError> {
+        if let Some(pending) = self.pending.take() {
+            let image = self.map_pixel_buffer(pending.width, pending.height);
+            self.deliver(pending.sinks, image);
+        }
+        let mut sinks = std::mem::take(&mut self.requests);
+        if self
+            .recording
+            .as_mut()
+            .is_some_and(FrameRecording::wants_frame)
+        {
+            sinks.push(CaptureSink::Recording);
+        }
+        if sinks.is_empty() || width <= 0 || height <= 0 {
+            return Ok(());
+        }
+        let (width, height) = (width as u32, height as u32);
+        if !matches!(&self.resolve_target, Some(target) if target.width() == width && target.height() == height)
+        {
+            self.resolve_target = Some(RenderTarget::new(width, height).with_depth(false));
+        }
+        let resolve_target = self.resolve_target.as_ref().unwrap();
+        let resolve_framebuffer = resolve_target.get_framebuffer_ref()?;
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, framebuffer);
+        gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, resolve_framebuffer);
+        gl::BlitFramebuffer(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            0,
+            0,
+            width as i32,
+            height as i32,
+            gl::COLOR_BUFFER_BIT,
+            gl::NEAREST,
+        );
+        if self.use_pixel_buffer {
+            let pixel_buffer = *self.pixel_buffer.get_or_init(|| {
+                let mut pixel_buffer = 0;
+                gl::GenBuffers(1, &mut pixel_buffer);
+                pixel_buffer
+            });
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, resolve_framebuffer);
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, pixel_buffer);
+            gl::BufferData(
+                gl::PIXEL_PACK_BUFFER,
+                (width * height * 4) as isize,
+                std::ptr::null(),
+                gl::STREAM_READ,
+            );
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null_mut(),
+            );
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+            self.pending = Some(PendingReadback {
+                width,
+                height,
+                sinks,
+            });
+        } else {
+            let image = resolve_target.read_pixels()?;
+            self.deliver(sinks, image);
+        }
+        gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+        Ok(())
+    }
+
+    unsafe fn map_pixel_buffer(&self, width: u32, height: u32) -> RgbaImage {
+        let mut image = RgbaImage::new(width, height);
+        if let Some(pixel_buffer) = self.pixel_buffer.get() {
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, *pixel_buffer);
+            let data = gl::MapBuffer(gl::PIXEL_PACK_BUFFER, gl::READ_ONLY) as *const u8;
+            if !data.is_null() {
+                let pixels = image.pixels_mut();
+                pixels.copy_from_slice(std::slice::from_raw_parts(data, pixels.len()));
+                gl::UnmapBuffer(gl::PIXEL_PACK_BUFFER);
+            }
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+        }
+        image.flip_vertically();
+        image
+    }
+
+    fn deliver(&mut self, sinks: Vec<CaptureSink>, image: RgbaImage) {
+        for sink in sinks {
+            match sink {
+                CaptureSink::Handle(slot) => *slot.borrow_mut() = Some(image.clone()),
+                #[cfg(feature = "image")]
+                CaptureSink::File(path) => self.saver.save(image.clone(), path),
+                CaptureSink::Recording => {
+                    if let Some(recording) = self.recording.as_mut() {
+                        recording.record(
+                            image.clone(),
+                            #[cfg(feature = "image")]
+                            &mut self.saver,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for FrameCapturer {
+    /// A readback still waiting in the pixel buffer is delivered before the
+    /// buffer goes away. Dropping the saver afterwards waits for queued
+    /// files to be written.
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(pending) = self.pending.take() {
+                let image = self.map_pixel_buffer(pending.width, pending.height);
+                self.deliver(pending.sinks, image);
+            }
+            if let Some(pixel_buffer) = self.pixel_buffer.get() {
+                gl::DeleteBuffers(1, pixel_buffer);
+            }
+        }
+    }
+}
+
+/// Frames queued for saving before the capturer blocks on the worker.
+#[cfg(feature = "image")]
+const SAVE_QUEUE_LENGTH: usize = 8;
+
+/// Encodes and writes PNG files on a single worker thread, started on first
+/// use. Dropping the saver waits for every queued file to be written.
+#[cfg(feature = "image")]
+#[derive(Default)]
+struct ImageSaver {
+    worker: Option<(SyncSender<SaveRequest>, JoinHandle<()>)>,
+}
+
+#[cfg(feature = "image")]
+type SaveRequest = (RgbaImage, PathBuf);
+
+#[cfg(feature = "image")]
+impl ImageSaver {
+    fn save(&mut self, image: RgbaImage, path: PathBuf) {
+        let (sender, _) = self.worker.get_or_insert_with(|| {
+            let (sender, receiver) = mpsc::sync_channel::<SaveRequest>(SAVE_QUEUE_LENGTH);
+            let handle = std::thread::spawn(move || {
+                for (image, path) in receiver {
+                    if let Err(e) = image.save_png(&path) {
+                        error!("Unable to save {}: {e}", path.display());
+                    }
+                }
+            });
+            (sender, handle)
+        });
+        if sender.send((image, path)).is_err() {
+            error!("The image saving thread has stopped");
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+impl Drop for ImageSaver {
+    fn drop(&mut self) {
+        if let Some((sender, handle)) = self.worker.take() {
+            drop(sender);
+            if handle.join().is_err() {
+                error!("The image saving thread panicked");
+            }
+        }
+    }
+}
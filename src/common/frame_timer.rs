@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+const FPS_SMOOTHING: f32 = 0.1;
+
+#[derive(Default)]
+pub struct FrameTimer {
+    start_time: Option<Duration>,
+    last_time: Duration,
+    delta_time: Duration,
+    elapsed: Duration,
+    frame_index: u64,
+    fps: f32,
+}
+
+impl FrameTimer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks the start of a new frame at `now`, as read from a
+    /// [`Clock`](super::clock::Clock).
+    pub fn tick(&mut self, now: Duration) {
+        let Some(start_time) = self.start_time else {
+            self.start_time = Some(now);
+            self.last_time = now;
+            return;
+        };
+        self.delta_time = now.saturating_sub(self.last_time);
+        self.elapsed = now.saturating_sub(start_time);
+        self.last_time = now;
+        self.frame_index += 1;
+        let delta_seconds = self.delta_time.as_secs_f32();
+        if delta_seconds > 0.0 {
+            let instant_fps = 1.0 / delta_seconds;
+            self.fps = if self.fps == 0.0 {
+                instant_fps
+            } else {
+                self.fps + (instant_fps - self.fps) * FPS_SMOOTHING
+            };
+        }
+    }
+
+    pub fn delta_time(&self) -> Duration {
+        self.delta_time
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn frame_index(&self) -> u64 {
+        self.frame_index
+    }
+
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::clock::{Clock, ManualClock};
+
+    fn tick_after(timer: &mut FrameTimer, clock: &ManualClock, milliseconds: u64) {
+        clock.advance(Duration::from_millis(milliseconds));
+        timer.tick(clock.now());
+    }
+
+    #[test]
+    fn first_tick_starts_the_timer() {
+        let clock = ManualClock::new();
+        clock.set(Duration::from_secs(5));
+        let mut timer = FrameTimer::new();
+        timer.tick(clock.now());
+        assert_eq!(timer.delta_time(), Duration::ZERO);
+        assert_eq!(timer.elapsed(), Duration::ZERO);
+        assert_eq!(timer.frame_index(), 0);
+        assert_eq!(timer.fps(), 0.0);
+    }
+
+    #[test]
+    fn delta_elapsed_and_frame_index_follow_the_clock() {
+        let clock = ManualClock::new();
+        clock.set(Duration::from_secs(5));
+        let mut timer = FrameTimer::new();
+        timer.tick(clock.now());
+        tick_after(&mut timer, &clock, 16);
+        assert_eq!(timer.delta_time(), Duration::from_millis(16));
+        assert_eq!(timer.elapsed(), Duration::from_millis(16));
+        assert_eq!(timer.frame_index(), 1);
+        tick_after(&mut timer, &clock, 34);
+        assert_eq!(timer.delta_time(), Duration::from_millis(34));
+        assert_eq!(timer.elapsed(), Duration::from_millis(50));
+        assert_eq!(timer.frame_index(), 2);
+    }
+
+    #[test]
+    fn fps_starts_at_the_first_frame_rate_then_smooths() {
+        let clock = ManualClock::new();
+        let mut timer = FrameTimer::new();
+        timer.tick(clock.now());
+        tick_after(&mut timer, &clock, 10);
+        assert!((timer.fps() - 100.0).abs() < 1e-3);
+        tick_after(&mut timer, &clock, 20);
+        // 100 + (50 - 100) * 0.1
+        assert!((timer.fps() - 95.0).abs() < 1e-3);
+        for _ in 0..200 {
+            tick_after(&mut timer, &clock, 20);
+        }
+        assert!((timer.fps() - 50.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn zero_length_frames_keep_the_fps() {
+        let clock = ManualClock::new();
+        let mut timer = FrameTimer::new();
+        timer.tick(clock.now());
+        tick_after(&mut timer, &clock, 10);
+        tick_after(&mut timer, &clock, 0);
+        assert_eq!(timer.delta_time(), Duration::ZERO);
+        assert_eq!(timer.frame_index(), 2);
+        assert!((timer.fps() - 100.0).abs() < 1e-3);
+    }
+}
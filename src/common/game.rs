@@ -1,7 +1,17 @@
-use crate::three_d::three_d_application_context::ThreeDApplicationContext;
+use super::application_context::ApplicationContext;
+use crate::internal::context_backend::ContextBackend;
 
+/// A game, driven by the loop through the context it declares: either
+/// [`ThreeDApplicationContext`] or [`TwoDApplicationContext`].
+///
+/// [`ThreeDApplicationContext`]: crate::three_d::three_d_application_context::ThreeDApplicationContext
+/// [`TwoDApplicationContext`]: crate::two_d::two_d_application_context::TwoDApplicationContext
 pub trait Game {
-    fn game_loop(&mut self, context: &mut ThreeDApplicationContext);
-    fn setup(&mut self, _context: &mut ThreeDApplicationContext) {}
-    fn teardown(&mut self, _context: &mut ThreeDApplicationContext) {}
+    type Context<'a>: ApplicationContext + ContextBackend<'a>;
+
+    fn game_loop(&mut self, context: &mut Self::Context<'_>);
+    fn setup(&mut self, _context: &mut Self::Context<'_>) {}
+    fn teardown(&mut self, _context: &mut Self::Context<'_>) {}
+    fn fixed_update(&mut self, _context: &mut Self::Context<'_>, _dt: f32) {}
+    fn render(&mut self, _context: &mut Self::Context<'_>, _alpha: f32) {}
 }
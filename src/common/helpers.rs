@@ -2,45 +2,243 @@ use super::{
     errors::GlError,
     gl::{
         self,
-        types::{GLchar, GLint},
+        types::{GLchar, GLenum, GLint},
     },
+    shader_preprocessor::{preprocess, PreprocessedSource, ShaderLibrary},
 };
+use glutin::prelude::GlDisplay;
 use std::{
-    ffi::CString,
+    borrow::Cow,
+    ffi::{CStr, CString},
     sync::{
-        atomic::{AtomicU32, Ordering},
-        Arc, LazyLock,
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        Arc, LazyLock, Mutex,
     },
+    time::{Duration, Instant, SystemTime},
 };
+use tracing::{error, info};
+
+static HOT_RELOAD: AtomicBool = AtomicBool::new(false);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Enables watching the files of shaders that have a path, see
+/// [`Shader::with_path`]. Meant for development.
+pub fn set_shader_hot_reload(enabled: bool) {
+    HOT_RELOAD.store(enabled, Ordering::Relaxed);
+}
+
+pub fn shader_hot_reload() -> bool {
+    HOT_RELOAD.load(Ordering::Relaxed)
+}
+
+/// Loads the OpenGL functions of `gl_display` and logs what is running them.
+pub(crate) fn load_gl<D>(gl_display: &D)
+where
+    D: GlDisplay,
+{
+    gl::load_with(|symbol| {
+        let symbol = CString::new(symbol).unwrap();
+        gl_display.get_proc_address(symbol.as_c_str()).cast()
+    });
+    if let Some(renderer) = get_gl_string(gl::RENDERER) {
+        info!("Running on {}", renderer.to_string_lossy());
+    }
+    if let Some(version) = get_gl_string(gl::VERSION) {
+        info!("OpenGL Version {}", version.to_string_lossy());
+    }
+    if let Some(shaders_version) = get_gl_string(gl::SHADING_LANGUAGE_VERSION) {
+        info!("Shaders version on {}", shaders_version.to_string_lossy());
+    }
+}
+
+fn get_gl_string(variant: GLenum) -> Option<&'static CStr> {
+    unsafe {
+        let s = gl::GetString(variant);
+        (!s.is_null()).then(|| CStr::from_ptr(s.cast()))
+    }
+}
 
 pub struct Fragment;
 
 pub struct Vertex;
 
 pub struct Shader<T> {
-    source: &'static str,
+    source: Option<&'static str>,
+    path: Option<&'static str>,
+    library: Option<&'static ShaderLibrary>,
+    defines: &'static [(&'static str, &'static str)],
+    file: Mutex<Option<ShaderFile>>,
+    dependencies: Mutex<Vec<(String, Option<SystemTime>)>>,
+    polled_at: Mutex<Option<Instant>>,
+    generation: AtomicU64,
     instance: LazyLock<Arc<AtomicU32>>,
     shader_type: std::marker::PhantomData<T>,
 }
 
-impl<T> Shader<T> {
-    pub fn get_source(&self) -> &'static str {
-        self.source
-    }
+struct ShaderFile {
+    source: String,
+    modified: Option<SystemTime>,
 }
 
-impl Shader<Fragment> {
-    pub const fn create_fragment_shader(source: &'static str) -> Self {
+impl<T> Shader<T> {
+    const fn new(source: Option<&'static str>, path: Option<&'static str>) -> Self {
         Self {
             source,
+            path,
+            library: None,
+            defines: &[],
+            file: Mutex::new(None),
+            dependencies: Mutex::new(Vec::new()),
+            polled_at: Mutex::new(None),
+            generation: AtomicU64::new(0),
             instance: LazyLock::new(|| Arc::new(AtomicU32::new(0))),
             shader_type: std::marker::PhantomData,
         }
     }
 
-    pub unsafe fn get_shader_handle(&self) -> Result<ShaderHandle<Fragment>, GlError> {
+    /// Associates the file an embedded shader was built from. While hot
+    /// reload is enabled, the file replaces the embedded source as soon as it
+    /// changes. Relative paths are resolved from the working directory, so
+    /// `concat!(env!("CARGO_MANIFEST_DIR"), "/...")` is usually what you want.
+    pub const fn with_path(mut self, path: &'static str) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    /// Resolves `#include` directives through `library`.
+    pub const fn with_library(mut self, library: &'static ShaderLibrary) -> Self {
+        self.library = Some(library);
+        self
+    }
+
+    /// Injects a `#define` per pair after the `#version` directive. Declaring
+    /// the same source with different defines builds permutation variants.
+    pub const fn with_defines(mut self, defines: &'static [(&'static str, &'static str)]) -> Self {
+        self.defines = defines;
+        self
+    }
+
+    /// The source as written, before preprocessing.
+    pub fn get_source(&self) -> Result<Cow<'static, str>, GlError> {
+        if let Some(file) = self.file.lock().unwrap().as_ref() {
+            return Ok(Cow::Owned(file.source.clone()));
+        }
+        match (self.source, self.path) {
+            (Some(source), _) => Ok(Cow::Borrowed(source)),
+            (None, Some(path)) => {
+                let source = read_shader_file(path)?;
+                *self.file.lock().unwrap() = Some(ShaderFile {
+                    source: source.clone(),
+                    modified: modified_time(path),
+                });
+                Ok(Cow::Owned(source))
+            }
+            (None, None) => unreachable!("shaders have a source or a path"),
+        }
+    }
+
+    /// The source with includes resolved and defines injected, as handed to
+    /// the driver.
+    pub fn preprocessed_source(&self) -> Result<PreprocessedSource, GlError> {
+        let source = self.get_source()?;
+        let preprocessed = preprocess(
+            &source,
+            self.path.unwrap_or("shader"),
+            self.library,
+            self.defines,
+        )?;
+        *self.dependencies.lock().unwrap() = preprocessed
+            .dependencies()
+            .iter()
+            .map(|path| (path.clone(), modified_time(path)))
+            .collect();
+        Ok(preprocessed)
+    }
+
+    pub fn path(&self) -> Option<&'static str> {
+        self.path
+    }
+
+    /// Incremented every time the source or one of its includes changes on
+    /// disk.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// Checks, at most a few times per second, whether the shader file or
+    /// the files it includes changed and picks up the new contents. Does
+    /// nothing unless hot reload is enabled. Returns `true` when something
+    /// changed.
+    pub fn poll_changes(&self) -> bool {
+        if !shader_hot_reload() {
+            return false;
+        }
+        {
+            let now = Instant::now();
+            let mut polled_at = self.polled_at.lock().unwrap();
+            if polled_at.is_some_and(|polled_at| now.duration_since(polled_at) < POLL_INTERVAL) {
+                return false;
+            }
+            *polled_at = Some(now);
+        }
+        let changed = self.poll_file() | self.poll_dependencies();
+        if changed {
+            self.generation.fetch_add(1, Ordering::Relaxed);
+            info!(
+                "Reloading shader {}",
+                self.path.unwrap_or("with changed includes")
+            );
+        }
+        changed
+    }
+
+    fn poll_file(&self) -> bool {
+        let Some(path) = self.path else {
+            return false;
+        };
+        let mut file = self.file.lock().unwrap();
+        let modified = modified_time(path);
+        match file.as_ref() {
+            Some(file) if file.modified == modified => return false,
+            // Embedded shaders are only replaced once their file changes.
+            None if self.source.is_some() => {
+                *file = Some(ShaderFile {
+                    source: self.source.unwrap_or_default().to_string(),
+                    modified,
+                });
+                return false;
+            }
+            _ => {}
+        }
+        let source = match read_shader_file(path) {
+            Ok(source) => source,
+            Err(e) => {
+                error!("{e}");
+                return false;
+            }
+        };
+        let changed = file.as_ref().is_none_or(|file| file.source != source);
+        *file = Some(ShaderFile { source, modified });
+        changed
+    }
+
+    fn poll_dependencies(&self) -> bool {
+        self.dependencies
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(path, modified)| modified_time(path) != *modified)
+    }
+
+    unsafe fn get_handle<U>(&self, shader_type: GLenum) -> Result<ShaderHandle<U>, GlError> {
         if Arc::strong_count(&self.instance) == 1 {
-            let shader_id = compile_shader(gl::FRAGMENT_SHADER, self.source.as_bytes())?;
+            let source = self.preprocessed_source()?;
+            let shader_id =
+                compile_shader(shader_type, source.text().as_bytes()).map_err(|e| match e {
+                    GlError::ShaderCompile(log) => GlError::ShaderCompile(source.rewrite_log(&log)),
+                    e => e,
+                })?;
             self.instance.store(shader_id, Ordering::Relaxed);
         }
         Ok(ShaderHandle {
@@ -50,27 +248,60 @@ impl Shader<Fragment> {
     }
 }
 
+impl Shader<Fragment> {
+    pub const fn create_fragment_shader(source: &'static str) -> Self {
+        Self::new(Some(source), None)
+    }
+
+    /// A shader read from `path` on first use instead of being embedded.
+    pub const fn load_fragment_shader(path: &'static str) -> Self {
+        Self::new(None, Some(path))
+    }
+
+    /// Compiles the current source, unless a handle from an earlier call is
+    /// still alive, in which case that shader is shared.
+    ///
+    /// # Safety
+    ///
+    /// A GL context must be current. Handles are shared between programs, so
+    /// every program using this shader must live in that same context.
+    pub unsafe fn get_shader_handle(&self) -> Result<ShaderHandle<Fragment>, GlError> {
+        self.get_handle(gl::FRAGMENT_SHADER)
+    }
+}
+
 impl Shader<Vertex> {
     pub const fn create_vertex_shader(source: &'static str) -> Self {
-        Self {
-            source,
-            instance: LazyLock::new(|| Arc::new(AtomicU32::new(0))),
-            shader_type: std::marker::PhantomData,
-        }
+        Self::new(Some(source), None)
+    }
+
+    /// A shader read from `path` on first use instead of being embedded.
+    pub const fn load_vertex_shader(path: &'static str) -> Self {
+        Self::new(None, Some(path))
     }
 
+    /// Compiles the current source, unless a handle from an earlier call is
+    /// still alive, in which case that shader is shared.
+    ///
+    /// # Safety
+    ///
+    /// A GL context must be current. Handles are shared between programs, so
+    /// every program using this shader must live in that same context.
     pub unsafe fn get_shader_handle(&self) -> Result<ShaderHandle<Vertex>, GlError> {
-        if Arc::strong_count(&self.instance) == 1 {
-            let shader_id = compile_shader(gl::VERTEX_SHADER, self.source.as_bytes())?;
-            self.instance.store(shader_id, Ordering::Relaxed);
-        }
-        Ok(ShaderHandle {
-            shader_id: Arc::clone(&self.instance),
-            shader_type: std::marker::PhantomData,
-        })
+        self.get_handle(gl::VERTEX_SHADER)
     }
 }
 
+fn read_shader_file(path: &str) -> Result<String, GlError> {
+    std::fs::read_to_string(path).map_err(|e| GlError::ShaderSource(format!("{path}: {e}")))
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
 #[derive(Clone)]
 pub struct ShaderHandle<T> {
     shader_id: Arc<AtomicU32>,
@@ -93,6 +324,12 @@ impl<T> Drop for ShaderHandle<T> {
     }
 }
 
+/// Compiles `source` as a shader of type `shader`, returning the compile log
+/// as the error if that fails.
+///
+/// # Safety
+///
+/// A GL context must be current.
 pub unsafe fn compile_shader(
     shader: gl::types::GLenum,
     source: &[u8],
@@ -123,6 +360,11 @@ pub unsafe fn compile_shader(
     Ok(shader)
 }
 
+/// Links the two shaders into a new program.
+///
+/// # Safety
+///
+/// The GL context the shaders were compiled in must be current.
 pub unsafe fn create_program(
     vertex_shader: &ShaderHandle<Vertex>,
     fragment_shader: &ShaderHandle<Fragment>,
@@ -154,3 +396,127 @@ pub unsafe fn create_program(
     }
     Ok(program_id)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs::File, path::PathBuf};
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("duende-helpers-{name}-{}", std::process::id()));
+            std::fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn leak_path(&self, file: &str) -> &'static str {
+            Box::leak(
+                self.0
+                    .join(file)
+                    .to_string_lossy()
+                    .into_owned()
+                    .into_boxed_str(),
+            )
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Writes `contents` with a modification time `seconds` past the epoch,
+    /// so changes are seen whatever the file system's time resolution.
+    fn write(path: &str, contents: &str, seconds: u64) {
+        std::fs::write(path, contents).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+            .unwrap();
+    }
+
+    /// Polls without waiting for the poll interval.
+    fn poll<T>(shader: &Shader<T>) -> bool {
+        *shader.polled_at.lock().unwrap() = None;
+        shader.poll_changes()
+    }
+
+    #[test]
+    fn file_changes_bump_the_generation() {
+        set_shader_hot_reload(true);
+        let directory = TempDir::new("file");
+        let path = directory.leak_path("shader.frag");
+        write(path, "void main() {}\n", 1);
+        let shader = Shader::load_fragment_shader(path);
+        assert_eq!(shader.get_source().unwrap(), "void main() {}\n");
+        assert!(!poll(&shader));
+        assert_eq!(shader.generation(), 0);
+
+        write(path, "void main() { discard; }\n", 2);
+        assert!(poll(&shader));
+        assert_eq!(shader.generation(), 1);
+        assert_eq!(shader.get_source().unwrap(), "void main() { discard; }\n");
+
+        // Touching the file without changing it does not reload it.
+        write(path, "void main() { discard; }\n", 3);
+        assert!(!poll(&shader));
+        assert_eq!(shader.generation(), 1);
+    }
+
+    #[test]
+    fn embedded_sources_are_replaced_once_their_file_changes() {
+        set_shader_hot_reload(true);
+        let directory = TempDir::new("embedded");
+        let path = directory.leak_path("shader.vert");
+        write(path, "on disk\n", 1);
+        let shader = Shader::create_vertex_shader("embedded\n").with_path(path);
+        assert!(!poll(&shader));
+        assert_eq!(shader.get_source().unwrap(), "embedded\n");
+
+        write(path, "edited\n", 2);
+        assert!(poll(&shader));
+        assert_eq!(shader.generation(), 1);
+        assert_eq!(shader.get_source().unwrap(), "edited\n");
+    }
+
+    #[test]
+    fn include_changes_bump_the_generation() {
+        set_shader_hot_reload(true);
+        let directory = TempDir::new("include");
+        let include = directory.leak_path("common.glsl");
+        write(include, "float shade() { return 1.0; }\n", 1);
+        let library: &'static ShaderLibrary =
+            Box::leak(Box::new(ShaderLibrary::Directory(directory.leak_path(""))));
+        let shader =
+            Shader::create_fragment_shader("#include \"common.glsl\"\n").with_library(library);
+        let source = shader.preprocessed_source().unwrap();
+        assert_eq!(source.dependencies(), [include]);
+        assert!(!poll(&shader));
+
+        write(include, "float shade() { return 0.5; }\n", 2);
+        assert!(poll(&shader));
+        assert_eq!(shader.generation(), 1);
+        assert!(shader.preprocessed_source().unwrap().text().contains("0.5"));
+        assert!(!poll(&shader));
+    }
+
+    #[test]
+    fn polling_is_throttled() {
+        set_shader_hot_reload(true);
+        let directory = TempDir::new("throttle");
+        let path = directory.leak_path("shader.frag");
+        write(path, "first\n", 1);
+        let shader = Shader::load_fragment_shader(path);
+        shader.get_source().unwrap();
+        assert!(!poll(&shader));
+        write(path, "second\n", 2);
+        assert!(!shader.poll_changes());
+        assert!(poll(&shader));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod gamepad;
+pub mod input_map;
+pub mod keyboard;
+pub mod mouse;
@@ -0,0 +1,399 @@
+#[cfg(feature = "gilrs")]
+pub mod gilrs_backend;
+
+use fnv::{FnvHashMap, FnvHashSet};
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+const DEFAULT_DEADZONE: f32 = 0.15;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct GamepadId(pub usize);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    Mode,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum GamepadEvent {
+    Connected {
+        id: GamepadId,
+        name: String,
+    },
+    Disconnected {
+        id: GamepadId,
+    },
+    ButtonPressed {
+        id: GamepadId,
+        button: GamepadButton,
+    },
+    ButtonReleased {
+        id: GamepadId,
+        button: GamepadButton,
+    },
+    AxisChanged {
+        id: GamepadId,
+        axis: GamepadAxis,
+        value: f32,
+    },
+}
+
+/// Source of gamepad events. The engine polls it once per frame.
+pub trait GamepadBackend {
+    fn poll_events(&mut self, events: &mut Vec<GamepadEvent>);
+}
+
+/// Backend used when no real one is available; it never reports anything.
+pub struct NullGamepadBackend;
+
+impl GamepadBackend for NullGamepadBackend {
+    fn poll_events(&mut self, _events: &mut Vec<GamepadEvent>) {}
+}
+
+/// Backend that replays events queued by the caller, one batch per poll.
+/// Clones share the same queue, so a test can keep feeding the backend after
+/// handing it to the engine.
+#[derive(Clone, Default)]
+pub struct ScriptedGamepadBackend {
+    frames: Rc<RefCell<VecDeque<Vec<GamepadEvent>>>>,
+}
+
+impl ScriptedGamepadBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_frame<I>(&self, events: I)
+    where
+        I: IntoIterator<Item = GamepadEvent>,
+    {
+        self.frames
+            .borrow_mut()
+            .push_back(events.into_iter().collect());
+    }
+}
+
+impl GamepadBackend for ScriptedGamepadBackend {
+    fn poll_events(&mut self, events: &mut Vec<GamepadEvent>) {
+        if let Some(frame) = self.frames.borrow_mut().pop_front() {
+            events.extend(frame);
+        }
+    }
+}
+
+pub(crate) fn default_backend() -> Box<dyn GamepadBackend> {
+    #[cfg(feature = "gilrs")]
+    match gilrs_backend::GilrsBackend::new() {
+        Ok(backend) => return Box::new(backend),
+        Err(e) => tracing::error!("Gamepad support unavailable: {e}"),
+    }
+    Box::new(NullGamepadBackend)
+}
+
+pub struct Gamepad {
+    name: String,
+    down: FnvHashSet<GamepadButton>,
+    just_pressed: FnvHashSet<GamepadButton>,
+    just_released: FnvHashSet<GamepadButton>,
+    axes: FnvHashMap<GamepadAxis, f32>,
+}
+
+impl Gamepad {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            down: FnvHashSet::default(),
+            just_pressed: FnvHashSet::default(),
+            just_released: FnvHashSet::default(),
+            axes: FnvHashMap::default(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_button_down(&self, button: GamepadButton) -> bool {
+        self.down.contains(&button)
+    }
+
+    pub fn is_button_just_pressed(&self, button: GamepadButton) -> bool {
+        self.just_pressed.contains(&button)
+    }
+
+    pub fn is_button_just_released(&self, button: GamepadButton) -> bool {
+        self.just_released.contains(&button)
+    }
+
+    /// Axis value as reported by the backend, without any deadzone.
+    pub fn raw_axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or(0.0)
+    }
+}
+
+pub struct GamepadState {
+    gamepads: FnvHashMap<GamepadId, Gamepad>,
+    events: Vec<GamepadEvent>,
+    deadzone: f32,
+}
+
+impl Default for GamepadState {
+    fn default() -> Self {
+        Self {
+            gamepads: FnvHashMap::default(),
+            events: Vec::new(),
+            deadzone: DEFAULT_DEADZONE,
+        }
+    }
+}
+
+impl GamepadState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn poll(&mut self, backend: &mut dyn GamepadBackend) {
+        let mut events = Vec::new();
+        backend.poll_events(&mut events);
+        for event in events {
+            self.process(event);
+        }
+    }
+
+    pub fn process(&mut self, event: GamepadEvent) {
+        match &event {
+            GamepadEvent::Connected { id, name } => {
+                self.gamepads.insert(*id, Gamepad::new(name.clone()));
+            }
+            GamepadEvent::Disconnected { id } => {
+                self.gamepads.remove(id);
+            }
+            GamepadEvent::ButtonPressed { id, button } => {
+                if let Some(gamepad) = self.gamepads.get_mut(id) {
+                    if gamepad.down.insert(*button) {
+                        gamepad.just_pressed.insert(*button);
+                    }
+                }
+            }
+            GamepadEvent::ButtonReleased { id, button } => {
+                if let Some(gamepad) = self.gamepads.get_mut(id) {
+                    if gamepad.down.remove(button) {
+                        gamepad.just_released.insert(*button);
+                    }
+                }
+            }
+            GamepadEvent::AxisChanged { id, axis, value } => {
+                if let Some(gamepad) = self.gamepads.get_mut(id) {
+                    gamepad.axes.insert(*axis, value.clamp(-1.0, 1.0));
+                }
+            }
+        }
+        self.events.push(event);
+    }
+
+    pub fn set_deadzone(&mut self, deadzone: f32) {
+        self.deadzone = deadzone.clamp(0.0, 0.99);
+    }
+
+    pub fn deadzone(&self) -> f32 {
+        self.deadzone
+    }
+
+    pub fn connected(&self) -> impl Iterator<Item = (GamepadId, &Gamepad)> {
+        self.gamepads.iter().map(|(id, gamepad)| (*id, gamepad))
+    }
+
+    pub fn gamepad(&self, id: GamepadId) -> Option<&Gamepad> {
+        self.gamepads.get(&id)
+    }
+
+    /// Every event received this frame, including connects and disconnects.
+    pub fn events(&self) -> &[GamepadEvent] {
+        &self.events
+    }
+
+    pub fn is_button_down(&self, id: GamepadId, button: GamepadButton) -> bool {
+        self.gamepad(id)
+            .is_some_and(|gamepad| gamepad.is_button_down(button))
+    }
+
+    pub fn is_button_just_pressed(&self, id: GamepadId, button: GamepadButton) -> bool {
+        self.gamepad(id)
+            .is_some_and(|gamepad| gamepad.is_button_just_pressed(button))
+    }
+
+    pub fn is_button_just_released(&self, id: GamepadId, button: GamepadButton) -> bool {
+        self.gamepad(id)
+            .is_some_and(|gamepad| gamepad.is_button_just_released(button))
+    }
+
+    /// Axis value with the deadzone applied. Values inside the deadzone read
+    /// as zero and the rest is rescaled so the output still spans `[-1, 1]`.
+    pub fn axis(&self, id: GamepadId, axis: GamepadAxis) -> f32 {
+        let value = self
+            .gamepad(id)
+            .map_or(0.0, |gamepad| gamepad.raw_axis(axis));
+        if value.abs() < self.deadzone {
+            0.0
+        } else {
+            value.signum() * (value.abs() - self.deadzone) / (1.0 - self.deadzone)
+        }
+    }
+
+    pub fn end_frame(&mut self) {
+        self.events.clear();
+        for gamepad in self.gamepads.values_mut() {
+            gamepad.just_pressed.clear();
+            gamepad.just_released.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAD: GamepadId = GamepadId(0);
+
+    fn connected(backend: &ScriptedGamepadBackend) -> GamepadState {
+        let mut state = GamepadState::new();
+        backend.push_frame([GamepadEvent::Connected {
+            id: PAD,
+            name: String::from("Test pad"),
+        }]);
+        state.poll(&mut backend.clone());
+        state.end_frame();
+        state
+    }
+
+    fn axis_changed(value: f32) -> GamepadEvent {
+        GamepadEvent::AxisChanged {
+            id: PAD,
+            axis: GamepadAxis::LeftStickX,
+            value,
+        }
+    }
+
+    #[test]
+    fn buttons_are_just_pressed_and_released_for_one_frame() {
+        let mut backend = ScriptedGamepadBackend::new();
+        let mut state = connected(&backend);
+        let south = GamepadButton::South;
+        backend.push_frame([GamepadEvent::ButtonPressed {
+            id: PAD,
+            button: south,
+        }]);
+        backend.push_frame([]);
+        backend.push_frame([GamepadEvent::ButtonReleased {
+            id: PAD,
+            button: south,
+        }]);
+        backend.push_frame([]);
+
+        state.poll(&mut backend);
+        assert!(state.is_button_down(PAD, south));
+        assert!(state.is_button_just_pressed(PAD, south));
+        assert_eq!(state.events().len(), 1);
+        state.end_frame();
+
+        state.poll(&mut backend);
+        assert!(state.is_button_down(PAD, south));
+        assert!(!state.is_button_just_pressed(PAD, south));
+        assert!(state.events().is_empty());
+        state.end_frame();
+
+        state.poll(&mut backend);
+        assert!(!state.is_button_down(PAD, south));
+        assert!(state.is_button_just_released(PAD, south));
+        state.end_frame();
+
+        state.poll(&mut backend);
+        assert!(!state.is_button_just_released(PAD, south));
+    }
+
+    #[test]
+    fn repeated_presses_do_not_fire_again() {
+        let mut backend = ScriptedGamepadBackend::new();
+        let mut state = connected(&backend);
+        let press = GamepadEvent::ButtonPressed {
+            id: PAD,
+            button: GamepadButton::Start,
+        };
+        backend.push_frame([press.clone()]);
+        backend.push_frame([press]);
+        state.poll(&mut backend);
+        state.end_frame();
+        state.poll(&mut backend);
+        assert!(state.is_button_down(PAD, GamepadButton::Start));
+        assert!(!state.is_button_just_pressed(PAD, GamepadButton::Start));
+    }
+
+    #[test]
+    fn deadzone_zeroes_small_values_and_rescales_the_rest() {
+        let mut state = connected(&ScriptedGamepadBackend::new());
+        state.set_deadzone(0.2);
+        state.process(axis_changed(0.1));
+        assert_eq!(state.axis(PAD, GamepadAxis::LeftStickX), 0.0);
+        assert_eq!(
+            state
+                .gamepad(PAD)
+                .unwrap()
+                .raw_axis(GamepadAxis::LeftStickX),
+            0.1
+        );
+        state.process(axis_changed(0.6));
+        assert!((state.axis(PAD, GamepadAxis::LeftStickX) - 0.5).abs() < 1e-6);
+        state.process(axis_changed(-1.0));
+        assert_eq!(state.axis(PAD, GamepadAxis::LeftStickX), -1.0);
+        // Out of range values are clamped before the deadzone is applied.
+        state.process(axis_changed(1.5));
+        assert_eq!(state.axis(PAD, GamepadAxis::LeftStickX), 1.0);
+    }
+
+    #[test]
+    fn events_for_unknown_gamepads_are_ignored() {
+        let mut backend = ScriptedGamepadBackend::new();
+        let mut state = connected(&backend);
+        let other = GamepadId(1);
+        backend.push_frame([
+            GamepadEvent::ButtonPressed {
+                id: other,
+                button: GamepadButton::South,
+            },
+            GamepadEvent::Disconnected { id: PAD },
+        ]);
+        state.poll(&mut backend);
+        assert!(!state.is_button_down(other, GamepadButton::South));
+        assert!(state.gamepad(PAD).is_none());
+        assert_eq!(state.connected().count(), 0);
+        assert_eq!(state.axis(PAD, GamepadAxis::LeftStickX), 0.0);
+    }
+}
@@ -0,0 +1,105 @@
+use super::{GamepadAxis, GamepadBackend, GamepadButton, GamepadEvent, GamepadId};
+use gilrs::{Axis, Button, EventType, Gilrs};
+
+pub struct GilrsBackend {
+    gilrs: Gilrs,
+    announced_initial: bool,
+}
+
+impl GilrsBackend {
+    pub fn new() -> Result<Self, gilrs::Error> {
+        Ok(Self {
+            gilrs: Gilrs::new()?,
+            announced_initial: false,
+        })
+    }
+}
+
+impl GamepadBackend for GilrsBackend {
+    fn poll_events(&mut self, events: &mut Vec<GamepadEvent>) {
+        // gilrs does not emit connect events for pads plugged in before it
+        // started, so report those on the first poll.
+        if !self.announced_initial {
+            self.announced_initial = true;
+            for (id, gamepad) in self.gilrs.gamepads() {
+                events.push(GamepadEvent::Connected {
+                    id: GamepadId(id.into()),
+                    name: gamepad.name().to_string(),
+                });
+            }
+        }
+        while let Some(gilrs::Event {
+            id: gilrs_id,
+            event,
+            ..
+        }) = self.gilrs.next_event()
+        {
+            let id = GamepadId(gilrs_id.into());
+            let event = match event {
+                EventType::Connected => Some(GamepadEvent::Connected {
+                    id,
+                    name: self.gilrs.gamepad(gilrs_id).name().to_string(),
+                }),
+                EventType::Disconnected => Some(GamepadEvent::Disconnected { id }),
+                EventType::ButtonPressed(button, _) => {
+                    map_button(button).map(|button| GamepadEvent::ButtonPressed { id, button })
+                }
+                EventType::ButtonReleased(button, _) => {
+                    map_button(button).map(|button| GamepadEvent::ButtonReleased { id, button })
+                }
+                EventType::ButtonChanged(Button::LeftTrigger2, value, _) => {
+                    Some(GamepadEvent::AxisChanged {
+                        id,
+                        axis: GamepadAxis::LeftTrigger,
+                        value,
+                    })
+                }
+                EventType::ButtonChanged(Button::RightTrigger2, value, _) => {
+                    Some(GamepadEvent::AxisChanged {
+                        id,
+                        axis: GamepadAxis::RightTrigger,
+                        value,
+                    })
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    map_axis(axis).map(|axis| GamepadEvent::AxisChanged { id, axis, value })
+                }
+                _ => None,
+            };
+            events.extend(event);
+        }
+    }
+}
+
+fn map_button(button: Button) -> Option<GamepadButton> {
+    Some(match button {
+        Button::South => GamepadButton::South,
+        Button::East => GamepadButton::East,
+        Button::North => GamepadButton::North,
+        Button::West => GamepadButton::West,
+        Button::LeftTrigger => GamepadButton::LeftBumper,
+        Button::RightTrigger => GamepadButton::RightBumper,
+        Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+        Button::RightTrigger2 => GamepadButton::RightTrigger,
+        Button::Select => GamepadButton::Select,
+        Button::Start => GamepadButton::Start,
+        Button::Mode => GamepadButton::Mode,
+        Button::LeftThumb => GamepadButton::LeftStick,
+        Button::RightThumb => GamepadButton::RightStick,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+fn map_axis(axis: Axis) -> Option<GamepadAxis> {
+    Some(match axis {
+        Axis::LeftStickX => GamepadAxis::LeftStickX,
+        Axis::LeftStickY => GamepadAxis::LeftStickY,
+        Axis::RightStickX => GamepadAxis::RightStickX,
+        Axis::RightStickY => GamepadAxis::RightStickY,
+        _ => return None,
+    })
+}
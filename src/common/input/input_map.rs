@@ -0,0 +1,410 @@
+use super::{
+    gamepad::{GamepadAxis, GamepadButton, GamepadState},
+    keyboard::{KeyboardKey, KeyboardState},
+    mouse::MouseState,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use winit::event::MouseButton;
+
+/// Analog bindings count as held once their value reaches this magnitude.
+const ANALOG_PRESS_THRESHOLD: f32 = 0.5;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MouseAxis {
+    MotionX,
+    MotionY,
+    ScrollX,
+    ScrollY,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Binding {
+    Key(KeyboardKey),
+    MouseButton(MouseButton),
+    MouseAxis(MouseAxis),
+    GamepadButton(GamepadButton),
+    GamepadAxis(GamepadAxis),
+    /// Two keys acting as one axis, e.g. A/D for horizontal movement.
+    KeyAxis {
+        negative: KeyboardKey,
+        positive: KeyboardKey,
+    },
+}
+
+impl Binding {
+    pub fn key<K>(key: K) -> Self
+    where
+        K: Into<KeyboardKey>,
+    {
+        Binding::Key(key.into())
+    }
+
+    pub fn key_axis<N, P>(negative: N, positive: P) -> Self
+    where
+        N: Into<KeyboardKey>,
+        P: Into<KeyboardKey>,
+    {
+        Binding::KeyAxis {
+            negative: negative.into(),
+            positive: positive.into(),
+        }
+    }
+}
+
+/// The devices an [`InputMap`] reads from.
+#[derive(Clone, Copy)]
+pub struct InputSources<'a> {
+    pub keyboard: &'a KeyboardState,
+    pub mouse: &'a MouseState,
+    pub gamepads: &'a GamepadState,
+}
+
+impl InputSources<'_> {
+    fn value(&self, binding: &Binding) -> f32 {
+        let digital = |down: bool| if down { 1.0 } else { 0.0 };
+        match binding {
+            Binding::Key(key) => digital(self.keyboard.is_key_down(key.clone())),
+            Binding::MouseButton(button) => digital(self.mouse.is_button_down(*button)),
+            Binding::MouseAxis(axis) => match axis {
+                MouseAxis::MotionX => self.mouse.motion().x,
+                MouseAxis::MotionY => self.mouse.motion().y,
+                MouseAxis::ScrollX => self.mouse.scroll().x,
+                MouseAxis::ScrollY => self.mouse.scroll().y,
+            },
+            Binding::GamepadButton(button) => digital(
+                self.gamepads
+                    .connected()
+                    .any(|(id, _)| self.gamepads.is_button_down(id, *button)),
+            ),
+            Binding::GamepadAxis(axis) => self
+                .gamepads
+                .connected()
+                .map(|(id, _)| self.gamepads.axis(id, *axis))
+                .fold(0.0, strongest),
+            Binding::KeyAxis { negative, positive } => {
+                digital(self.keyboard.is_key_down(positive.clone()))
+                    - digital(self.keyboard.is_key_down(negative.clone()))
+            }
+        }
+    }
+
+    /// Whether `binding` went down during this frame. Together with
+    /// [`just_released`](Self::just_released) this catches presses that
+    /// start and end between two frames. Axes only change through their
+    /// value, which [`InputMap`] compares across frames.
+    fn just_pressed(&self, binding: &Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.keyboard.is_key_just_pressed(key.clone()),
+            Binding::MouseButton(button) => self.mouse.is_button_just_pressed(*button),
+            Binding::GamepadButton(button) => self
+                .gamepads
+                .connected()
+                .any(|(id, _)| self.gamepads.is_button_just_pressed(id, *button)),
+            Binding::KeyAxis { negative, positive } => {
+                self.keyboard.is_key_just_pressed(negative.clone())
+                    || self.keyboard.is_key_just_pressed(positive.clone())
+            }
+            Binding::MouseAxis(_) | Binding::GamepadAxis(_) => false,
+        }
+    }
+
+    fn just_released(&self, binding: &Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.keyboard.is_key_just_released(key.clone()),
+            Binding::MouseButton(button) => self.mouse.is_button_just_released(*button),
+            Binding::GamepadButton(button) => self
+                .gamepads
+                .connected()
+                .any(|(id, _)| self.gamepads.is_button_just_released(id, *button)),
+            Binding::KeyAxis { negative, positive } => {
+                self.keyboard.is_key_just_released(negative.clone())
+                    || self.keyboard.is_key_just_released(positive.clone())
+            }
+            Binding::MouseAxis(_) | Binding::GamepadAxis(_) => false,
+        }
+    }
+}
+
+/// Named actions bound to any number of inputs. With the `serde` feature the
+/// map can be written to and read from a config file so players can rebind.
+#[derive(Clone, Default, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct InputMap {
+    actions: BTreeMap<String, Vec<Binding>>,
+    /// Actions that were down at the end of the last frame.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    down_last_frame: BTreeSet<String>,
+}
+
+/// Two maps are equal when they bind the same inputs.
+impl PartialEq for InputMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.actions == other.actions
+    }
+}
+
+impl InputMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_binding<A>(mut self, action: A, binding: Binding) -> Self
+    where
+        A: Into<String>,
+    {
+        self.bind(action, binding);
+        self
+    }
+
+    pub fn bind<A>(&mut self, action: A, binding: Binding)
+    where
+        A: Into<String>,
+    {
+        let bindings = self.actions.entry(action.into()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: &str, binding: &Binding) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.retain(|existing| existing != binding);
+        }
+    }
+
+    /// Replaces every binding of `action`, as a rebinding menu would.
+    pub fn rebind<A>(&mut self, action: A, bindings: Vec<Binding>)
+    where
+        A: Into<String>,
+    {
+        self.actions.insert(action.into(), bindings);
+    }
+
+    pub fn remove_action(&mut self, action: &str) {
+        self.actions.remove(action);
+        self.down_last_frame.remove(action);
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.actions.keys().map(String::as_str)
+    }
+
+    /// The strongest value among the action's bindings. Buttons and keys
+    /// read as `0.0` or `1.0`, axes as their signed value.
+    pub fn value(&self, action: &str, sources: InputSources) -> f32 {
+        self.bindings(action)
+            .iter()
+            .map(|binding| sources.value(binding))
+            .fold(0.0, strongest)
+    }
+
+    pub fn is_down(&self, action: &str, sources: InputSources) -> bool {
+        self.value(action, sources).abs() >= ANALOG_PRESS_THRESHOLD
+    }
+
+    /// Whether the action was up last frame and went down since, including
+    /// a press released again before this frame ended.
+    pub fn is_just_pressed(&self, action: &str, sources: InputSources) -> bool {
+        !self.down_last_frame.contains(action)
+            && (self.is_down(action, sources)
+                || self
+                    .bindings(action)
+                    .iter()
+                    .any(|binding| sources.just_pressed(binding)))
+    }
+
+    /// Whether the action is up now but was down at some point since the
+    /// last frame.
+    pub fn is_just_released(&self, action: &str, sources: InputSources) -> bool {
+        !self.is_down(action, sources)
+            && (self.down_last_frame.contains(action)
+                || self
+                    .bindings(action)
+                    .iter()
+                    .any(|binding| sources.just_released(binding)))
+    }
+
+    /// Remembers which actions are down, for the next frame's
+    /// [`is_just_pressed`](Self::is_just_pressed) and
+    /// [`is_just_released`](Self::is_just_released). Call it before the
+    /// devices clear their per-frame state.
+    pub fn end_frame(&mut self, sources: InputSources) {
+        let down = self
+            .actions()
+            .filter(|action| self.is_down(action, sources))
+            .map(String::from)
+            .collect();
+        self.down_last_frame = down;
+    }
+}
+
+fn strongest(current: f32, value: f32) -> f32 {
+    if value.abs() > current.abs() {
+        value
+    } else {
+        current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::input::gamepad::{GamepadEvent, GamepadId};
+    use winit::keyboard::NamedKey;
+
+    #[derive(Default)]
+    struct Devices {
+        keyboard: KeyboardState,
+        mouse: MouseState,
+        gamepads: GamepadState,
+    }
+
+    impl Devices {
+        fn sources(&self) -> InputSources<'_> {
+            InputSources {
+                keyboard: &self.keyboard,
+                mouse: &self.mouse,
+                gamepads: &self.gamepads,
+            }
+        }
+
+        fn end_frame(&mut self, map: &mut InputMap) {
+            map.end_frame(self.sources());
+            self.keyboard.end_frame();
+            self.mouse.end_frame();
+            self.gamepads.end_frame();
+        }
+
+        fn pressed_and_released(&self, map: &InputMap, action: &str) -> (bool, bool) {
+            (
+                map.is_just_pressed(action, self.sources()),
+                map.is_just_released(action, self.sources()),
+            )
+        }
+    }
+
+    #[test]
+    fn a_second_binding_does_not_press_a_held_action_again() {
+        let mut map = InputMap::new()
+            .with_binding("jump", Binding::key(NamedKey::Space))
+            .with_binding("jump", Binding::key("w"));
+        let mut devices = Devices::default();
+        devices.keyboard.press(NamedKey::Space);
+        assert_eq!(devices.pressed_and_released(&map, "jump"), (true, false));
+        devices.end_frame(&mut map);
+
+        devices.keyboard.press("w");
+        assert_eq!(devices.pressed_and_released(&map, "jump"), (false, false));
+        devices.end_frame(&mut map);
+
+        devices.keyboard.release(NamedKey::Space);
+        assert_eq!(devices.pressed_and_released(&map, "jump"), (false, false));
+        devices.end_frame(&mut map);
+
+        devices.keyboard.release("w");
+        assert_eq!(devices.pressed_and_released(&map, "jump"), (false, true));
+        devices.end_frame(&mut map);
+        assert_eq!(devices.pressed_and_released(&map, "jump"), (false, false));
+    }
+
+    #[test]
+    fn a_press_within_one_frame_is_reported() {
+        let mut map = InputMap::new().with_binding("fire", Binding::key(NamedKey::Enter));
+        let mut devices = Devices::default();
+        devices.keyboard.press(NamedKey::Enter);
+        devices.keyboard.release(NamedKey::Enter);
+        assert!(!map.is_down("fire", devices.sources()));
+        assert_eq!(devices.pressed_and_released(&map, "fire"), (true, true));
+        devices.end_frame(&mut map);
+        assert_eq!(devices.pressed_and_released(&map, "fire"), (false, false));
+    }
+
+    #[test]
+    fn gamepad_axes_cross_the_press_threshold() {
+        let pad = GamepadId(0);
+        let mut map = InputMap::new().with_binding(
+            "accelerate",
+            Binding::GamepadAxis(GamepadAxis::RightTrigger),
+        );
+        let mut devices = Devices::default();
+        devices.gamepads.set_deadzone(0.0);
+        devices.gamepads.process(GamepadEvent::Connected {
+            id: pad,
+            name: String::from("Test pad"),
+        });
+        let set_trigger = |devices: &mut Devices, value: f32| {
+            devices.gamepads.process(GamepadEvent::AxisChanged {
+                id: pad,
+                axis: GamepadAxis::RightTrigger,
+                value,
+            });
+        };
+
+        set_trigger(&mut devices, 0.3);
+        assert_eq!(
+            devices.pressed_and_released(&map, "accelerate"),
+            (false, false)
+        );
+        devices.end_frame(&mut map);
+
+        set_trigger(&mut devices, 0.8);
+        assert_eq!(
+            devices.pressed_and_released(&map, "accelerate"),
+            (true, false)
+        );
+        devices.end_frame(&mut map);
+
+        set_trigger(&mut devices, 0.9);
+        assert_eq!(
+            devices.pressed_and_released(&map, "accelerate"),
+            (false, false)
+        );
+        devices.end_frame(&mut map);
+
+        set_trigger(&mut devices, 0.1);
+        assert_eq!(
+            devices.pressed_and_released(&map, "accelerate"),
+            (false, true)
+        );
+        devices.end_frame(&mut map);
+        assert_eq!(
+            devices.pressed_and_released(&map, "accelerate"),
+            (false, false)
+        );
+    }
+
+    #[test]
+    fn mouse_motion_presses_for_the_frames_it_moves() {
+        let mut map = InputMap::new().with_binding("look", Binding::MouseAxis(MouseAxis::MotionX));
+        let mut devices = Devices::default();
+        devices.mouse.handle_motion(3.0, 0.0);
+        assert_eq!(devices.pressed_and_released(&map, "look"), (true, false));
+        devices.end_frame(&mut map);
+
+        devices.mouse.handle_motion(-2.0, 0.0);
+        assert_eq!(devices.pressed_and_released(&map, "look"), (false, false));
+        devices.end_frame(&mut map);
+
+        assert_eq!(devices.pressed_and_released(&map, "look"), (false, true));
+    }
+
+    #[test]
+    fn equality_ignores_the_frame_state() {
+        let mut map = InputMap::new().with_binding("jump", Binding::key(NamedKey::Space));
+        let unchanged = map.clone();
+        let mut devices = Devices::default();
+        devices.keyboard.press(NamedKey::Space);
+        devices.end_frame(&mut map);
+        assert_eq!(map, unchanged);
+    }
+}
@@ -0,0 +1,277 @@
+use fnv::{FnvHashMap, FnvHashSet};
+use winit::{
+    event::{ElementState, KeyEvent},
+    keyboard::{Key, KeyCode, NamedKey, PhysicalKey, SmolStr},
+};
+
+/// A key as seen by the game: a named key, a character produced by the
+/// current layout, or a physical key code that is layout independent.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyboardKey {
+    Named(NamedKey),
+    Character(SmolStr),
+    Code(KeyCode),
+}
+
+impl KeyboardKey {
+    fn character(text: &str) -> Self {
+        KeyboardKey::Character(SmolStr::new(text.to_lowercase()))
+    }
+}
+
+impl From<NamedKey> for KeyboardKey {
+    fn from(key: NamedKey) -> Self {
+        KeyboardKey::Named(key)
+    }
+}
+
+impl From<KeyCode> for KeyboardKey {
+    fn from(code: KeyCode) -> Self {
+        KeyboardKey::Code(code)
+    }
+}
+
+impl From<char> for KeyboardKey {
+    fn from(character: char) -> Self {
+        KeyboardKey::character(character.encode_utf8(&mut [0; 4]))
+    }
+}
+
+impl From<&str> for KeyboardKey {
+    fn from(text: &str) -> Self {
+        KeyboardKey::character(text)
+    }
+}
+
+#[derive(Default)]
+pub struct KeyboardState {
+    down: FnvHashSet<KeyboardKey>,
+    just_pressed: FnvHashSet<KeyboardKey>,
+    just_released: FnvHashSet<KeyboardKey>,
+    logical_by_physical: FnvHashMap<PhysicalKey, KeyboardKey>,
+}
+
+impl KeyboardState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn handle_key_event(&mut self, event: &KeyEvent) {
+        self.process(&event.logical_key, event.physical_key, event.state);
+    }
+
+    /// Records a key transition. This is what [`handle_key_event`] feeds, and
+    /// it can be called directly to inject synthetic input.
+    ///
+    /// [`handle_key_event`]: KeyboardState::handle_key_event
+    pub fn process(&mut self, logical_key: &Key, physical_key: PhysicalKey, state: ElementState) {
+        match state {
+            ElementState::Pressed => {
+                if let PhysicalKey::Code(code) = physical_key {
+                    self.press(code);
+                }
+                if let Some(logical_key) = to_keyboard_key(logical_key) {
+                    // Remember what the key produced when it went down, so that
+                    // releasing it after a modifier changed still matches.
+                    if let Some(previous) = self
+                        .logical_by_physical
+                        .insert(physical_key, logical_key.clone())
+                    {
+                        if previous != logical_key {
+                            self.release(previous);
+                        }
+                    }
+                    self.press(logical_key);
+                }
+            }
+            ElementState::Released => {
+                if let PhysicalKey::Code(code) = physical_key {
+                    self.release(code);
+                }
+                let logical_key = self
+                    .logical_by_physical
+                    .remove(&physical_key)
+                    .or_else(|| to_keyboard_key(logical_key));
+                if let Some(logical_key) = logical_key {
+                    self.release(logical_key);
+                }
+            }
+        }
+    }
+
+    pub fn press<K>(&mut self, key: K)
+    where
+        K: Into<KeyboardKey>,
+    {
+        let key = key.into();
+        if self.down.insert(key.clone()) {
+            self.just_pressed.insert(key);
+        }
+    }
+
+    pub fn release<K>(&mut self, key: K)
+    where
+        K: Into<KeyboardKey>,
+    {
+        let key = key.into();
+        if self.down.remove(&key) {
+            self.just_released.insert(key);
+        }
+    }
+
+    /// Releases every held key, e.g. when the window loses focus and the
+    /// matching release events will never arrive.
+    pub fn release_all(&mut self) {
+        self.just_released.extend(self.down.drain());
+        self.logical_by_physical.clear();
+    }
+
+    pub fn is_key_down<K>(&self, key: K) -> bool
+    where
+        K: Into<KeyboardKey>,
+    {
+        self.down.contains(&key.into())
+    }
+
+    pub fn is_key_just_pressed<K>(&self, key: K) -> bool
+    where
+        K: Into<KeyboardKey>,
+    {
+        self.just_pressed.contains(&key.into())
+    }
+
+    pub fn is_key_just_released<K>(&self, key: K) -> bool
+    where
+        K: Into<KeyboardKey>,
+    {
+        self.just_released.contains(&key.into())
+    }
+
+    pub fn end_frame(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+}
+
+fn to_keyboard_key(key: &Key) -> Option<KeyboardKey> {
+    match key {
+        Key::Named(named) => Some(KeyboardKey::Named(*named)),
+        Key::Character(text) => Some(KeyboardKey::character(text)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn character(text: &str) -> Key {
+        Key::Character(SmolStr::new(text))
+    }
+
+    #[test]
+    fn press_is_just_pressed_for_one_frame() {
+        let mut keyboard = KeyboardState::new();
+        keyboard.process(
+            &character("a"),
+            PhysicalKey::Code(KeyCode::KeyA),
+            ElementState::Pressed,
+        );
+        assert!(keyboard.is_key_down('a'));
+        assert!(keyboard.is_key_down(KeyCode::KeyA));
+        assert!(keyboard.is_key_just_pressed('a'));
+        keyboard.end_frame();
+        assert!(keyboard.is_key_down('a'));
+        assert!(!keyboard.is_key_just_pressed('a'));
+    }
+
+    #[test]
+    fn repeated_presses_do_not_fire_again() {
+        let mut keyboard = KeyboardState::new();
+        let space = Key::Named(NamedKey::Space);
+        let code = PhysicalKey::Code(KeyCode::Space);
+        keyboard.process(&space, code, ElementState::Pressed);
+        keyboard.end_frame();
+        keyboard.process(&space, code, ElementState::Pressed);
+        assert!(keyboard.is_key_down(NamedKey::Space));
+        assert!(!keyboard.is_key_just_pressed(NamedKey::Space));
+    }
+
+    #[test]
+    fn release_is_just_released_for_one_frame() {
+        let mut keyboard = KeyboardState::new();
+        let code = PhysicalKey::Code(KeyCode::KeyA);
+        keyboard.process(&character("a"), code, ElementState::Pressed);
+        keyboard.end_frame();
+        keyboard.process(&character("a"), code, ElementState::Released);
+        assert!(!keyboard.is_key_down('a'));
+        assert!(!keyboard.is_key_down(KeyCode::KeyA));
+        assert!(keyboard.is_key_just_released('a'));
+        assert!(keyboard.is_key_just_released(KeyCode::KeyA));
+        keyboard.end_frame();
+        assert!(!keyboard.is_key_just_released('a'));
+    }
+
+    #[test]
+    fn press_and_release_in_one_frame_are_both_seen() {
+        let mut keyboard = KeyboardState::new();
+        keyboard.press(NamedKey::Enter);
+        keyboard.release(NamedKey::Enter);
+        assert!(!keyboard.is_key_down(NamedKey::Enter));
+        assert!(keyboard.is_key_just_pressed(NamedKey::Enter));
+        assert!(keyboard.is_key_just_released(NamedKey::Enter));
+    }
+
+    #[test]
+    fn characters_are_lowercased() {
+        let mut keyboard = KeyboardState::new();
+        keyboard.process(
+            &character("A"),
+            PhysicalKey::Code(KeyCode::KeyA),
+            ElementState::Pressed,
+        );
+        assert!(keyboard.is_key_down('a'));
+        assert!(keyboard.is_key_down('A'));
+        assert!(keyboard.is_key_down("A"));
+        assert_eq!(KeyboardKey::from('Ä'), KeyboardKey::from("ä"));
+    }
+
+    #[test]
+    fn release_matches_the_character_produced_on_press() {
+        let mut keyboard = KeyboardState::new();
+        let code = PhysicalKey::Code(KeyCode::Digit1);
+        keyboard.process(&character("1"), code, ElementState::Pressed);
+        // Shift went down meanwhile, so the release reports another character.
+        keyboard.process(&character("!"), code, ElementState::Released);
+        assert!(!keyboard.is_key_down('1'));
+        assert!(keyboard.is_key_just_released('1'));
+        assert!(!keyboard.is_key_just_released('!'));
+    }
+
+    #[test]
+    fn focus_loss_releases_every_key() {
+        let mut keyboard = KeyboardState::new();
+        keyboard.process(
+            &character("w"),
+            PhysicalKey::Code(KeyCode::KeyW),
+            ElementState::Pressed,
+        );
+        keyboard.press(NamedKey::Shift);
+        keyboard.end_frame();
+        keyboard.release_all();
+        assert!(!keyboard.is_key_down('w'));
+        assert!(!keyboard.is_key_down(KeyCode::KeyW));
+        assert!(!keyboard.is_key_down(NamedKey::Shift));
+        assert!(keyboard.is_key_just_released('w'));
+        assert!(keyboard.is_key_just_released(NamedKey::Shift));
+        keyboard.end_frame();
+        // A late release event for a key that is already up changes nothing.
+        keyboard.process(
+            &character("w"),
+            PhysicalKey::Code(KeyCode::KeyW),
+            ElementState::Released,
+        );
+        assert!(!keyboard.is_key_just_released('w'));
+    }
+}
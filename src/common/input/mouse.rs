@@ -0,0 +1,201 @@
+use fnv::FnvHashSet;
+use nalgebra::{Point2, Vector2};
+use winit::event::{ElementState, MouseButton, MouseScrollDelta};
+
+/// Pixel scroll deltas (touchpads) are converted to lines using this ratio so
+/// games only have to deal with one unit.
+const PIXELS_PER_LINE: f32 = 20.0;
+
+#[derive(Default)]
+pub struct MouseState {
+    position: Point2<f32>,
+    window_size: Vector2<f32>,
+    inside_window: bool,
+    motion: Vector2<f32>,
+    scroll: Vector2<f32>,
+    down: FnvHashSet<MouseButton>,
+    just_pressed: FnvHashSet<MouseButton>,
+    just_released: FnvHashSet<MouseButton>,
+}
+
+impl MouseState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_window_size(&mut self, width: u32, height: u32) {
+        self.window_size = Vector2::new(width as f32, height as f32);
+    }
+
+    pub fn handle_cursor_moved(&mut self, x: f64, y: f64) {
+        self.position = Point2::new(x as f32, y as f32);
+        self.inside_window = true;
+    }
+
+    pub fn handle_cursor_left(&mut self) {
+        self.inside_window = false;
+    }
+
+    /// Accumulates raw device motion. Unlike the cursor position this keeps
+    /// reporting while the cursor is grabbed.
+    pub fn handle_motion(&mut self, dx: f64, dy: f64) {
+        self.motion += Vector2::new(dx as f32, dy as f32);
+    }
+
+    pub fn handle_button(&mut self, button: MouseButton, state: ElementState) {
+        match state {
+            ElementState::Pressed => {
+                if self.down.insert(button) {
+                    self.just_pressed.insert(button);
+                }
+            }
+            ElementState::Released => {
+                if self.down.remove(&button) {
+                    self.just_released.insert(button);
+                }
+            }
+        }
+    }
+
+    pub fn handle_scroll(&mut self, delta: MouseScrollDelta) {
+        self.scroll += match delta {
+            MouseScrollDelta::LineDelta(x, y) => Vector2::new(x, y),
+            MouseScrollDelta::PixelDelta(position) => {
+                Vector2::new(position.x as f32, position.y as f32) / PIXELS_PER_LINE
+            }
+        };
+    }
+
+    pub fn release_all(&mut self) {
+        self.just_released.extend(self.down.drain());
+    }
+
+    /// Cursor position in physical pixels, with the origin at the top left.
+    pub fn position(&self) -> Point2<f32> {
+        self.position
+    }
+
+    /// Cursor position scaled to `[0, 1]` on both axes, with the origin at
+    /// the top left.
+    pub fn normalized_position(&self) -> Point2<f32> {
+        if self.window_size.x == 0.0 || self.window_size.y == 0.0 {
+            return Point2::origin();
+        }
+        Point2::new(
+            self.position.x / self.window_size.x,
+            self.position.y / self.window_size.y,
+        )
+    }
+
+    pub fn is_inside_window(&self) -> bool {
+        self.inside_window
+    }
+
+    /// Raw mouse motion since the previous frame.
+    pub fn motion(&self) -> Vector2<f32> {
+        self.motion
+    }
+
+    /// Scroll wheel movement since the previous frame, in lines.
+    pub fn scroll(&self) -> Vector2<f32> {
+        self.scroll
+    }
+
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.down.contains(&button)
+    }
+
+    pub fn is_button_just_pressed(&self, button: MouseButton) -> bool {
+        self.just_pressed.contains(&button)
+    }
+
+    pub fn is_button_just_released(&self, button: MouseButton) -> bool {
+        self.just_released.contains(&button)
+    }
+
+    pub fn end_frame(&mut self) {
+        self.motion = Vector2::zeros();
+        self.scroll = Vector2::zeros();
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::dpi::PhysicalPosition;
+
+    #[test]
+    fn press_and_release_are_reported_for_one_frame() {
+        let mut mouse = MouseState::new();
+        mouse.handle_button(MouseButton::Left, ElementState::Pressed);
+        assert!(mouse.is_button_just_pressed(MouseButton::Left));
+        assert!(mouse.is_button_down(MouseButton::Left));
+        assert!(!mouse.is_button_down(MouseButton::Right));
+
+        mouse.end_frame();
+        mouse.handle_button(MouseButton::Left, ElementState::Pressed);
+        assert!(!mouse.is_button_just_pressed(MouseButton::Left));
+        assert!(mouse.is_button_down(MouseButton::Left));
+
+        mouse.end_frame();
+        mouse.handle_button(MouseButton::Left, ElementState::Released);
+        assert!(mouse.is_button_just_released(MouseButton::Left));
+        assert!(!mouse.is_button_down(MouseButton::Left));
+
+        mouse.end_frame();
+        mouse.handle_button(MouseButton::Left, ElementState::Released);
+        assert!(!mouse.is_button_just_released(MouseButton::Left));
+    }
+
+    #[test]
+    fn release_all_releases_held_buttons() {
+        let mut mouse = MouseState::new();
+        mouse.handle_button(MouseButton::Left, ElementState::Pressed);
+        mouse.handle_button(MouseButton::Middle, ElementState::Pressed);
+        mouse.end_frame();
+        mouse.release_all();
+        assert!(!mouse.is_button_down(MouseButton::Left));
+        assert!(mouse.is_button_just_released(MouseButton::Left));
+        assert!(mouse.is_button_just_released(MouseButton::Middle));
+        assert!(!mouse.is_button_just_released(MouseButton::Right));
+    }
+
+    #[test]
+    fn pixel_scroll_is_converted_to_lines() {
+        let mut mouse = MouseState::new();
+        mouse.handle_scroll(MouseScrollDelta::PixelDelta(PhysicalPosition::new(
+            10.0, -40.0,
+        )));
+        mouse.handle_scroll(MouseScrollDelta::LineDelta(1.0, 1.0));
+        assert_eq!(mouse.scroll(), Vector2::new(1.5, -1.0));
+    }
+
+    #[test]
+    fn normalized_position_handles_a_zero_size_window() {
+        let mut mouse = MouseState::new();
+        mouse.handle_cursor_moved(30.0, 45.0);
+        assert_eq!(mouse.normalized_position(), Point2::origin());
+        mouse.set_window_size(120, 0);
+        assert_eq!(mouse.normalized_position(), Point2::origin());
+        mouse.set_window_size(120, 90);
+        assert_eq!(mouse.normalized_position(), Point2::new(0.25, 0.5));
+    }
+
+    #[test]
+    fn end_frame_resets_motion_and_scroll() {
+        let mut mouse = MouseState::new();
+        mouse.handle_cursor_moved(12.0, 8.0);
+        mouse.handle_motion(3.0, -2.0);
+        mouse.handle_motion(1.0, 1.0);
+        mouse.handle_scroll(MouseScrollDelta::LineDelta(0.0, 2.0));
+        assert_eq!(mouse.motion(), Vector2::new(4.0, -1.0));
+
+        mouse.end_frame();
+        assert_eq!(mouse.motion(), Vector2::zeros());
+        assert_eq!(mouse.scroll(), Vector2::zeros());
+        assert_eq!(mouse.position(), Point2::new(12.0, 8.0));
+        assert!(mouse.is_inside_window());
+    }
+}
@@ -0,0 +1,403 @@
+use super::{
+    drawables::{Drawable, RendererContext},
+    errors::GlError,
+    gl::{self, types::GLenum},
+    helpers::{Fragment, Shader, Vertex},
+    render_state::RenderState,
+    texture::Texture2D,
+    uniform::UniformValue,
+    vertex_layout::VertexLayout,
+    wrappers::{
+        buffer_wrapper::{as_bytes, BufferUpload, BufferUsage, BufferWrapper},
+        program_wrapper::ProgramWrapper,
+    },
+};
+use std::{cell::RefCell, rc::Rc};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PrimitiveTopology {
+    Points,
+    Lines,
+    LineStrip,
+    LineLoop,
+    #[default]
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+}
+
+impl PrimitiveTopology {
+    fn gl_mode(self) -> GLenum {
+        match self {
+            PrimitiveTopology::Points => gl::POINTS,
+            PrimitiveTopology::Lines => gl::LINES,
+            PrimitiveTopology::LineStrip => gl::LINE_STRIP,
+            PrimitiveTopology::LineLoop => gl::LINE_LOOP,
+            PrimitiveTopology::Triangles => gl::TRIANGLES,
+            PrimitiveTopology::TriangleStrip => gl::TRIANGLE_STRIP,
+            PrimitiveTopology::TriangleFan => gl::TRIANGLE_FAN,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl From<Vec<u16>> for Indices {
+    fn from(indices: Vec<u16>) -> Self {
+        Indices::U16(indices)
+    }
+}
+
+impl From<Vec<u32>> for Indices {
+    fn from(indices: Vec<u32>) -> Self {
+        Indices::U32(indices)
+    }
+}
+
+enum IndexBuffer {
+    U16(BufferWrapper<u16>),
+    U32(BufferWrapper<u32>),
+}
+
+impl IndexBuffer {
+    fn new(indices: Indices, usage: BufferUsage) -> Self {
+        match indices {
+            Indices::U16(indices) => IndexBuffer::U16(BufferWrapper::index(indices, usage)),
+            Indices::U32(indices) => IndexBuffer::U32(BufferWrapper::index(indices, usage)),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            IndexBuffer::U16(buffer) => buffer.len(),
+            IndexBuffer::U32(buffer) => buffer.len(),
+        }
+    }
+
+    fn gl_type(&self) -> GLenum {
+        match self {
+            IndexBuffer::U16(_) => gl::UNSIGNED_SHORT,
+            IndexBuffer::U32(_) => gl::UNSIGNED_INT,
+        }
+    }
+
+    fn set_usage(&mut self, usage: BufferUsage) {
+        match self {
+            IndexBuffer::U16(buffer) => buffer.set_usage(usage),
+            IndexBuffer::U32(buffer) => buffer.set_usage(usage),
+        }
+    }
+
+    unsafe fn get_buffer_ref(&self) -> u32 {
+        match self {
+            IndexBuffer::U16(buffer) => buffer.get_buffer_ref(),
+            IndexBuffer::U32(buffer) => buffer.get_buffer_ref(),
+        }
+    }
+
+    fn take_upload(&self) -> Option<BufferUpload> {
+        match self {
+            IndexBuffer::U16(buffer) => buffer.take_upload(),
+            IndexBuffer::U32(buffer) => buffer.take_upload(),
+        }
+    }
+}
+
+/// Arbitrary geometry described by a [`VertexLayout`], drawn with the given
+/// shaders. Vertex and index data live in GPU buffers that are only written
+/// when they change.
+pub struct Mesh {
+    program_wrapper: ProgramWrapper,
+    layout: VertexLayout,
+    vertices: BufferWrapper<u8>,
+    indices: Option<IndexBuffer>,
+    topology: PrimitiveTopology,
+    render_state: RenderState,
+    uniforms: RefCell<Vec<(&'static str, UniformValue)>>,
+    textures: Vec<(&'static str, Rc<Texture2D>)>,
+}
+
+impl Mesh {
+    pub fn new<T>(
+        vertex_shader: &'static Shader<Vertex>,
+        fragment_shader: &'static Shader<Fragment>,
+        layout: VertexLayout,
+        vertices: &[T],
+    ) -> Self
+    where
+        T: Copy,
+    {
+        Self {
+            program_wrapper: ProgramWrapper::new(vertex_shader, fragment_shader),
+            layout,
+            vertices: BufferWrapper::vertex(as_bytes(vertices).to_vec(), BufferUsage::Static),
+            indices: None,
+            topology: PrimitiveTopology::default(),
+            render_state: RenderState::default(),
+            uniforms: RefCell::new(Vec::new()),
+            textures: Vec::new(),
+        }
+    }
+
+    pub fn with_indices<I>(mut self, indices: I) -> Self
+    where
+        I: Into<Indices>,
+    {
+        self.set_indices(indices);
+        self
+    }
+
+    pub fn with_render_state(mut self, render_state: RenderState) -> Self {
+        self.render_state = render_state;
+        self
+    }
+
+    pub fn set_render_state(&mut self, render_state: RenderState) {
+        self.render_state = render_state;
+    }
+
+    pub fn render_state(&self) -> RenderState {
+        self.render_state
+    }
+
+    pub fn with_usage(mut self, usage: BufferUsage) -> Self {
+        self.set_usage(usage);
+        self
+    }
+
+    pub fn set_usage(&mut self, usage: BufferUsage) {
+        self.vertices.set_usage(usage);
+        if let Some(indices) = self.indices.as_mut() {
+            indices.set_usage(usage);
+        }
+    }
+
+    pub fn set_vertices<T>(&mut self, vertices: &[T])
+    where
+        T: Copy,
+    {
+        self.vertices.set_data(as_bytes(vertices).to_vec());
+    }
+
+    /// Overwrites vertices starting at `first_vertex` without touching the
+    /// rest of the buffer.
+    pub fn update_vertices<T>(&mut self, first_vertex: usize, vertices: &[T])
+    where
+        T: Copy,
+    {
+        let offset = first_vertex * self.layout.stride();
+        self.vertices.update(offset, as_bytes(vertices));
+    }
+
+    pub fn set_indices<I>(&mut self, indices: I)
+    where
+        I: Into<Indices>,
+    {
+        self.indices = Some(IndexBuffer::new(indices.into(), self.vertices.usage()));
+    }
+
+    pub fn with_topology(mut self, topology: PrimitiveTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// Sets a uniform, sent to the GPU on the next draw. Unknown names are
+    /// reported as [`GlError::NonexistantUniformName`] by [`Drawable::draw`].
+    pub fn set_uniform<U>(&mut self, name: &'static str, value: U)
+    where
+        U: Into<UniformValue>,
+    {
+        let value = value.into();
+        let uniforms = self.uniforms.get_mut();
+        match uniforms.iter_mut().find(|(existing, _)| *existing == name) {
+            Some((_, existing_value)) => *existing_value = value,
+            None => uniforms.push((name, value)),
+        }
+    }
+
+    /// Samples `texture` through the sampler uniform `name`. Each texture
+    /// gets its own texture unit, in the order they were first set.
+    pub fn set_texture(&mut self, name: &'static str, texture: Rc<Texture2D>) {
+        match self
+            .textures
+            .iter_mut()
+            .find(|(existing, _)| *existing == name)
+        {
+            Some((_, existing_texture)) => *existing_texture = texture,
+            None => self.textures.push((name, texture)),
+        }
+    }
+
+    pub fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+
+    pub fn topology(&self) -> PrimitiveTopology {
+        self.topology
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        match self.layout.stride() {
+            0 => 0,
+            stride => self.vertices.len() / stride,
+        }
+    }
+
+    pub fn program_wrapper(&self) -> &ProgramWrapper {
+        &self.program_wrapper
+    }
+}
+
+impl Drawable for Mesh {
+    fn draw(&self, ctx: &mut RendererContext<'_>) -> Result<(), GlError> {
+        unsafe {
+            let program_id = self.program_wrapper.get_program_id()?;
+            let vao_ref = self.program_wrapper.get_vao_ref();
+            let vbo_ref = self.vertices.get_buffer_ref();
+            let ebo_ref = self
+                .indices
+                .as_ref()
+                .map(|indices| indices.get_buffer_ref());
+            let attribute_bindings = match self.program_wrapper.get_variable_helper() {
+                Some(variable_helper) => Some(variable_helper.resolve_layout(&self.layout)?),
+                None => None,
+            };
+            self.program_wrapper
+                .set_uniform_if_present("model", ctx.model_matrix())?;
+            self.program_wrapper
+                .set_uniform_if_present("view", ctx.view_matrix())?;
+            self.program_wrapper
+                .set_uniform_if_present("projection", ctx.projection_matrix())?;
+            for (name, value) in self.uniforms.take() {
+                self.program_wrapper.set_uniform(name, value)?;
+            }
+            let texture_bindings = self
+                .textures
+                .iter()
+                .enumerate()
+                .map(|(unit, (name, texture))| {
+                    self.program_wrapper.set_texture(name, unit as u32, texture)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let uniforms = self.program_wrapper.take_pending_uniforms();
+            let vertex_upload = self.vertices.take_upload();
+            let index_upload = self.indices.as_ref().and_then(IndexBuffer::take_upload);
+            let gl_state = ctx.gl_state();
+            let render_state = self.render_state;
+            let mode = self.topology.gl_mode();
+            let vertex_count = self.vertex_count() as i32;
+            let index_info = self
+                .indices
+                .as_ref()
+                .map(|indices| (indices.len() as i32, indices.gl_type()));
+            ctx.add_commands(move || {
+                gl_state.apply(&render_state);
+                gl::UseProgram(program_id);
+                uniforms.apply();
+                for texture_binding in &texture_bindings {
+                    texture_binding.apply();
+                }
+                gl::BindVertexArray(vao_ref);
+                gl::BindBuffer(gl::ARRAY_BUFFER, vbo_ref);
+                if let Some(ebo_ref) = ebo_ref {
+                    gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo_ref);
+                }
+                if let Some(ref vertex_upload) = vertex_upload {
+                    vertex_upload.apply();
+                }
+                if let Some(ref index_upload) = index_upload {
+                    index_upload.apply();
+                }
+                if let Some(ref attribute_bindings) = attribute_bindings {
+                    attribute_bindings.apply();
+                }
+                match index_info {
+                    Some((index_count, index_type)) => {
+                        gl::DrawElements(mode, index_count, index_type, std::ptr::null())
+                    }
+                    None => gl::DrawArrays(mode, 0, vertex_count),
+                }
+            });
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::vertex_layout::{AttributeType, VertexAttribute};
+
+    static VERTEX: Shader<Vertex> = Shader::create_vertex_shader("");
+    static FRAGMENT: Shader<Fragment> = Shader::create_fragment_shader("");
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct ColoredVertex {
+        position: [f32; 3],
+        color: [u8; 4],
+        uv: [f32; 2],
+    }
+
+    fn vertex(x: f32, red: u8) -> ColoredVertex {
+        ColoredVertex {
+            position: [x, 0.0, 0.0],
+            color: [red, 0, 0, 255],
+            uv: [0.0, 1.0],
+        }
+    }
+
+    fn mesh(vertices: &[ColoredVertex]) -> Mesh {
+        let layout = VertexLayout::new()
+            .with_attribute(VertexAttribute::float("position", 3))
+            .with_attribute(
+                VertexAttribute::new("color", 4, AttributeType::UnsignedByte).normalized(),
+            )
+            .with_attribute(VertexAttribute::float("uv", 2));
+        Mesh::new(&VERTEX, &FRAGMENT, layout, vertices)
+    }
+
+    #[test]
+    fn vertex_count_uses_the_stride_of_mixed_attributes() {
+        let mesh = mesh(&[vertex(0.0, 1), vertex(1.0, 2), vertex(2.0, 3)]);
+        assert_eq!(mesh.layout().stride(), std::mem::size_of::<ColoredVertex>());
+        assert_eq!(mesh.vertex_count(), 3);
+    }
+
+    #[test]
+    fn update_vertices_writes_at_the_vertex_offset() {
+        let mut mesh = mesh(&[vertex(0.0, 1), vertex(1.0, 2), vertex(2.0, 3)]);
+        mesh.update_vertices(2, &[vertex(5.0, 200)]);
+        let stride = mesh.layout().stride();
+        let bytes = mesh.vertices.data();
+        assert_eq!(
+            &bytes[..2 * stride],
+            as_bytes(&[vertex(0.0, 1), vertex(1.0, 2)])
+        );
+        assert_eq!(&bytes[2 * stride..], as_bytes(&[vertex(5.0, 200)]));
+        assert_eq!(mesh.vertex_count(), 3);
+    }
+
+    #[test]
+    fn an_empty_layout_has_no_vertices() {
+        let mesh = Mesh::new(&VERTEX, &FRAGMENT, VertexLayout::new(), &[1.0f32, 2.0]);
+        assert_eq!(mesh.vertex_count(), 0);
+    }
+}
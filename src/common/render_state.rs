@@ -0,0 +1,344 @@
+use super::gl::{self, types::GLenum};
+use std::{cell::RefCell, rc::Rc};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum DepthFunc {
+    Never,
+    #[default]
+    Less,
+    Equal,
+    LessOrEqual,
+    Greater,
+    NotEqual,
+    GreaterOrEqual,
+    Always,
+}
+
+impl DepthFunc {
+    fn gl_func(self) -> GLenum {
+        match self {
+            DepthFunc::Never => gl::NEVER,
+            DepthFunc::Less => gl::LESS,
+            DepthFunc::Equal => gl::EQUAL,
+            DepthFunc::LessOrEqual => gl::LEQUAL,
+            DepthFunc::Greater => gl::GREATER,
+            DepthFunc::NotEqual => gl::NOTEQUAL,
+            DepthFunc::GreaterOrEqual => gl::GEQUAL,
+            DepthFunc::Always => gl::ALWAYS,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CullMode {
+    #[default]
+    None,
+    Front,
+    Back,
+    FrontAndBack,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum FrontFace {
+    #[default]
+    CounterClockwise,
+    Clockwise,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum BlendMode {
+    #[default]
+    Opaque,
+    /// Classic transparency for straight (non-premultiplied) alpha.
+    Alpha,
+    /// Adds the source color on top, for glows and particles.
+    Additive,
+    /// Transparency for colors already multiplied by their alpha.
+    Premultiplied,
+    Multiply,
+}
+
+impl BlendMode {
+    /// Source and destination factors, or `None` when blending is off.
+    fn gl_factors(self) -> Option<(GLenum, GLenum)> {
+        match self {
+            BlendMode::Opaque => None,
+            BlendMode::Alpha => Some((gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA)),
+            BlendMode::Additive => Some((gl::SRC_ALPHA, gl::ONE)),
+            BlendMode::Premultiplied => Some((gl::ONE, gl::ONE_MINUS_SRC_ALPHA)),
+            BlendMode::Multiply => Some((gl::DST_COLOR, gl::ZERO)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RenderState {
+    pub depth_test: bool,
+    pub depth_write: bool,
+    pub depth_func: DepthFunc,
+    pub cull_mode: CullMode,
+    pub front_face: FrontFace,
+    pub blend_mode: BlendMode,
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        Self::opaque()
+    }
+}
+
+impl RenderState {
+    pub const fn opaque() -> Self {
+        Self {
+            depth_test: true,
+            depth_write: true,
+            depth_func: DepthFunc::Less,
+            cull_mode: CullMode::None,
+            front_face: FrontFace::CounterClockwise,
+            blend_mode: BlendMode::Opaque,
+        }
+    }
+
+    /// Blended geometry is depth tested against the scene but does not write
+    /// depth, so it does not hide what is drawn behind it afterwards.
+    pub const fn transparent() -> Self {
+        Self {
+            depth_write: false,
+            blend_mode: BlendMode::Alpha,
+            ..Self::opaque()
+        }
+    }
+
+    pub const fn additive() -> Self {
+        Self {
+            depth_write: false,
+            blend_mode: BlendMode::Additive,
+            ..Self::opaque()
+        }
+    }
+
+    /// Drawn on top of everything regardless of depth.
+    pub const fn overlay() -> Self {
+        Self {
+            depth_test: false,
+            depth_write: false,
+            blend_mode: BlendMode::Alpha,
+            ..Self::opaque()
+        }
+    }
+
+    pub const fn with_depth_test(mut self, depth_test: bool) -> Self {
+        self.depth_test = depth_test;
+        self
+    }
+
+    pub const fn with_depth_write(mut self, depth_write: bool) -> Self {
+        self.depth_write = depth_write;
+        self
+    }
+
+    pub const fn with_depth_func(mut self, depth_func: DepthFunc) -> Self {
+        self.depth_func = depth_func;
+        self
+    }
+
+    pub const fn with_cull_mode(mut self, cull_mode: CullMode) -> Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    pub const fn with_front_face(mut self, front_face: FrontFace) -> Self {
+        self.front_face = front_face;
+        self
+    }
+
+    pub const fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+}
+
+/// Remembers the render state last sent to GL and only issues the calls for
+/// what differs. Clones share the same cache, so a handle can be moved into
+/// render commands.
+#[derive(Clone, Default)]
+pub struct GlStateTracker {
+    current: Rc<RefCell<Option<RenderState>>>,
+}
+
+impl GlStateTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the GL state that differs from what was last applied.
+    ///
+    /// # Safety
+    ///
+    /// A GL context must be current, and it must be the same context for
+    /// every call on this tracker and its clones. State changed without the
+    /// tracker is not noticed.
+    pub unsafe fn apply(&self, state: &RenderState) {
+        self.changes(state).apply();
+    }
+
+    /// Records `state` as current and returns what differs from the previous
+    /// one, everything if nothing was applied yet.
+    fn changes(&self, state: &RenderState) -> StateChanges {
+        let previous = self.current.borrow_mut().replace(*state);
+        StateChanges::between(previous.as_ref(), state)
+    }
+
+    /// Forgets the cached state, e.g. after foreign code touched GL directly.
+    pub fn invalidate(&self) {
+        self.current.borrow_mut().take();
+    }
+}
+
+/// The fields of a [`RenderState`] that have to be sent to GL.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct StateChanges {
+    depth_test: Option<bool>,
+    depth_write: Option<bool>,
+    depth_func: Option<DepthFunc>,
+    cull_mode: Option<CullMode>,
+    front_face: Option<FrontFace>,
+    blend_mode: Option<BlendMode>,
+}
+
+impl StateChanges {
+    fn between(previous: Option<&RenderState>, state: &RenderState) -> Self {
+        fn changed<T: PartialEq>(previous: Option<T>, value: T) -> Option<T> {
+            (previous.as_ref() != Some(&value)).then_some(value)
+        }
+        Self {
+            depth_test: changed(previous.map(|p| p.depth_test), state.depth_test),
+            depth_write: changed(previous.map(|p| p.depth_write), state.depth_write),
+            depth_func: changed(previous.map(|p| p.depth_func), state.depth_func),
+            cull_mode: changed(previous.map(|p| p.cull_mode), state.cull_mode),
+            front_face: changed(previous.map(|p| p.front_face), state.front_face),
+            blend_mode: changed(previous.map(|p| p.blend_mode), state.blend_mode),
+        }
+    }
+
+    unsafe fn apply(self) {
+        if let Some(depth_test) = self.depth_test {
+            set_capability(gl::DEPTH_TEST, depth_test);
+        }
+        if let Some(depth_write) = self.depth_write {
+            gl::DepthMask(if depth_write { gl::TRUE } else { gl::FALSE });
+        }
+        if let Some(depth_func) = self.depth_func {
+            gl::DepthFunc(depth_func.gl_func());
+        }
+        if let Some(cull_mode) = self.cull_mode {
+            match cull_mode {
+                CullMode::None => gl::Disable(gl::CULL_FACE),
+                CullMode::Front => cull(gl::FRONT),
+                CullMode::Back => cull(gl::BACK),
+                CullMode::FrontAndBack => cull(gl::FRONT_AND_BACK),
+            }
+        }
+        if let Some(front_face) = self.front_face {
+            gl::FrontFace(match front_face {
+                FrontFace::CounterClockwise => gl::CCW,
+                FrontFace::Clockwise => gl::CW,
+            });
+        }
+        if let Some(blend_mode) = self.blend_mode {
+            match blend_mode.gl_factors() {
+                Some((source, destination)) => {
+                    gl::Enable(gl::BLEND);
+                    gl::BlendFunc(source, destination);
+                }
+                None => gl::Disable(gl::BLEND),
+            }
+        }
+    }
+}
+
+unsafe fn set_capability(capability: GLenum, enable: bool) {
+    if enable {
+        gl::Enable(capability);
+    } else {
+        gl::Disable(capability);
+    }
+}
+
+unsafe fn cull(face: GLenum) {
+    gl::Enable(gl::CULL_FACE);
+    gl::CullFace(face);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn everything(state: &RenderState) -> StateChanges {
+        StateChanges {
+            depth_test: Some(state.depth_test),
+            depth_write: Some(state.depth_write),
+            depth_func: Some(state.depth_func),
+            cull_mode: Some(state.cull_mode),
+            front_face: Some(state.front_face),
+            blend_mode: Some(state.blend_mode),
+        }
+    }
+
+    #[test]
+    fn the_first_state_is_applied_in_full() {
+        let tracker = GlStateTracker::new();
+        let state = RenderState::transparent();
+        assert_eq!(tracker.changes(&state), everything(&state));
+    }
+
+    #[test]
+    fn reapplying_the_same_state_changes_nothing() {
+        let tracker = GlStateTracker::new();
+        let state = RenderState::opaque().with_cull_mode(CullMode::Back);
+        tracker.changes(&state);
+        assert_eq!(tracker.changes(&state), StateChanges::default());
+        assert_eq!(tracker.clone().changes(&state), StateChanges::default());
+    }
+
+    #[test]
+    fn only_changed_fields_are_applied() {
+        let tracker = GlStateTracker::new();
+        tracker.changes(&RenderState::opaque());
+        assert_eq!(
+            tracker.changes(&RenderState::transparent()),
+            StateChanges {
+                depth_write: Some(false),
+                blend_mode: Some(BlendMode::Alpha),
+                ..StateChanges::default()
+            }
+        );
+        assert_eq!(
+            tracker.changes(&RenderState::overlay().with_front_face(FrontFace::Clockwise)),
+            StateChanges {
+                depth_test: Some(false),
+                front_face: Some(FrontFace::Clockwise),
+                ..StateChanges::default()
+            }
+        );
+        assert_eq!(
+            tracker.changes(&RenderState::opaque()),
+            StateChanges {
+                depth_test: Some(true),
+                depth_write: Some(true),
+                front_face: Some(FrontFace::CounterClockwise),
+                blend_mode: Some(BlendMode::Opaque),
+                ..StateChanges::default()
+            }
+        );
+    }
+
+    #[test]
+    fn invalidate_applies_everything_again() {
+        let tracker = GlStateTracker::new();
+        let state = RenderState::additive().with_depth_func(DepthFunc::LessOrEqual);
+        tracker.changes(&state);
+        tracker.clone().invalidate();
+        assert_eq!(tracker.changes(&state), everything(&state));
+    }
+}
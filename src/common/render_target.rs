@@ -0,0 +1,367 @@
+use super::{
+    errors::GlError,
+    gl::{self, types::GLenum},
+    render_state::{GlStateTracker, RenderState},
+    rgba_image::RgbaImage,
+    texture::{PixelFormat, Texture2D, TextureOptions, TextureWrap},
+};
+use std::{cell::OnceCell, rc::Rc};
+
+/// An offscreen framebuffer whose color attachment can be sampled as a
+/// [`Texture2D`]. With multisampling enabled, rendering goes to multisampled
+/// renderbuffers that are resolved into the texture when the pass ends.
+///
+/// As with the other GL wrappers, the framebuffer is only created on first
+/// use.
+pub struct RenderTarget {
+    width: u32,
+    height: u32,
+    samples: u8,
+    depth: bool,
+    clear_color: [f32; 4],
+    color_texture: Rc<Texture2D>,
+    framebuffers: OnceCell<Framebuffers>,
+}
+
+struct Framebuffers {
+    framebuffer: u32,
+    depth_renderbuffer: Option<u32>,
+    multisample: Option<MultisampleFramebuffer>,
+}
+
+struct MultisampleFramebuffer {
+    framebuffer: u32,
+    color_renderbuffer: u32,
+    depth_renderbuffer: Option<u32>,
+}
+
+impl RenderTarget {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            samples: 0,
+            depth: true,
+            clear_color: [0.0, 0.0, 0.0, 0.0],
+            color_texture: Rc::new(color_texture(width, height, PixelFormat::Rgba8)),
+            framebuffers: OnceCell::new(),
+        }
+    }
+
+    /// Float formats are useful for HDR rendering.
+    pub fn with_color_format(mut self, format: PixelFormat) -> Self {
+        self.color_texture = Rc::new(color_texture(self.width, self.height, format));
+        self
+    }
+
+    pub fn with_depth(mut self, depth: bool) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Number of MSAA samples, `0` disables multisampling.
+    pub fn with_samples(mut self, samples: u8) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    pub fn with_clear_color(mut self, red: u8, green: u8, blue: u8, alpha: u8) -> Self {
+        self.clear_color = [
+            red as f32 / u8::MAX as f32,
+            green as f32 / u8::MAX as f32,
+            blue as f32 / u8::MAX as f32,
+            alpha as f32 / u8::MAX as f32,
+        ];
+        self
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn samples(&self) -> u8 {
+        self.samples
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width as f32 / self.height.max(1) as f32
+    }
+
+    /// The texture holding the rendered (and resolved) image.
+    pub fn texture(&self) -> Rc<Texture2D> {
+        Rc::clone(&self.color_texture)
+    }
+
+    /// The single-sampled framebuffer holding the color texture. Fails if
+    /// the framebuffer is incomplete.
+    ///
+    /// # Safety
+    ///
+    /// A GL context must be current, and it must be the same context for
+    /// every call on this target.
+    pub unsafe fn get_framebuffer_ref(&self) -> Result<u32, GlError> {
+        Ok(self.get_framebuffers()?.framebuffer)
+    }
+
+    /// Creates the framebuffer if needed and captures what a command needs
+    /// to render into it.
+    ///
+    /// # Safety
+    ///
+    /// A GL context must be current, and it must be the same context for
+    /// every call on this target.
+    pub unsafe fn bind(&self) -> Result<RenderTargetBinding, GlError> {
+        let framebuffers = self.get_framebuffers()?;
+        let (draw_framebuffer, resolve) = match &framebuffers.multisample {
+            Some(multisample) => (
+                multisample.framebuffer,
+                Some((multisample.framebuffer, framebuffers.framebuffer)),
+            ),
+            None => (framebuffers.framebuffer, None),
+        };
+        Ok(RenderTargetBinding {
+            draw_framebuffer,
+            resolve,
+            width: self.width as i32,
+            height: self.height as i32,
+            clear_color: self.clear_color,
+            depth: self.depth,
+        })
+    }
+
+    /// Reads the (resolved) color attachment back to the CPU. Multisampled
+    /// targets must have been resolved first, which [`RenderTargetBinding`]
+    /// does at the end of every pass.
+    ///
+    /// # Safety
+    ///
+    /// A GL context must be current, and it must be the same context for
+    /// every call on this target.
+    pub unsafe fn read_pixels(&self) -> Result<RgbaImage, GlError> {
+        let framebuffer = self.get_framebuffer_ref()?;
+        let mut image = RgbaImage::new(self.width, self.height);
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, framebuffer);
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            self.width as i32,
+            self.height as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            image.pixels_mut().as_mut_ptr() as *mut _,
+        );
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+        image.flip_vertically();
+        Ok(image)
+    }
+
+    unsafe fn get_framebuffers(&self) -> Result<&Framebuffers, GlError> {
+        if let Some(framebuffers) = self.framebuffers.get() {
+            return Ok(framebuffers);
+        }
+        let width = self.width as i32;
+        let height = self.height as i32;
+        let texture_id = self.color_texture.get_texture_id();
+        let mut framebuffer = 0;
+        gl::GenFramebuffers(1, &mut framebuffer);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+        gl::FramebufferTexture2D(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::TEXTURE_2D,
+            texture_id,
+            0,
+        );
+        // The resolve target only needs a depth buffer when it is drawn to
+        // directly.
+        let depth_renderbuffer =
+            (self.depth && self.samples == 0).then(|| create_depth_renderbuffer(0, width, height));
+        let status = check_framebuffer_status();
+        let multisample = (self.samples > 0).then(|| {
+            let samples = self.samples as i32;
+            let (internal_format, _, _) = self.color_texture.format().gl_formats();
+            let mut multisample_framebuffer = 0;
+            gl::GenFramebuffers(1, &mut multisample_framebuffer);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, multisample_framebuffer);
+            let mut color_renderbuffer = 0;
+            gl::GenRenderbuffers(1, &mut color_renderbuffer);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, color_renderbuffer);
+            gl::RenderbufferStorageMultisample(
+                gl::RENDERBUFFER,
+                samples,
+                internal_format as GLenum,
+                width,
+                height,
+            );
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::RENDERBUFFER,
+                color_renderbuffer,
+            );
+            let depth_renderbuffer = self
+                .depth
+                .then(|| create_depth_renderbuffer(samples, width, height));
+            MultisampleFramebuffer {
+                framebuffer: multisample_framebuffer,
+                color_renderbuffer,
+                depth_renderbuffer,
+            }
+        });
+        let multisample_status = multisample.as_ref().map(|_| check_framebuffer_status());
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        let framebuffers = Framebuffers {
+            framebuffer,
+            depth_renderbuffer,
+            multisample,
+        };
+        // An incomplete framebuffer is not cached, so every bind reports it.
+        if let Err(e) = status.and(multisample_status.transpose()) {
+            framebuffers.delete();
+            return Err(e);
+        }
+        Ok(self.framebuffers.get_or_init(|| framebuffers))
+    }
+}
+
+impl Framebuffers {
+    unsafe fn delete(&self) {
+        gl::DeleteFramebuffers(1, &self.framebuffer);
+        if let Some(depth_renderbuffer) = &self.depth_renderbuffer {
+            gl::DeleteRenderbuffers(1, depth_renderbuffer);
+        }
+        if let Some(multisample) = &self.multisample {
+            gl::DeleteFramebuffers(1, &multisample.framebuffer);
+            gl::DeleteRenderbuffers(1, &multisample.color_renderbuffer);
+            if let Some(depth_renderbuffer) = &multisample.depth_renderbuffer {
+                gl::DeleteRenderbuffers(1, depth_renderbuffer);
+            }
+        }
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        if let Some(framebuffers) = self.framebuffers.get() {
+            unsafe { framebuffers.delete() }
+        }
+    }
+}
+
+/// A [`RenderTarget`] captured for use inside render commands.
+#[derive(Clone, Copy, Debug)]
+pub struct RenderTargetBinding {
+    draw_framebuffer: u32,
+    resolve: Option<(u32, u32)>,
+    width: i32,
+    height: i32,
+    clear_color: [f32; 4],
+    depth: bool,
+}
+
+impl RenderTargetBinding {
+    /// Binds the framebuffer and sets the viewport to cover it.
+    ///
+    /// # Safety
+    ///
+    /// The GL context the target was created in must be current, and the
+    /// target must still be alive.
+    pub unsafe fn apply(&self) {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, self.draw_framebuffer);
+        gl::Viewport(0, 0, self.width, self.height);
+    }
+
+    /// Binds the framebuffer and clears it. Depth writes have to be enabled
+    /// for the depth buffer to be cleared, so the default state is applied
+    /// first.
+    ///
+    /// # Safety
+    ///
+    /// As for [`apply`](RenderTargetBinding::apply). `gl_state` must track
+    /// the same context.
+    pub unsafe fn begin(&self, gl_state: &GlStateTracker) {
+        self.apply();
+        gl_state.apply(&RenderState::default());
+        gl::ClearBufferfv(gl::COLOR, 0, self.clear_color.as_ptr());
+        if self.depth {
+            gl::ClearBufferfv(gl::DEPTH, 0, &1.0);
+        }
+    }
+
+    /// Resolves multisampled rendering into the color texture.
+    ///
+    /// # Safety
+    ///
+    /// As for [`apply`](RenderTargetBinding::apply).
+    pub unsafe fn resolve(&self) {
+        if let Some((from, to)) = self.resolve {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, from);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, to);
+            gl::BlitFramebuffer(
+                0,
+                0,
+                self.width,
+                self.height,
+                0,
+                0,
+                self.width,
+                self.height,
+                gl::COLOR_BUFFER_BIT,
+                gl::NEAREST,
+            );
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub(crate) fn draw_framebuffer(&self) -> u32 {
+        self.draw_framebuffer
+    }
+}
+
+fn color_texture(width: u32, height: u32, format: PixelFormat) -> Texture2D {
+    Texture2D::empty(width, height, format).with_options(
+        TextureOptions::default()
+            .with_wrap(TextureWrap::ClampToEdge)
+            .with_mipmaps(false),
+    )
+}
+
+unsafe fn create_depth_renderbuffer(samples: i32, width: i32, height: i32) -> u32 {
+    let mut renderbuffer = 0;
+    gl::GenRenderbuffers(1, &mut renderbuffer);
+    gl::BindRenderbuffer(gl::RENDERBUFFER, renderbuffer);
+    gl::RenderbufferStorageMultisample(
+        gl::RENDERBUFFER,
+        samples,
+        gl::DEPTH_COMPONENT24,
+        width,
+        height,
+    );
+    gl::FramebufferRenderbuffer(
+        gl::FRAMEBUFFER,
+        gl::DEPTH_ATTACHMENT,
+        gl::RENDERBUFFER,
+        renderbuffer,
+    );
+    renderbuffer
+}
+
+unsafe fn check_framebuffer_status() -> Result<(), GlError> {
+    match gl::CheckFramebufferStatus(gl::FRAMEBUFFER) {
+        gl::FRAMEBUFFER_COMPLETE => Ok(()),
+        status => Err(GlError::IncompleteFramebuffer(status)),
+    }
+}
@@ -0,0 +1,109 @@
+/// An 8-bit RGBA image, rows stored top to bottom.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RgbaImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    /// Returns `None` when `pixels` does not hold exactly `width * height`
+    /// RGBA pixels.
+    pub fn from_raw(width: u32, height: u32, pixels: Vec<u8>) -> Option<Self> {
+        (pixels.len() == width as usize * height as usize * 4).then_some(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [u8] {
+        &mut self.pixels
+    }
+
+    pub fn into_raw(self) -> Vec<u8> {
+        self.pixels
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let index = self.index(x, y);
+        self.pixels[index..index + 4].try_into().unwrap()
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, pixel: [u8; 4]) {
+        let index = self.index(x, y);
+        self.pixels[index..index + 4].copy_from_slice(&pixel);
+    }
+
+    /// Swaps rows top to bottom, e.g. to convert from GL's bottom-left
+    /// origin.
+    pub fn flip_vertically(&mut self) {
+        let row_length = self.width as usize * 4;
+        let height = self.height as usize;
+        for row in 0..height / 2 {
+            let (top, bottom) = self.pixels.split_at_mut((height - row - 1) * row_length);
+            top[row * row_length..(row + 1) * row_length]
+                .swap_with_slice(&mut bottom[..row_length]);
+        }
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        assert!(
+            x < self.width && y < self.height,
+            "pixel ({x}, {y}) out of bounds"
+        );
+        (y as usize * self.width as usize + x as usize) * 4
+    }
+}
+
+#[cfg(feature = "image")]
+impl RgbaImage {
+    /// Loads any image format enabled in the `image` crate, converted to
+    /// RGBA.
+    pub fn open<P>(path: P) -> Result<Self, image::ImageError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let image = image::open(path)?.into_rgba8();
+        let (width, height) = image.dimensions();
+        Ok(Self {
+            width,
+            height,
+            pixels: image.into_raw(),
+        })
+    }
+
+    pub fn save_png<P>(&self, path: P) -> Result<(), image::ImageError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        image::save_buffer_with_format(
+            path,
+            &self.pixels,
+            self.width,
+            self.height,
+            image::ExtendedColorType::Rgba8,
+            image::ImageFormat::Png,
+        )
+    }
+}
@@ -0,0 +1,421 @@
+use super::errors::GlError;
+use std::{borrow::Cow, path::Path};
+
+const DEFINES_FILE: &str = "<defines>";
+
+/// Where `#include "name"` directives are resolved from.
+pub enum ShaderLibrary {
+    /// Files compiled into the binary, as `(name, source)` pairs, usually
+    /// built with `include_str!`.
+    Embedded(&'static [(&'static str, &'static str)]),
+    /// A directory on disk. Included files are watched when shader hot
+    /// reload is enabled.
+    Directory(&'static str),
+}
+
+impl ShaderLibrary {
+    /// Returns the source of `name` and, for files on disk, their path.
+    fn load(&self, name: &str) -> Result<(Cow<'static, str>, Option<String>), String> {
+        match self {
+            ShaderLibrary::Embedded(files) => files
+                .iter()
+                .find(|(file_name, _)| *file_name == name)
+                .map(|(_, source)| (Cow::Borrowed(*source), None))
+                .ok_or_else(|| format!("unknown include \"{name}\"")),
+            ShaderLibrary::Directory(directory) => {
+                let path = Path::new(directory).join(name);
+                let source = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("unable to read {}: {e}", path.display()))?;
+                Ok((
+                    Cow::Owned(source),
+                    Some(path.to_string_lossy().into_owned()),
+                ))
+            }
+        }
+    }
+}
+
+/// Shader source after resolving includes and injecting defines, along with
+/// where every line came from.
+#[derive(Clone, Debug)]
+pub struct PreprocessedSource {
+    text: String,
+    files: Vec<String>,
+    lines: Vec<(usize, u32)>,
+    dependencies: Vec<String>,
+}
+
+impl PreprocessedSource {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Files on disk that were included.
+    pub fn dependencies(&self) -> &[String] {
+        &self.dependencies
+    }
+
+    /// Maps a 1-based line of the output back to its file and line.
+    pub fn source_location(&self, line: u32) -> Option<(&str, u32)> {
+        let (file, original_line) = self.lines.get((line as usize).checked_sub(1)?)?;
+        Some((&self.files[*file], *original_line))
+    }
+
+    /// Rewrites the locations in a driver's info log, such as `0:12(5)`,
+    /// `0(12)` or `0:12:`, to `file:line` of the original sources.
+    pub fn rewrite_log(&self, log: &str) -> String {
+        log.lines()
+            .map(|line| match find_location(line) {
+                Some((start, end, output_line)) => match self.source_location(output_line) {
+                    Some((file, original_line)) => {
+                        format!("{}{file}:{original_line}{}", &line[..start], &line[end..])
+                    }
+                    None => line.to_string(),
+                },
+                None => line.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Resolves `#include "name"` directives through `library`, each file being
+/// included at most once, and inserts a `#define` for every pair of
+/// `defines` right after the `#version` directive.
+pub fn preprocess(
+    source: &str,
+    name: &str,
+    library: Option<&ShaderLibrary>,
+    defines: &[(&str, &str)],
+) -> Result<PreprocessedSource, GlError> {
+    let mut preprocessor = Preprocessor {
+        library,
+        output: PreprocessedSource {
+            text: String::with_capacity(source.len()),
+            files: Vec::new(),
+            lines: Vec::new(),
+            dependencies: Vec::new(),
+        },
+        stack: Vec::new(),
+    };
+    let main_file = preprocessor.add_file(name);
+    let defines_file = preprocessor.add_file(DEFINES_FILE);
+    let mut defines_pending = true;
+    for (index, line) in source.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let is_version = trimmed.starts_with("#version");
+        let is_preamble = trimmed.is_empty() || trimmed.starts_with("//");
+        if defines_pending && !is_version && !is_preamble {
+            preprocessor.push_defines(defines, defines_file);
+            defines_pending = false;
+        }
+        preprocessor.process_line(line, main_file, index as u32 + 1)?;
+        if defines_pending && is_version {
+            preprocessor.push_defines(defines, defines_file);
+            defines_pending = false;
+        }
+    }
+    if defines_pending {
+        preprocessor.push_defines(defines, defines_file);
+    }
+    Ok(preprocessor.output)
+}
+
+struct Preprocessor<'a> {
+    library: Option<&'a ShaderLibrary>,
+    output: PreprocessedSource,
+    stack: Vec<String>,
+}
+
+impl Preprocessor<'_> {
+    fn add_file(&mut self, name: &str) -> usize {
+        self.output.files.push(name.to_string());
+        self.output.files.len() - 1
+    }
+
+    fn push_line(&mut self, line: &str, file: usize, line_number: u32) {
+        self.output.text.push_str(line);
+        self.output.text.push('\n');
+        self.output.lines.push((file, line_number));
+    }
+
+    fn push_defines(&mut self, defines: &[(&str, &str)], file: usize) {
+        for (index, (name, value)) in defines.iter().enumerate() {
+            self.push_line(&format!("#define {name} {value}"), file, index as u32 + 1);
+        }
+    }
+
+    fn process_line(&mut self, line: &str, file: usize, line_number: u32) -> Result<(), GlError> {
+        let Some(directive) = line.trim_start().strip_prefix("#include") else {
+            self.push_line(line, file, line_number);
+            return Ok(());
+        };
+        let location = format!("{}:{line_number}", self.output.files[file]);
+        let name = parse_include(directive)
+            .ok_or_else(|| GlError::ShaderPreprocess(format!("{location}: malformed #include")))?;
+        if self.stack.iter().any(|included| included == name) {
+            return Err(GlError::ShaderPreprocess(format!(
+                "{location}: recursive include of \"{name}\""
+            )));
+        }
+        // Include guards are implicit, every file is only included once.
+        if self.output.files.iter().any(|included| included == name) {
+            return Ok(());
+        }
+        let library = self.library.ok_or_else(|| {
+            GlError::ShaderPreprocess(format!("{location}: #include without a shader library"))
+        })?;
+        let (source, path) = library
+            .load(name)
+            .map_err(|e| GlError::ShaderPreprocess(format!("{location}: {e}")))?;
+        self.output.dependencies.extend(path);
+        let included_file = self.add_file(name);
+        self.stack.push(name.to_string());
+        for (index, line) in source.lines().enumerate() {
+            self.process_line(line, included_file, index as u32 + 1)?;
+        }
+        self.stack.pop();
+        Ok(())
+    }
+}
+
+fn parse_include(directive: &str) -> Option<&str> {
+    let directive = directive.trim();
+    let (open, close) = match directive.chars().next()? {
+        '"' => ('"', '"'),
+        '<' => ('<', '>'),
+        _ => return None,
+    };
+    let rest = directive.strip_prefix(open)?;
+    let end = rest.find(close)?;
+    Some(&rest[..end])
+}
+
+/// Finds the first `<source>:<line>` or `<source>(<line>)` in `line`, and
+/// returns its byte range and the line number.
+fn find_location(line: &str) -> Option<(usize, usize, u32)> {
+    let bytes = line.as_bytes();
+    let mut start = 0;
+    while start < bytes.len() {
+        if !bytes[start].is_ascii_digit() || (start > 0 && bytes[start - 1].is_ascii_alphanumeric())
+        {
+            start += 1;
+            continue;
+        }
+        let source_end = start + digits(&bytes[start..]);
+        let separator = bytes.get(source_end).copied();
+        let line_start = source_end + 1;
+        let line_end = line_start + digits(bytes.get(line_start..).unwrap_or_default());
+        if matches!(separator, Some(b':') | Some(b'(')) && line_end > line_start {
+            let end = match separator {
+                Some(b'(') if bytes.get(line_end) == Some(&b')') => line_end + 1,
+                Some(b'(') => {
+                    start = line_end;
+                    continue;
+                }
+                _ => line_end,
+            };
+            let line_number = line[line_start..line_end].parse().ok()?;
+            return Some((start, end, line_number));
+        }
+        start = source_end.max(start + 1);
+    }
+    None
+}
+
+fn digits(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .take_while(|byte| byte.is_ascii_digit())
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static LIBRARY: ShaderLibrary = ShaderLibrary::Embedded(&[
+        (
+            "light.glsl",
+            "#include \"math.glsl\"\nfloat light() { return PI; }",
+        ),
+        ("math.glsl", "#define PI 3.14159"),
+        ("loop_a.glsl", "#include \"loop_b.glsl\""),
+        ("loop_b.glsl", "// b\n#include <loop_a.glsl>"),
+    ]);
+
+    fn lines(source: &PreprocessedSource) -> Vec<&str> {
+        source.text().lines().collect()
+    }
+
+    fn error(result: Result<PreprocessedSource, GlError>) -> String {
+        match result {
+            Err(GlError::ShaderPreprocess(message)) => message,
+            Err(e) => panic!("unexpected error {e}"),
+            Ok(source) => panic!("expected an error, got {:?}", source.text()),
+        }
+    }
+
+    #[test]
+    fn sources_without_directives_are_unchanged() {
+        let source = preprocess("void main() {}\n", "main.frag", None, &[]).unwrap();
+        assert_eq!(source.text(), "void main() {}\n");
+        assert_eq!(source.source_location(1), Some(("main.frag", 1)));
+        assert!(source.dependencies().is_empty());
+    }
+
+    #[test]
+    fn defines_go_right_after_the_version() {
+        let source = preprocess(
+            "// header\n#version 330 core\nvoid main() {}",
+            "main.frag",
+            None,
+            &[("SHADOWS", "1"), ("LIGHTS", "4")],
+        )
+        .unwrap();
+        assert_eq!(
+            lines(&source),
+            [
+                "// header",
+                "#version 330 core",
+                "#define SHADOWS 1",
+                "#define LIGHTS 4",
+                "void main() {}",
+            ]
+        );
+        assert_eq!(source.source_location(2), Some(("main.frag", 2)));
+        assert_eq!(source.source_location(3), Some((DEFINES_FILE, 1)));
+        assert_eq!(source.source_location(4), Some((DEFINES_FILE, 2)));
+        assert_eq!(source.source_location(5), Some(("main.frag", 3)));
+    }
+
+    #[test]
+    fn defines_without_a_version_go_before_the_first_statement() {
+        let source = preprocess(
+            "// comment\n\nvoid main() {}",
+            "main.frag",
+            None,
+            &[("A", "1")],
+        )
+        .unwrap();
+        assert_eq!(
+            lines(&source),
+            ["// comment", "", "#define A 1", "void main() {}"]
+        );
+        let only_comments = preprocess("// nothing", "main.frag", None, &[("A", "1")]).unwrap();
+        assert_eq!(lines(&only_comments), ["// nothing", "#define A 1"]);
+    }
+
+    #[test]
+    fn includes_are_expanded_once_and_mapped_back() {
+        let source = preprocess(
+            "#version 330 core\n#include \"light.glsl\"\n#include \"math.glsl\"\nvoid main() {}",
+            "main.frag",
+            Some(&LIBRARY),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            lines(&source),
+            [
+                "#version 330 core",
+                "#define PI 3.14159",
+                "float light() { return PI; }",
+                "void main() {}",
+            ]
+        );
+        assert_eq!(source.source_location(2), Some(("math.glsl", 1)));
+        assert_eq!(source.source_location(3), Some(("light.glsl", 2)));
+        assert_eq!(source.source_location(4), Some(("main.frag", 4)));
+        assert_eq!(source.source_location(0), None);
+        assert_eq!(source.source_location(5), None);
+        // Embedded files are not watched.
+        assert!(source.dependencies().is_empty());
+    }
+
+    #[test]
+    fn recursive_includes_are_errors() {
+        let message = error(preprocess(
+            "#include \"loop_a.glsl\"",
+            "main.frag",
+            Some(&LIBRARY),
+            &[],
+        ));
+        assert_eq!(
+            message,
+            "loop_b.glsl:2: recursive include of \"loop_a.glsl\""
+        );
+    }
+
+    #[test]
+    fn bad_includes_are_errors_with_their_location() {
+        assert_eq!(
+            error(preprocess(
+                "\n#include \"missing.glsl\"",
+                "main.frag",
+                Some(&LIBRARY),
+                &[]
+            )),
+            "main.frag:2: unknown include \"missing.glsl\""
+        );
+        assert_eq!(
+            error(preprocess(
+                "#include math.glsl",
+                "main.frag",
+                Some(&LIBRARY),
+                &[]
+            )),
+            "main.frag:1: malformed #include"
+        );
+        assert_eq!(
+            error(preprocess("#include \"math.glsl\"", "main.frag", None, &[])),
+            "main.frag:1: #include without a shader library"
+        );
+    }
+
+    #[test]
+    fn find_location_understands_common_drivers() {
+        // Mesa
+        assert_eq!(
+            find_location("0:12(5): error: `x' undeclared"),
+            Some((0, 4, 12))
+        );
+        // NVIDIA
+        assert_eq!(
+            find_location("0(12) : error C1008: undefined variable"),
+            Some((0, 5, 12))
+        );
+        // AMD and Apple
+        assert_eq!(
+            find_location("ERROR: 0:12: 'x' : undeclared identifier"),
+            Some((7, 11, 12))
+        );
+    }
+
+    #[test]
+    fn find_location_skips_numbers_in_words() {
+        assert_eq!(find_location("vec4(1.0) is not a location"), None);
+        assert_eq!(find_location("no location here"), None);
+        assert_eq!(find_location("3 errors"), None);
+        assert_eq!(find_location("0(x) then 0:7:"), Some((10, 13, 7)));
+    }
+
+    #[test]
+    fn rewrite_log_points_at_the_original_files() {
+        let source = preprocess(
+            "#version 330 core\n#include \"light.glsl\"\nvoid main() { oops; }",
+            "main.frag",
+            Some(&LIBRARY),
+            &[("A", "1")],
+        )
+        .unwrap();
+        let log = "0:5(15): error: `oops' undeclared\n0(4) : warning: precision\nERROR: 0:3: bad define\n0:99: out of range";
+        assert_eq!(
+            source.rewrite_log(log),
+            "main.frag:3(15): error: `oops' undeclared\n\
+             light.glsl:2 : warning: precision\n\
+             ERROR: math.glsl:1: bad define\n\
+             0:99: out of range"
+        );
+    }
+}
@@ -0,0 +1,3 @@
+pub mod font;
+pub mod glyph_atlas;
+pub mod layout;
@@ -0,0 +1,52 @@
+use crate::common::errors::FontError;
+use ab_glyph::{FontArc, FontVec};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_FONT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A TrueType or OpenType font. Cloning is cheap and clones share their
+/// glyphs in the atlas.
+#[derive(Clone)]
+pub struct Font {
+    id: u64,
+    font: FontArc,
+}
+
+impl Font {
+    fn new(font: FontArc) -> Self {
+        Self {
+            id: NEXT_FONT_ID.fetch_add(1, Ordering::Relaxed),
+            font,
+        }
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, FontError> {
+        Ok(Self::new(FontArc::new(FontVec::try_from_vec(bytes)?)))
+    }
+
+    /// Uses font data embedded in the binary, e.g. with `include_bytes!`.
+    pub fn from_static(bytes: &'static [u8]) -> Result<Self, FontError> {
+        Ok(Self::new(FontArc::try_from_slice(bytes)?))
+    }
+
+    pub fn from_file<P>(path: P) -> Result<Self, FontError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub(crate) fn inner(&self) -> &FontArc {
+        &self.font
+    }
+}
+
+impl std::fmt::Debug for Font {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Font").field("id", &self.id).finish()
+    }
+}
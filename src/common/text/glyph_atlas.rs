@@ -0,0 +1,300 @@
+use super::layout::TextLayout;
+use crate::common::{
+    drawables::RendererContext,
+    texture::{Texture2D, TextureFilter, TextureOptions, TextureUpload, TextureWrap},
+};
+use ab_glyph::{Font as _, GlyphId, PxScale};
+use fnv::FnvHashMap;
+use nalgebra::{Point2, Vector2};
+use std::rc::Rc;
+use tracing::warn;
+
+const INITIAL_SIZE: u32 = 512;
+const MAX_SIZE: u32 = 4096;
+/// Empty pixels around each glyph, so linear filtering does not pick up
+/// its neighbours.
+const PADDING: u32 = 1;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+struct GlyphKey {
+    font_id: u64,
+    glyph_id: GlyphId,
+    size_bits: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct AtlasEntry {
+    /// Top-left corner of the bitmap relative to the pen position.
+    offset: Vector2<f32>,
+    size: Vector2<f32>,
+    uv_min: [f32; 2],
+    uv_max: [f32; 2],
+}
+
+/// A glyph bitmap to draw: screen or world rectangle and where it is in the
+/// atlas texture.
+#[derive(Clone)]
+pub struct GlyphQuad {
+    pub texture: Rc<Texture2D>,
+    pub min: Point2<f32>,
+    pub max: Point2<f32>,
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+}
+
+/// Glyphs rasterized on first use and packed in rows into an RGBA texture:
+/// white, with the coverage in alpha, so it can be tinted like a sprite.
+/// When the texture is full it is replaced by a larger one and glyphs are
+/// rasterized again as they are needed. At the maximum size it is emptied
+/// instead, at most once per frame; glyphs that still do not fit are skipped
+/// until the next frame.
+pub struct GlyphAtlas {
+    texture: Rc<Texture2D>,
+    size: u32,
+    entries: FnvHashMap<GlyphKey, Option<AtlasEntry>>,
+    cursor: (u32, u32),
+    row_height: u32,
+    uploads: Vec<TextureUpload>,
+    cleared_this_frame: bool,
+    skipped_glyphs: usize,
+}
+
+impl Default for GlyphAtlas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GlyphAtlas {
+    pub fn new() -> Self {
+        Self {
+            texture: Rc::new(create_texture(INITIAL_SIZE)),
+            size: INITIAL_SIZE,
+            entries: FnvHashMap::default(),
+            cursor: (PADDING, PADDING),
+            row_height: 0,
+            uploads: Vec::new(),
+            cleared_this_frame: false,
+            skipped_glyphs: 0,
+        }
+    }
+
+    pub fn texture(&self) -> &Rc<Texture2D> {
+        &self.texture
+    }
+
+    /// Rectangles for every visible glyph of `layout`, with its top-left
+    /// corner at `origin`. Glyphs missing from the atlas are rasterized.
+    ///
+    /// # Safety
+    ///
+    /// A GL context must be current, and it must be the same context for
+    /// every call on this atlas, since rasterizing creates the texture.
+    pub unsafe fn quads(&mut self, layout: &TextLayout, origin: Point2<f32>) -> Vec<GlyphQuad> {
+        let mut quads = Vec::with_capacity(layout.glyphs().len());
+        for glyph in layout.glyphs() {
+            let key = GlyphKey {
+                font_id: layout.font().id(),
+                glyph_id: glyph.id,
+                size_bits: layout.size().to_bits(),
+            };
+            let entry = match self.entries.get(&key) {
+                Some(entry) => *entry,
+                None => self.rasterize(key, layout),
+            };
+            if let Some(entry) = entry {
+                let pen = origin + glyph.position.coords;
+                let min = Point2::new(pen.x.round(), pen.y.round()) + entry.offset;
+                quads.push(GlyphQuad {
+                    texture: self.texture.clone(),
+                    min,
+                    max: min + entry.size,
+                    uv_min: entry.uv_min,
+                    uv_max: entry.uv_max,
+                });
+            }
+        }
+        quads
+    }
+
+    /// Texture writes for the glyphs added since the last call, to be
+    /// applied in a render command before the glyphs are drawn.
+    pub fn take_uploads(&mut self) -> Vec<TextureUpload> {
+        std::mem::take(&mut self.uploads)
+    }
+
+    /// Queues the pending texture writes as a render command, so they run
+    /// before any glyph drawn afterwards.
+    pub(crate) fn queue_uploads(&mut self, ctx: &mut RendererContext) {
+        let uploads = self.take_uploads();
+        if !uploads.is_empty() {
+            ctx.add_commands(move || unsafe {
+                for upload in &uploads {
+                    upload.apply();
+                }
+            });
+        }
+    }
+
+    pub(crate) fn end_frame(&mut self) {
+        if self.skipped_glyphs > 0 {
+            warn!(
+                "Skipped {} glyphs this frame, the glyph atlas is full",
+                self.skipped_glyphs
+            );
+        }
+        self.cleared_this_frame = false;
+        self.skipped_glyphs = 0;
+    }
+
+    unsafe fn rasterize(&mut self, key: GlyphKey, layout: &TextLayout) -> Option<AtlasEntry> {
+        let glyph = key.glyph_id.with_scale(PxScale::from(layout.size()));
+        let Some(outline) = layout.font().inner().outline_glyph(glyph) else {
+            self.entries.insert(key, None);
+            return None;
+        };
+        let bounds = outline.px_bounds();
+        let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+        if width + 2 * PADDING > MAX_SIZE || height + 2 * PADDING > MAX_SIZE {
+            warn!("Glyph of {width}x{height} pixels does not fit in the atlas");
+            self.entries.insert(key, None);
+            return None;
+        }
+        let Some((x, y)) = self.allocate(width, height) else {
+            self.skipped_glyphs += 1;
+            return None;
+        };
+        let mut pixels = vec![u8::MAX; width as usize * height as usize * 4];
+        for alpha in pixels.iter_mut().skip(3).step_by(4) {
+            *alpha = 0;
+        }
+        outline.draw(|glyph_x, glyph_y, coverage| {
+            let index = (glyph_y * width + glyph_x) as usize * 4 + 3;
+            if let Some(alpha) = pixels.get_mut(index) {
+                *alpha = (coverage.clamp(0.0, 1.0) * u8::MAX as f32).round() as u8;
+            }
+        });
+        let upload = self
+            .texture
+            .region_upload(x, y, width, height, pixels)
+            .expect("the bitmap has the size of the glyph");
+        self.uploads.push(upload);
+        let atlas_size = self.size as f32;
+        let entry = AtlasEntry {
+            offset: Vector2::new(bounds.min.x, bounds.min.y),
+            size: Vector2::new(width as f32, height as f32),
+            uv_min: [x as f32 / atlas_size, y as f32 / atlas_size],
+            uv_max: [
+                (x + width) as f32 / atlas_size,
+                (y + height) as f32 / atlas_size,
+            ],
+        };
+        self.entries.insert(key, Some(entry));
+        Some(entry)
+    }
+
+    /// Finds room for a bitmap, growing the atlas when it is full. The bitmap
+    /// must fit in an atlas of the maximum size.
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        loop {
+            let (mut x, mut y) = self.cursor;
+            if x + width + PADDING > self.size {
+                x = PADDING;
+                y += self.row_height + PADDING;
+                self.row_height = 0;
+            }
+            if y + height + PADDING <= self.size {
+                self.cursor = (x + width + PADDING, y);
+                self.row_height = self.row_height.max(height);
+                return Some((x, y));
+            }
+            if !self.grow() {
+                return None;
+            }
+        }
+    }
+
+    /// Starts over with an empty texture, twice as large unless the atlas is
+    /// already at its maximum size. Quads handed out before keep the old
+    /// texture alive until they are drawn. Returns `false` when the atlas
+    /// was already emptied at its maximum size this frame.
+    fn grow(&mut self) -> bool {
+        if self.size == MAX_SIZE {
+            if self.cleared_this_frame {
+                return false;
+            }
+            warn!("The glyph atlas is full at {MAX_SIZE}x{MAX_SIZE} pixels, clearing it");
+            self.cleared_this_frame = true;
+        }
+        self.size = (self.size * 2).min(MAX_SIZE);
+        self.texture = Rc::new(create_texture(self.size));
+        self.entries.clear();
+        self.cursor = (PADDING, PADDING);
+        self.row_height = 0;
+        true
+    }
+}
+
+fn create_texture(size: u32) -> Texture2D {
+    let pixels = vec![0; size as usize * size as usize * 4];
+    Texture2D::from_rgba8(size, size, pixels)
+        .expect("the atlas is square")
+        .with_options(
+            TextureOptions::default()
+                .with_wrap(TextureWrap::ClampToEdge)
+                .with_filter(TextureFilter::Linear)
+                .with_mipmaps(false),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bitmaps_are_packed_in_rows_with_padding() {
+        let mut atlas = GlyphAtlas::new();
+        assert_eq!(atlas.allocate(10, 20), Some((1, 1)));
+        assert_eq!(atlas.allocate(30, 5), Some((12, 1)));
+        let row_end = INITIAL_SIZE - 43 - PADDING;
+        assert_eq!(atlas.allocate(row_end, 8), Some((43, 1)));
+        // The next row starts below the tallest bitmap of the previous one.
+        assert_eq!(atlas.allocate(1, 1), Some((1, 22)));
+        assert_eq!(atlas.size, INITIAL_SIZE);
+    }
+
+    #[test]
+    fn full_atlases_grow_and_forget_their_glyphs() {
+        let mut atlas = GlyphAtlas::new();
+        let texture = atlas.texture().clone();
+        atlas.entries.insert(
+            GlyphKey {
+                font_id: 0,
+                glyph_id: GlyphId(1),
+                size_bits: 0,
+            },
+            None,
+        );
+        let side = INITIAL_SIZE - 2 * PADDING;
+        assert_eq!(atlas.allocate(side, side), Some((1, 1)));
+        assert_eq!(atlas.allocate(side, side), Some((1, 1)));
+        assert_eq!(atlas.size, INITIAL_SIZE * 2);
+        assert!(atlas.entries.is_empty());
+        assert!(!Rc::ptr_eq(&texture, atlas.texture()));
+        assert_eq!(atlas.texture().width(), INITIAL_SIZE * 2);
+    }
+
+    #[test]
+    fn full_atlases_at_the_maximum_size_clear_once_per_frame() {
+        let mut atlas = GlyphAtlas::new();
+        atlas.size = MAX_SIZE;
+        let side = MAX_SIZE - 2 * PADDING;
+        assert_eq!(atlas.allocate(side, side), Some((1, 1)));
+        assert_eq!(atlas.allocate(side, side), Some((1, 1)));
+        assert_eq!(atlas.size, MAX_SIZE);
+        assert!(atlas.cleared_this_frame);
+        assert_eq!(atlas.allocate(side, side), None);
+        atlas.end_frame();
+        assert_eq!(atlas.allocate(side, side), Some((1, 1)));
+    }
+}
@@ -0,0 +1,353 @@
+use super::font::Font;
+use crate::common::color::Color;
+use ab_glyph::{Font as _, FontArc, GlyphId, PxScale, PxScaleFont, ScaleFont};
+use nalgebra::{Point2, Vector2};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// How text is drawn. `size` is the height of a line in pixels, without
+/// line spacing.
+#[derive(Clone, Debug)]
+pub struct TextStyle {
+    pub font: Font,
+    pub size: f32,
+    pub color: Color,
+    pub align: TextAlign,
+    /// Lines longer than this are wrapped, between words when possible.
+    pub max_width: Option<f32>,
+    /// Distance between baselines, as a multiple of the font's own.
+    pub line_spacing: f32,
+    /// Sprite layer, for 2D text.
+    pub layer: i32,
+}
+
+impl TextStyle {
+    pub fn new(font: Font, size: f32) -> Self {
+        Self {
+            font,
+            size,
+            color: Color::WHITE,
+            align: TextAlign::Left,
+            max_width: None,
+            line_spacing: 1.0,
+            layer: 0,
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn with_max_width(mut self, max_width: f32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    pub fn with_line_spacing(mut self, line_spacing: f32) -> Self {
+        self.line_spacing = line_spacing;
+        self
+    }
+
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LaidOutGlyph {
+    pub id: GlyphId,
+    pub character: char,
+    /// Pen position on the baseline, relative to the top-left corner of the
+    /// text.
+    pub position: Point2<f32>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct PendingGlyph {
+    id: GlyphId,
+    character: char,
+    x: f32,
+    advance: f32,
+}
+
+#[derive(Default)]
+struct Line {
+    glyphs: Vec<PendingGlyph>,
+}
+
+impl Line {
+    /// Width up to the end of the last visible glyph.
+    fn width(&self) -> f32 {
+        self.glyphs
+            .iter()
+            .rev()
+            .find(|glyph| !glyph.character.is_whitespace())
+            .map_or(0.0, |glyph| glyph.x + glyph.advance)
+    }
+}
+
+/// Text broken into lines and positioned glyph by glyph, with kerning.
+#[derive(Clone, Debug)]
+pub struct TextLayout {
+    font: Font,
+    size: f32,
+    glyphs: Vec<LaidOutGlyph>,
+    line_count: usize,
+    bounds: Vector2<f32>,
+}
+
+impl TextLayout {
+    pub fn new(text: &str, style: &TextStyle) -> Self {
+        let font = style.font.inner().as_scaled(PxScale::from(style.size));
+        let lines = text
+            .split('\n')
+            .flat_map(|paragraph| wrap(&font, paragraph, style.max_width))
+            .collect::<Vec<_>>();
+        let widest = lines.iter().map(Line::width).fold(0.0, f32::max);
+        let box_width = style.max_width.unwrap_or(widest);
+        let line_advance = font.height() + font.line_gap();
+        let line_advance = line_advance * style.line_spacing;
+        let mut glyphs = Vec::new();
+        for (index, line) in lines.iter().enumerate() {
+            let offset = match style.align {
+                TextAlign::Left => 0.0,
+                TextAlign::Center => (box_width - line.width()) / 2.0,
+                TextAlign::Right => box_width - line.width(),
+            };
+            let baseline = font.ascent() + index as f32 * line_advance;
+            glyphs.extend(line.glyphs.iter().map(|glyph| LaidOutGlyph {
+                id: glyph.id,
+                character: glyph.character,
+                position: Point2::new(offset + glyph.x, baseline),
+            }));
+        }
+        let height = match lines.len() {
+            0 => 0.0,
+            count => font.height() + (count - 1) as f32 * line_advance,
+        };
+        Self {
+            font: style.font.clone(),
+            size: style.size,
+            glyphs,
+            line_count: lines.len(),
+            bounds: Vector2::new(box_width, height),
+        }
+    }
+
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    pub fn glyphs(&self) -> &[LaidOutGlyph] {
+        &self.glyphs
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_count
+    }
+
+    /// Width and height of the text. The width is the wrapping width when
+    /// there is one.
+    pub fn bounds(&self) -> Vector2<f32> {
+        self.bounds
+    }
+}
+
+fn wrap(font: &PxScaleFont<&FontArc>, paragraph: &str, max_width: Option<f32>) -> Vec<Line> {
+    let tab_advance = font.h_advance(font.glyph_id(' ')) * 4.0;
+    let mut lines = vec![Line::default()];
+    let mut pen = 0.0;
+    let mut previous: Option<GlyphId> = None;
+    // Index of the first glyph after the last whitespace on the line.
+    let mut break_at: Option<usize> = None;
+    for character in paragraph.chars() {
+        if character.is_control() && character != '\t' {
+            continue;
+        }
+        let id = font.glyph_id(character);
+        let advance = match character {
+            '\t' => tab_advance,
+            _ => font.h_advance(id),
+        };
+        let x = pen + previous.map_or(0.0, |previous| font.kern(previous, id));
+        let line = lines.last_mut().expect("there is always a line");
+        let overflows = max_width.is_some_and(|max_width| x + advance > max_width);
+        if overflows && !character.is_whitespace() && !line.glyphs.is_empty() {
+            // Move the word being typed to a new line, or break inside it
+            // when it is the only one.
+            let moved = match break_at {
+                Some(index) if index < line.glyphs.len() => line.glyphs.split_off(index),
+                _ => Vec::new(),
+            };
+            let shift = moved.first().map_or(0.0, |glyph| glyph.x);
+            let moved = moved
+                .into_iter()
+                .map(|glyph| PendingGlyph {
+                    x: glyph.x - shift,
+                    ..glyph
+                })
+                .collect::<Vec<_>>();
+            pen = moved.last().map_or(0.0, |glyph| glyph.x + glyph.advance);
+            previous = moved.last().map(|glyph| glyph.id);
+            lines.push(Line { glyphs: moved });
+            break_at = None;
+        }
+        let x = pen + previous.map_or(0.0, |previous| font.kern(previous, id));
+        let line = lines.last_mut().expect("there is always a line");
+        line.glyphs.push(PendingGlyph {
+            id,
+            character,
+            x,
+            advance,
+        });
+        pen = x + advance;
+        previous = Some(id);
+        if character.is_whitespace() {
+            break_at = Some(line.glyphs.len());
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// At 100 pixels: ascent 80, line height 100, advances of 25 for spaces,
+    /// 20 for "i", 80 for "M" and "W" and 50 otherwise, and kerning of -10
+    /// between "A" and "V". See `tests/fonts/make_boxes.py`.
+    fn style() -> TextStyle {
+        let font = Font::from_static(include_bytes!("../../../tests/fonts/boxes.ttf")).unwrap();
+        TextStyle::new(font, 100.0)
+    }
+
+    fn lines(layout: &TextLayout) -> Vec<String> {
+        let mut lines: Vec<(f32, String)> = Vec::new();
+        for glyph in layout.glyphs() {
+            match lines.last_mut() {
+                Some((baseline, line)) if *baseline == glyph.position.y => {
+                    line.push(glyph.character)
+                }
+                _ => lines.push((glyph.position.y, glyph.character.to_string())),
+            }
+        }
+        lines.into_iter().map(|(_, line)| line).collect()
+    }
+
+    fn positions(layout: &TextLayout) -> Vec<(f32, f32)> {
+        layout
+            .glyphs()
+            .iter()
+            .map(|glyph| (glyph.position.x, glyph.position.y))
+            .collect()
+    }
+
+    #[test]
+    fn single_lines_advance_glyph_by_glyph() {
+        let layout = TextLayout::new("aiM b", &style());
+        assert_eq!(
+            positions(&layout),
+            [
+                (0.0, 80.0),
+                (50.0, 80.0),
+                (70.0, 80.0),
+                (150.0, 80.0),
+                (175.0, 80.0)
+            ]
+        );
+        assert_eq!(layout.line_count(), 1);
+        assert_eq!(layout.bounds(), Vector2::new(225.0, 100.0));
+    }
+
+    #[test]
+    fn kerning_moves_pairs_closer() {
+        let layout = TextLayout::new("AVA", &style());
+        assert_eq!(
+            positions(&layout),
+            [(0.0, 80.0), (40.0, 80.0), (80.0, 80.0)]
+        );
+        assert_eq!(layout.bounds().x, 130.0);
+    }
+
+    #[test]
+    fn tabs_are_four_spaces_wide() {
+        let layout = TextLayout::new("a\tb", &style());
+        assert_eq!(layout.glyphs()[2].position.x, 150.0);
+    }
+
+    #[test]
+    fn newlines_start_lines() {
+        let style = style().with_line_spacing(1.5);
+        let layout = TextLayout::new("ab\n\ncd", &style);
+        assert_eq!(lines(&layout), ["ab", "cd"]);
+        assert_eq!(layout.line_count(), 3);
+        assert_eq!(layout.glyphs()[2].position, Point2::new(0.0, 380.0));
+        assert_eq!(layout.bounds(), Vector2::new(100.0, 400.0));
+        assert_eq!(TextLayout::new("", &style).line_count(), 1);
+    }
+
+    #[test]
+    fn wrapping_breaks_between_words() {
+        let style = style().with_max_width(140.0);
+        let layout = TextLayout::new("a bc de", &style);
+        assert_eq!(lines(&layout), ["a ", "bc ", "de"]);
+        assert_eq!(layout.line_count(), 3);
+        assert_eq!(layout.glyphs()[2].position, Point2::new(0.0, 180.0));
+        assert_eq!(layout.glyphs()[5].position, Point2::new(0.0, 280.0));
+        // The wrapping width, not the widest line.
+        assert_eq!(layout.bounds(), Vector2::new(140.0, 300.0));
+    }
+
+    #[test]
+    fn trailing_spaces_never_wrap() {
+        let style = style().with_max_width(100.0);
+        let layout = TextLayout::new("ab    cd", &style);
+        assert_eq!(lines(&layout), ["ab    ", "cd"]);
+    }
+
+    #[test]
+    fn long_words_break_between_characters() {
+        let style = style().with_max_width(120.0);
+        let layout = TextLayout::new("abcde f", &style);
+        assert_eq!(lines(&layout), ["ab", "cd", "e ", "f"]);
+    }
+
+    #[test]
+    fn center_and_right_alignment_use_the_widest_line() {
+        let style = style().with_align(TextAlign::Center);
+        let layout = TextLayout::new("ab\nabcd ", &style);
+        assert_eq!(layout.glyphs()[0].position.x, 50.0);
+        assert_eq!(layout.glyphs()[2].position.x, 0.0);
+        let style = style.with_align(TextAlign::Right);
+        let layout = TextLayout::new("ab\nabcd", &style);
+        assert_eq!(layout.glyphs()[0].position.x, 100.0);
+        assert_eq!(layout.glyphs()[2].position.x, 0.0);
+        assert_eq!(layout.bounds().x, 200.0);
+    }
+
+    #[test]
+    fn alignment_uses_the_wrapping_width() {
+        let style = style().with_max_width(300.0).with_align(TextAlign::Center);
+        let layout = TextLayout::new("ab", &style);
+        assert_eq!(layout.glyphs()[0].position.x, 100.0);
+        let layout = TextLayout::new("ab", &style.with_align(TextAlign::Right));
+        assert_eq!(layout.glyphs()[0].position.x, 200.0);
+    }
+}
@@ -0,0 +1,450 @@
+use super::{
+    errors::TextureError,
+    gl::{
+        self,
+        types::{GLenum, GLint},
+    },
+};
+use std::cell::{OnceCell, RefCell};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PixelFormat {
+    R8,
+    Rgb8,
+    Rgba8,
+    R32F,
+    Rgb32F,
+    Rgba32F,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::R8 => 1,
+            PixelFormat::Rgb8 => 3,
+            PixelFormat::Rgba8 => 4,
+            PixelFormat::R32F => 4,
+            PixelFormat::Rgb32F => 12,
+            PixelFormat::Rgba32F => 16,
+        }
+    }
+
+    /// Internal format, pixel format and component type, as passed to
+    /// `glTexImage2D`.
+    pub(crate) fn gl_formats(self) -> (GLint, GLenum, GLenum) {
+        match self {
+            PixelFormat::R8 => (gl::R8 as GLint, gl::RED, gl::UNSIGNED_BYTE),
+            PixelFormat::Rgb8 => (gl::RGB8 as GLint, gl::RGB, gl::UNSIGNED_BYTE),
+            PixelFormat::Rgba8 => (gl::RGBA8 as GLint, gl::RGBA, gl::UNSIGNED_BYTE),
+            PixelFormat::R32F => (gl::R32F as GLint, gl::RED, gl::FLOAT),
+            PixelFormat::Rgb32F => (gl::RGB32F as GLint, gl::RGB, gl::FLOAT),
+            PixelFormat::Rgba32F => (gl::RGBA32F as GLint, gl::RGBA, gl::FLOAT),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TextureWrap {
+    #[default]
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+impl TextureWrap {
+    fn gl_wrap(self) -> GLint {
+        (match self {
+            TextureWrap::Repeat => gl::REPEAT,
+            TextureWrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+            TextureWrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+        }) as GLint
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TextureFilter {
+    Nearest,
+    #[default]
+    Linear,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TextureOptions {
+    pub wrap_s: TextureWrap,
+    pub wrap_t: TextureWrap,
+    pub min_filter: TextureFilter,
+    pub mag_filter: TextureFilter,
+    pub mipmaps: bool,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self {
+            wrap_s: TextureWrap::default(),
+            wrap_t: TextureWrap::default(),
+            min_filter: TextureFilter::default(),
+            mag_filter: TextureFilter::default(),
+            mipmaps: true,
+        }
+    }
+}
+
+impl TextureOptions {
+    /// Crisp, unfiltered sampling, as wanted for pixel art.
+    pub fn pixelated() -> Self {
+        Self {
+            min_filter: TextureFilter::Nearest,
+            mag_filter: TextureFilter::Nearest,
+            mipmaps: false,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_wrap(mut self, wrap: TextureWrap) -> Self {
+        self.wrap_s = wrap;
+        self.wrap_t = wrap;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: TextureFilter) -> Self {
+        self.min_filter = filter;
+        self.mag_filter = filter;
+        self
+    }
+
+    pub fn with_mipmaps(mut self, mipmaps: bool) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    fn gl_min_filter(&self) -> GLint {
+        (match (self.min_filter, self.mipmaps) {
+            (TextureFilter::Nearest, false) => gl::NEAREST,
+            (TextureFilter::Linear, false) => gl::LINEAR,
+            (TextureFilter::Nearest, true) => gl::NEAREST_MIPMAP_NEAREST,
+            (TextureFilter::Linear, true) => gl::LINEAR_MIPMAP_LINEAR,
+        }) as GLint
+    }
+
+    fn gl_mag_filter(&self) -> GLint {
+        (match self.mag_filter {
+            TextureFilter::Nearest => gl::NEAREST,
+            TextureFilter::Linear => gl::LINEAR,
+        }) as GLint
+    }
+}
+
+/// A 2D texture. Like [`ProgramWrapper`], the GL object is only created on
+/// first use, so textures can be built before the context exists. The pixel
+/// data is kept on the CPU until then. Rows go from the top of the image to
+/// the bottom, so `v = 0` is the top edge.
+///
+/// [`ProgramWrapper`]: super::wrappers::program_wrapper::ProgramWrapper
+pub struct Texture2D {
+    texture_id: OnceCell<u32>,
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    options: TextureOptions,
+    pixels: RefCell<Option<Vec<u8>>>,
+}
+
+impl Texture2D {
+    pub fn from_pixels(
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+        pixels: Vec<u8>,
+    ) -> Result<Self, TextureError> {
+        let expected = width as usize * height as usize * format.bytes_per_pixel();
+        if pixels.len() != expected {
+            return Err(TextureError::SizeMismatch {
+                expected,
+                actual: pixels.len(),
+            });
+        }
+        Ok(Self {
+            texture_id: OnceCell::new(),
+            width,
+            height,
+            format,
+            options: TextureOptions::default(),
+            pixels: RefCell::new(Some(pixels)),
+        })
+    }
+
+    pub fn from_rgba8(width: u32, height: u32, pixels: Vec<u8>) -> Result<Self, TextureError> {
+        Self::from_pixels(width, height, PixelFormat::Rgba8, pixels)
+    }
+
+    pub fn from_rgb8(width: u32, height: u32, pixels: Vec<u8>) -> Result<Self, TextureError> {
+        Self::from_pixels(width, height, PixelFormat::Rgb8, pixels)
+    }
+
+    pub fn from_r8(width: u32, height: u32, pixels: Vec<u8>) -> Result<Self, TextureError> {
+        Self::from_pixels(width, height, PixelFormat::R8, pixels)
+    }
+
+    /// `format` must be one of the float formats.
+    pub fn from_f32(
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+        pixels: &[f32],
+    ) -> Result<Self, TextureError> {
+        if !matches!(
+            format,
+            PixelFormat::R32F | PixelFormat::Rgb32F | PixelFormat::Rgba32F
+        ) {
+            return Err(TextureError::NotFloatFormat(format));
+        }
+        let bytes = pixels
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        Self::from_pixels(width, height, format, bytes)
+    }
+
+    /// A texture without initial contents, e.g. to render into.
+    pub fn empty(width: u32, height: u32, format: PixelFormat) -> Self {
+        Self {
+            texture_id: OnceCell::new(),
+            width,
+            height,
+            format,
+            options: TextureOptions::default().with_mipmaps(false),
+            pixels: RefCell::new(None),
+        }
+    }
+
+    /// Decodes a PNG or JPEG image held in memory.
+    #[cfg(feature = "image")]
+    pub fn from_image_bytes(bytes: &[u8]) -> Result<Self, TextureError> {
+        let image = image::load_from_memory(bytes)?.into_rgba8();
+        let (width, height) = image.dimensions();
+        Self::from_rgba8(width, height, image.into_raw())
+    }
+
+    /// Loads a PNG or JPEG image from disk.
+    #[cfg(feature = "image")]
+    pub fn from_file<P>(path: P) -> Result<Self, TextureError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        Self::from_image_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn with_options(mut self, options: TextureOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    pub fn options(&self) -> TextureOptions {
+        self.options
+    }
+
+    /// Creates and uploads the texture on first use.
+    ///
+    /// # Safety
+    ///
+    /// A GL context must be current, and it must be the same context for
+    /// every call on this texture.
+    pub unsafe fn get_texture_id(&self) -> u32 {
+        *self.texture_id.get_or_init(|| {
+            let mut texture_id = 0;
+            gl::GenTextures(1, &mut texture_id);
+            gl::BindTexture(gl::TEXTURE_2D, texture_id);
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_S,
+                self.options.wrap_s.gl_wrap(),
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_T,
+                self.options.wrap_t.gl_wrap(),
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MIN_FILTER,
+                self.options.gl_min_filter(),
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MAG_FILTER,
+                self.options.gl_mag_filter(),
+            );
+            let pixels = self.pixels.take();
+            let (internal_format, format, component_type) = self.format.gl_formats();
+            // Rows of RGB8 and R8 data are not necessarily 4-byte aligned.
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                internal_format,
+                self.width as i32,
+                self.height as i32,
+                0,
+                format,
+                component_type,
+                pixels
+                    .as_ref()
+                    .map_or(std::ptr::null(), |pixels| pixels.as_ptr() as *const _),
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            if self.options.mipmaps {
+                gl::GenerateMipmap(gl::TEXTURE_2D);
+            }
+            texture_id
+        })
+    }
+
+    /// Binds the texture to texture unit `unit`.
+    ///
+    /// # Safety
+    ///
+    /// A GL context must be current, and it must be the same context for
+    /// every call on this texture.
+    pub unsafe fn bind(&self, unit: u32) {
+        bind_texture(unit, self.get_texture_id());
+    }
+
+    /// New contents for the `width` by `height` region at `x`, `y`, to be
+    /// applied inside a render command. Regions outside the texture are
+    /// clipped.
+    ///
+    /// # Safety
+    ///
+    /// A GL context must be current, and it must be the same context for
+    /// every call on this texture.
+    pub unsafe fn region_upload(
+        &self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>,
+    ) -> Result<TextureUpload, TextureError> {
+        let expected = width as usize * height as usize * self.format.bytes_per_pixel();
+        if pixels.len() != expected {
+            return Err(TextureError::SizeMismatch {
+                expected,
+                actual: pixels.len(),
+            });
+        }
+        let (_, format, component_type) = self.format.gl_formats();
+        Ok(TextureUpload {
+            texture_id: self.get_texture_id(),
+            x: x as i32,
+            y: y as i32,
+            width: width.min(self.width.saturating_sub(x)) as i32,
+            height: height.min(self.height.saturating_sub(y)) as i32,
+            row_length: width as i32,
+            format,
+            component_type,
+            pixels,
+        })
+    }
+}
+
+impl Drop for Texture2D {
+    fn drop(&mut self) {
+        if let Some(texture_id) = self.texture_id.get() {
+            unsafe {
+                gl::DeleteTextures(1, texture_id);
+            }
+        }
+    }
+}
+
+/// A texture bound to a sampler uniform, ready to be applied inside a render
+/// command.
+#[derive(Clone, Copy, Debug)]
+pub struct TextureBinding {
+    pub(crate) unit: u32,
+    pub(crate) texture_id: u32,
+}
+
+impl TextureBinding {
+    /// # Safety
+    ///
+    /// The GL context the texture was created in must be current.
+    pub unsafe fn apply(&self) {
+        bind_texture(self.unit, self.texture_id);
+    }
+}
+
+pub struct TextureUpload {
+    texture_id: u32,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    row_length: i32,
+    format: GLenum,
+    component_type: GLenum,
+    pixels: Vec<u8>,
+}
+
+impl TextureUpload {
+    /// Writes the region through the currently active texture unit.
+    ///
+    /// # Safety
+    ///
+    /// The GL context the texture was created in must be current, with no
+    /// buffer bound to `GL_PIXEL_UNPACK_BUFFER`.
+    pub unsafe fn apply(&self) {
+        if self.width <= 0 || self.height <= 0 {
+            return;
+        }
+        gl::BindTexture(gl::TEXTURE_2D, self.texture_id);
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        gl::PixelStorei(gl::UNPACK_ROW_LENGTH, self.row_length);
+        gl::TexSubImage2D(
+            gl::TEXTURE_2D,
+            0,
+            self.x,
+            self.y,
+            self.width,
+            self.height,
+            self.format,
+            self.component_type,
+            self.pixels.as_ptr() as *const _,
+        );
+        gl::PixelStorei(gl::UNPACK_ROW_LENGTH, 0);
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+    }
+}
+
+unsafe fn bind_texture(unit: u32, texture_id: u32) {
+    gl::ActiveTexture(gl::TEXTURE0 + unit);
+    gl::BindTexture(gl::TEXTURE_2D, texture_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_f32_requires_a_float_format() {
+        let pixels = [0.0; 4];
+        assert!(Texture2D::from_f32(1, 1, PixelFormat::Rgba32F, &pixels).is_ok());
+        assert!(Texture2D::from_f32(2, 2, PixelFormat::R32F, &pixels).is_ok());
+        assert!(matches!(
+            Texture2D::from_f32(1, 1, PixelFormat::Rgba8, &pixels),
+            Err(TextureError::NotFloatFormat(PixelFormat::Rgba8))
+        ));
+    }
+}
@@ -0,0 +1,110 @@
+use super::gl;
+use nalgebra::{Matrix2, Matrix3, Matrix4, Point2, Point3, Vector2, Vector3, Vector4};
+
+/// Texture unit a sampler uniform reads from.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Sampler(pub u32);
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum UniformValue {
+    Float(f32),
+    Vec2(Vector2<f32>),
+    Vec3(Vector3<f32>),
+    Vec4(Vector4<f32>),
+    Int(i32),
+    UInt(u32),
+    Mat2(Matrix2<f32>),
+    Mat3(Matrix3<f32>),
+    Mat4(Matrix4<f32>),
+    Sampler(Sampler),
+}
+
+impl UniformValue {
+    /// Writes the value to `location` of the program currently in use.
+    ///
+    /// # Safety
+    ///
+    /// A GL context must be current, with a program in use that has a
+    /// uniform of the matching type at `location`.
+    pub unsafe fn upload(&self, location: i32) {
+        match self {
+            UniformValue::Float(value) => gl::Uniform1f(location, *value),
+            UniformValue::Vec2(value) => gl::Uniform2fv(location, 1, value.as_ptr()),
+            UniformValue::Vec3(value) => gl::Uniform3fv(location, 1, value.as_ptr()),
+            UniformValue::Vec4(value) => gl::Uniform4fv(location, 1, value.as_ptr()),
+            UniformValue::Int(value) => gl::Uniform1i(location, *value),
+            UniformValue::UInt(value) => gl::Uniform1ui(location, *value),
+            UniformValue::Mat2(value) => {
+                gl::UniformMatrix2fv(location, 1, gl::FALSE, value.as_ptr())
+            }
+            UniformValue::Mat3(value) => {
+                gl::UniformMatrix3fv(location, 1, gl::FALSE, value.as_ptr())
+            }
+            UniformValue::Mat4(value) => {
+                gl::UniformMatrix4fv(location, 1, gl::FALSE, value.as_ptr())
+            }
+            UniformValue::Sampler(Sampler(unit)) => gl::Uniform1i(location, *unit as i32),
+        }
+    }
+}
+
+macro_rules! impl_from_for_uniform_value {
+    ($($source:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$source> for UniformValue {
+                fn from(value: $source) -> Self {
+                    UniformValue::$variant(value)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_for_uniform_value!(
+    f32 => Float,
+    Vector2<f32> => Vec2,
+    Vector3<f32> => Vec3,
+    Vector4<f32> => Vec4,
+    i32 => Int,
+    u32 => UInt,
+    Matrix2<f32> => Mat2,
+    Matrix3<f32> => Mat3,
+    Matrix4<f32> => Mat4,
+    Sampler => Sampler,
+);
+
+impl From<bool> for UniformValue {
+    fn from(value: bool) -> Self {
+        UniformValue::Int(value as i32)
+    }
+}
+
+impl From<Point2<f32>> for UniformValue {
+    fn from(value: Point2<f32>) -> Self {
+        UniformValue::Vec2(value.coords)
+    }
+}
+
+impl From<Point3<f32>> for UniformValue {
+    fn from(value: Point3<f32>) -> Self {
+        UniformValue::Vec3(value.coords)
+    }
+}
+
+impl From<[f32; 2]> for UniformValue {
+    fn from(value: [f32; 2]) -> Self {
+        UniformValue::Vec2(value.into())
+    }
+}
+
+impl From<[f32; 3]> for UniformValue {
+    fn from(value: [f32; 3]) -> Self {
+        UniformValue::Vec3(value.into())
+    }
+}
+
+impl From<[f32; 4]> for UniformValue {
+    fn from(value: [f32; 4]) -> Self {
+        UniformValue::Vec4(value.into())
+    }
+}
@@ -0,0 +1,158 @@
+use super::gl::{self, types::GLenum};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AttributeType {
+    Float,
+    Byte,
+    UnsignedByte,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+}
+
+impl AttributeType {
+    pub fn size(self) -> usize {
+        match self {
+            AttributeType::Float | AttributeType::Int | AttributeType::UnsignedInt => 4,
+            AttributeType::Short | AttributeType::UnsignedShort => 2,
+            AttributeType::Byte | AttributeType::UnsignedByte => 1,
+        }
+    }
+
+    pub(crate) fn gl_type(self) -> GLenum {
+        match self {
+            AttributeType::Float => gl::FLOAT,
+            AttributeType::Byte => gl::BYTE,
+            AttributeType::UnsignedByte => gl::UNSIGNED_BYTE,
+            AttributeType::Short => gl::SHORT,
+            AttributeType::UnsignedShort => gl::UNSIGNED_SHORT,
+            AttributeType::Int => gl::INT,
+            AttributeType::UnsignedInt => gl::UNSIGNED_INT,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct VertexAttribute {
+    pub name: &'static str,
+    pub components: u8,
+    pub attribute_type: AttributeType,
+    pub normalized: bool,
+}
+
+impl VertexAttribute {
+    pub const fn new(name: &'static str, components: u8, attribute_type: AttributeType) -> Self {
+        assert!(components >= 1 && components <= 4);
+        Self {
+            name,
+            components,
+            attribute_type,
+            normalized: false,
+        }
+    }
+
+    pub const fn float(name: &'static str, components: u8) -> Self {
+        Self::new(name, components, AttributeType::Float)
+    }
+
+    /// Integer data is mapped to `[0, 1]` (or `[-1, 1]` when signed) instead
+    /// of being converted as is.
+    pub const fn normalized(mut self) -> Self {
+        self.normalized = true;
+        self
+    }
+
+    pub fn size(&self) -> usize {
+        self.components as usize * self.attribute_type.size()
+    }
+}
+
+/// Attributes stored interleaved in a single buffer, in declaration order.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct VertexLayout {
+    attributes: Vec<VertexAttribute>,
+}
+
+impl VertexLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_attribute(mut self, attribute: VertexAttribute) -> Self {
+        self.attributes.push(attribute);
+        self
+    }
+
+    pub fn attributes(&self) -> &[VertexAttribute] {
+        &self.attributes
+    }
+
+    pub fn stride(&self) -> usize {
+        self.attributes.iter().map(VertexAttribute::size).sum()
+    }
+
+    /// Each attribute along with its byte offset inside a vertex.
+    pub fn offsets(&self) -> impl Iterator<Item = (&VertexAttribute, usize)> {
+        self.attributes.iter().scan(0, |offset, attribute| {
+            let current = *offset;
+            *offset += attribute.size();
+            Some((attribute, current))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mixed() -> VertexLayout {
+        VertexLayout::new()
+            .with_attribute(VertexAttribute::float("position", 3))
+            .with_attribute(
+                VertexAttribute::new("color", 4, AttributeType::UnsignedByte).normalized(),
+            )
+            .with_attribute(VertexAttribute::new("normal", 3, AttributeType::Short).normalized())
+            .with_attribute(VertexAttribute::new("bone", 1, AttributeType::UnsignedInt))
+            .with_attribute(VertexAttribute::float("uv", 2))
+    }
+
+    #[test]
+    fn stride_adds_up_mixed_attribute_types() {
+        assert_eq!(mixed().stride(), 12 + 4 + 6 + 4 + 8);
+        assert_eq!(VertexLayout::new().stride(), 0);
+    }
+
+    #[test]
+    fn offsets_follow_declaration_order() {
+        let layout = mixed();
+        let offsets: Vec<_> = layout
+            .offsets()
+            .map(|(attribute, offset)| (attribute.name, offset))
+            .collect();
+        assert_eq!(
+            offsets,
+            [
+                ("position", 0),
+                ("color", 12),
+                ("normal", 16),
+                ("bone", 22),
+                ("uv", 26)
+            ]
+        );
+        assert_eq!(VertexLayout::new().offsets().count(), 0);
+    }
+
+    #[test]
+    fn normalizing_keeps_the_size() {
+        let attribute = VertexAttribute::new("color", 4, AttributeType::UnsignedByte);
+        let normalized = attribute.normalized();
+        assert!(!attribute.normalized);
+        assert!(normalized.normalized);
+        assert_eq!(normalized.size(), attribute.size());
+        assert_eq!(normalized.attribute_type, AttributeType::UnsignedByte);
+        let layout = mixed();
+        let flags: Vec<_> = layout.attributes().iter().map(|a| a.normalized).collect();
+        assert_eq!(flags, [false, true, true, false, false]);
+    }
+}
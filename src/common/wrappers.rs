@@ -1 +1,2 @@
+pub mod buffer_wrapper;
 pub mod program_wrapper;
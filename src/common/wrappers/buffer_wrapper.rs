@@ -0,0 +1,228 @@
+use crate::common::gl::{self, types::GLenum};
+use std::cell::{Cell, OnceCell};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum BufferUsage {
+    /// Written once, drawn many times.
+    #[default]
+    Static,
+    /// Modified repeatedly, drawn many times.
+    Dynamic,
+    /// Rewritten roughly every frame.
+    Stream,
+}
+
+impl BufferUsage {
+    fn gl_usage(self) -> GLenum {
+        match self {
+            BufferUsage::Static => gl::STATIC_DRAW,
+            BufferUsage::Dynamic => gl::DYNAMIC_DRAW,
+            BufferUsage::Stream => gl::STREAM_DRAW,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BufferTarget {
+    Vertex,
+    Index,
+}
+
+impl BufferTarget {
+    fn gl_target(self) -> GLenum {
+        match self {
+            BufferTarget::Vertex => gl::ARRAY_BUFFER,
+            BufferTarget::Index => gl::ELEMENT_ARRAY_BUFFER,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Dirty {
+    Clean,
+    All,
+    /// Element range `start..end` changed but the size did not.
+    Range(usize, usize),
+}
+
+/// A GPU buffer together with the CPU copy of its contents. The data is only
+/// sent to the GPU when it has changed since the last upload, and small edits
+/// are sent with `glBufferSubData` instead of reallocating the buffer.
+pub struct BufferWrapper<T> {
+    target: BufferTarget,
+    usage: BufferUsage,
+    data: Vec<T>,
+    buffer_ref: OnceCell<u32>,
+    dirty: Cell<Dirty>,
+}
+
+impl<T> BufferWrapper<T>
+where
+    T: Copy,
+{
+    pub fn new(target: BufferTarget, data: Vec<T>, usage: BufferUsage) -> Self {
+        Self {
+            target,
+            usage,
+            data,
+            buffer_ref: OnceCell::new(),
+            dirty: Cell::new(Dirty::All),
+        }
+    }
+
+    pub fn vertex(data: Vec<T>, usage: BufferUsage) -> Self {
+        Self::new(BufferTarget::Vertex, data, usage)
+    }
+
+    pub fn index(data: Vec<T>, usage: BufferUsage) -> Self {
+        Self::new(BufferTarget::Index, data, usage)
+    }
+
+    pub fn data(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn usage(&self) -> BufferUsage {
+        self.usage
+    }
+
+    pub fn set_usage(&mut self, usage: BufferUsage) {
+        self.usage = usage;
+        self.dirty.set(Dirty::All);
+    }
+
+    pub fn set_data(&mut self, data: Vec<T>) {
+        self.data = data;
+        self.dirty.set(Dirty::All);
+    }
+
+    /// Moves the data out, leaving the buffer empty until the next
+    /// [`set_data`](BufferWrapper::set_data).
+    pub fn take_data(&mut self) -> Vec<T> {
+        self.dirty.set(Dirty::All);
+        std::mem::take(&mut self.data)
+    }
+
+    /// Overwrites the elements starting at `offset`. Only that range is
+    /// uploaded on the next draw.
+    pub fn update(&mut self, offset: usize, values: &[T]) {
+        let end = offset + values.len();
+        assert!(end <= self.data.len(), "buffer update out of bounds");
+        self.data[offset..end].copy_from_slice(values);
+        self.mark_range_dirty(offset, end);
+    }
+
+    /// Mutable access to the whole buffer. Since the changes cannot be
+    /// tracked, everything is uploaded again on the next draw.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        self.mark_range_dirty(0, self.data.len());
+        &mut self.data
+    }
+
+    fn mark_range_dirty(&self, start: usize, end: usize) {
+        let dirty = match self.dirty.get() {
+            Dirty::Clean => Dirty::Range(start, end),
+            Dirty::All => Dirty::All,
+            Dirty::Range(dirty_start, dirty_end) => {
+                Dirty::Range(dirty_start.min(start), dirty_end.max(end))
+            }
+        };
+        self.dirty.set(dirty);
+    }
+
+    /// Creates the buffer object on first use.
+    ///
+    /// # Safety
+    ///
+    /// A GL context must be current on this thread, and it must be the same
+    /// context for every call on this wrapper.
+    pub unsafe fn get_buffer_ref(&self) -> u32 {
+        *self.buffer_ref.get_or_init(|| {
+            let mut buffer_ref = 0;
+            gl::GenBuffers(1, &mut buffer_ref);
+            buffer_ref
+        })
+    }
+
+    /// Takes whatever has changed since the last call. The returned upload
+    /// owns a copy of those bytes, so it can be moved into a render command.
+    pub fn take_upload(&self) -> Option<BufferUpload> {
+        let element_size = std::mem::size_of::<T>();
+        let upload = match self.dirty.replace(Dirty::Clean) {
+            Dirty::Clean => return None,
+            Dirty::All => BufferUpload {
+                target: self.target.gl_target(),
+                kind: UploadKind::Full(self.usage.gl_usage()),
+                bytes: as_bytes(&self.data).to_vec(),
+            },
+            Dirty::Range(start, end) => BufferUpload {
+                target: self.target.gl_target(),
+                kind: UploadKind::Partial(start * element_size),
+                bytes: as_bytes(&self.data[start..end]).to_vec(),
+            },
+        };
+        Some(upload)
+    }
+}
+
+impl<T> Drop for BufferWrapper<T> {
+    fn drop(&mut self) {
+        if let Some(buffer_ref) = self.buffer_ref.get() {
+            unsafe {
+                gl::DeleteBuffers(1, buffer_ref);
+            }
+        }
+    }
+}
+
+enum UploadKind {
+    Full(GLenum),
+    Partial(usize),
+}
+
+pub struct BufferUpload {
+    target: GLenum,
+    kind: UploadKind,
+    bytes: Vec<u8>,
+}
+
+impl BufferUpload {
+    /// Sends the data to the buffer currently bound to the matching target.
+    ///
+    /// # Safety
+    ///
+    /// A GL context must be current and a buffer must be bound to the
+    /// upload's target. For partial updates that buffer must be large enough
+    /// to hold the updated range.
+    pub unsafe fn apply(&self) {
+        match self.kind {
+            UploadKind::Full(usage) => gl::BufferData(
+                self.target,
+                self.bytes.len() as isize,
+                self.bytes.as_ptr() as *const _,
+                usage,
+            ),
+            UploadKind::Partial(offset) => gl::BufferSubData(
+                self.target,
+                offset as isize,
+                self.bytes.len() as isize,
+                self.bytes.as_ptr() as *const _,
+            ),
+        }
+    }
+}
+
+pub(crate) fn as_bytes<T>(data: &[T]) -> &[u8]
+where
+    T: Copy,
+{
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) }
+}
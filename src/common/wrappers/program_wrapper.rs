@@ -1,20 +1,29 @@
 use crate::common::{
     errors::GlError,
     gl,
-    helpers::{create_program, Fragment, Shader, Vertex},
+    helpers::{create_program, shader_hot_reload, Fragment, Shader, Vertex},
+    texture::{Texture2D, TextureBinding},
+    uniform::{Sampler, UniformValue},
+    vertex_layout::{VertexAttribute, VertexLayout},
 };
+use fnv::FnvHashMap;
 use std::{
-    cell::{Cell, OnceCell},
+    cell::{Cell, OnceCell, RefCell},
     ffi::CString,
 };
+use tracing::{error, info};
 
 pub struct ProgramWrapper {
-    program_id: OnceCell<Result<u32, GlError>>,
+    program_id: RefCell<Option<Result<u32, GlError>>>,
+    linked_generations: Cell<(u64, u64)>,
     vao_ref: OnceCell<u32>,
     vbo_ref: OnceCell<u32>,
     vertex_shader: &'static Shader<Vertex>,
     fragment_shader: &'static Shader<Fragment>,
     variable_created: Cell<bool>,
+    uniform_locations: RefCell<FnvHashMap<&'static str, Option<i32>>>,
+    pending_uniforms: RefCell<Vec<(i32, UniformValue)>>,
+    uniform_values: RefCell<FnvHashMap<&'static str, UniformValue>>,
 }
 
 impl ProgramWrapper {
@@ -23,24 +32,101 @@ impl ProgramWrapper {
         fragment_shader: &'static Shader<Fragment>,
     ) -> Self {
         Self {
-            program_id: OnceCell::new(),
+            program_id: RefCell::new(None),
+            linked_generations: Cell::new((0, 0)),
             vao_ref: OnceCell::new(),
             vbo_ref: OnceCell::new(),
             vertex_shader,
             fragment_shader,
             variable_created: Cell::new(false),
+            uniform_locations: RefCell::new(FnvHashMap::default()),
+            pending_uniforms: RefCell::new(Vec::new()),
+            uniform_values: RefCell::new(FnvHashMap::default()),
         }
     }
 
+    /// Links the program on first use. With shader hot reload enabled, the
+    /// program is relinked whenever one of its shaders changes on disk; if
+    /// that fails the error is logged and the previous program stays in use.
     pub unsafe fn get_program_id(&self) -> Result<u32, GlError> {
-        self.program_id
-            .get_or_init(|| {
-                let vertex_shader = self.vertex_shader.get_shader_handle()?;
-                let fragment_shader = self.fragment_shader.get_shader_handle()?;
-                let program_id = create_program(&vertex_shader, &fragment_shader)?;
-                Ok(program_id)
-            })
-            .clone()
+        if shader_hot_reload() {
+            self.vertex_shader.poll_changes();
+            self.fragment_shader.poll_changes();
+        }
+        let generations = (
+            self.vertex_shader.generation(),
+            self.fragment_shader.generation(),
+        );
+        let current = self.program_id.borrow().clone();
+        match current {
+            None => {
+                let program_id = self.link();
+                self.linked_generations.set(generations);
+                *self.program_id.borrow_mut() = Some(program_id.clone());
+                program_id
+            }
+            Some(Ok(program_id)) if self.linked_generations.get() != generations => {
+                self.linked_generations.set(generations);
+                match self.link() {
+                    Ok(new_program_id) => {
+                        gl::DeleteProgram(program_id);
+                        *self.program_id.borrow_mut() = Some(Ok(new_program_id));
+                        self.restore_state();
+                        info!("Relinked program {new_program_id}");
+                        Ok(new_program_id)
+                    }
+                    Err(e) => {
+                        error!("Keeping the previous program: {e}");
+                        Ok(program_id)
+                    }
+                }
+            }
+            // A program that never linked has nothing to fall back to, so it
+            // is retried as soon as a shader changes.
+            Some(Err(_)) if self.linked_generations.get() != generations => {
+                self.linked_generations.set(generations);
+                let program_id = self.link();
+                if let Ok(program_id) = program_id {
+                    info!("Linked program {program_id} after a shader change");
+                }
+                *self.program_id.borrow_mut() = Some(program_id.clone());
+                program_id
+            }
+            Some(program_id) => program_id,
+        }
+    }
+
+    unsafe fn link(&self) -> Result<u32, GlError> {
+        let vertex_shader = self.vertex_shader.get_shader_handle()?;
+        let fragment_shader = self.fragment_shader.get_shader_handle()?;
+        create_program(&vertex_shader, &fragment_shader)
+    }
+
+    /// Locations are only valid for the program they were queried from, so
+    /// after relinking they are looked up again and every uniform is sent
+    /// anew.
+    unsafe fn restore_state(&self) {
+        self.uniform_locations.borrow_mut().clear();
+        self.pending_uniforms.borrow_mut().clear();
+        self.variable_created.set(false);
+        self.replay_uniforms(|name| self.get_uniform_location(name));
+    }
+
+    /// Queues every uniform set so far again, at the location `location_of`
+    /// now reports for it. Uniforms the program no longer declares are
+    /// dropped.
+    fn replay_uniforms<F>(&self, mut location_of: F)
+    where
+        F: FnMut(&'static str) -> Result<i32, GlError>,
+    {
+        let uniform_values = self.uniform_values.take();
+        for (name, value) in uniform_values {
+            match location_of(name) {
+                Ok(location) => self.queue_uniform(name, location, value),
+                Err(GlError::NonexistantUniformName(_)) => {}
+                Err(e) => error!("Unable to restore uniform {name}: {e}"),
+            }
+        }
     }
 
     pub unsafe fn get_vao_ref(&self) -> u32 {
@@ -59,11 +145,115 @@ impl ProgramWrapper {
         })
     }
 
+    /// Looks up `name` once and caches the result, including its absence.
+    ///
+    /// # Safety
+    ///
+    /// The same GL context must be current for every call on this wrapper,
+    /// since the program is linked on first use.
+    pub unsafe fn get_uniform_location(&self, name: &'static str) -> Result<i32, GlError> {
+        let cached = self.uniform_locations.borrow().get(name).copied();
+        let location = match cached {
+            Some(location) => location,
+            None => {
+                let program_id = self.get_program_id()?;
+                let uniform_name = CString::new(name).map_err(|_| GlError::NullByte)?;
+                let location = gl::GetUniformLocation(program_id, uniform_name.as_ptr());
+                let location = (location != -1).then_some(location);
+                self.uniform_locations.borrow_mut().insert(name, location);
+                location
+            }
+        };
+        location.ok_or(GlError::NonexistantUniformName(name))
+    }
+
+    /// Queues a uniform write. Uniforms keep their value inside the program,
+    /// so each value only has to be sent once; see [`take_pending_uniforms`].
+    ///
+    /// # Safety
+    ///
+    /// As for [`get_uniform_location`](ProgramWrapper::get_uniform_location).
+    ///
+    /// [`take_pending_uniforms`]: ProgramWrapper::take_pending_uniforms
+    pub unsafe fn set_uniform<U>(&self, name: &'static str, value: U) -> Result<(), GlError>
+    where
+        U: Into<UniformValue>,
+    {
+        let location = self.get_uniform_location(name)?;
+        self.queue_uniform(name, location, value.into());
+        Ok(())
+    }
+
+    /// Remembers `value` for [`restore_state`] and queues it, replacing a
+    /// write to the same location that was not taken yet.
+    ///
+    /// [`restore_state`]: ProgramWrapper::restore_state
+    fn queue_uniform(&self, name: &'static str, location: i32, value: UniformValue) {
+        self.uniform_values.borrow_mut().insert(name, value.clone());
+        let mut pending_uniforms = self.pending_uniforms.borrow_mut();
+        match pending_uniforms
+            .iter_mut()
+            .find(|(pending_location, _)| *pending_location == location)
+        {
+            Some((_, pending_value)) => *pending_value = value,
+            None => pending_uniforms.push((location, value)),
+        }
+    }
+
+    /// Like [`set_uniform`](ProgramWrapper::set_uniform), but silently skips
+    /// uniforms the program does not declare. Used for values the engine
+    /// provides to every shader, such as the camera matrices.
+    ///
+    /// # Safety
+    ///
+    /// As for [`get_uniform_location`](ProgramWrapper::get_uniform_location).
+    pub unsafe fn set_uniform_if_present<U>(
+        &self,
+        name: &'static str,
+        value: U,
+    ) -> Result<(), GlError>
+    where
+        U: Into<UniformValue>,
+    {
+        match self.set_uniform(name, value) {
+            Err(GlError::NonexistantUniformName(_)) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Points the sampler uniform `name` at texture unit `unit`. The returned
+    /// binding attaches `texture` to that unit when applied in a command.
+    ///
+    /// # Safety
+    ///
+    /// As for [`get_uniform_location`](ProgramWrapper::get_uniform_location),
+    /// and `texture` must belong to the same GL context.
+    pub unsafe fn set_texture(
+        &self,
+        name: &'static str,
+        unit: u32,
+        texture: &Texture2D,
+    ) -> Result<TextureBinding, GlError> {
+        self.set_uniform(name, Sampler(unit))?;
+        Ok(TextureBinding {
+            unit,
+            texture_id: texture.get_texture_id(),
+        })
+    }
+
+    /// Takes the uniform writes queued since the last call, to be applied
+    /// inside a render command after `glUseProgram`.
+    pub fn take_pending_uniforms(&self) -> PendingUniforms {
+        PendingUniforms {
+            uniforms: self.pending_uniforms.take(),
+        }
+    }
+
     pub fn get_variable_helper(&self) -> Option<VariableHelper> {
         if !self.variable_created.get() {
-            if let Some(Ok(program_id)) = self.program_id.get() {
+            if let Some(Ok(program_id)) = *self.program_id.borrow() {
                 self.variable_created.set(true);
-                return Some(VariableHelper::new(*program_id));
+                return Some(VariableHelper::new(program_id));
             }
         }
         None
@@ -73,13 +263,33 @@ impl ProgramWrapper {
 impl Drop for ProgramWrapper {
     fn drop(&mut self) {
         unsafe {
-            if let Some(Ok(program_id)) = self.program_id.get() {
-                gl::DeleteProgram(*program_id);
+            if let Some(Ok(program_id)) = *self.program_id.get_mut() {
+                gl::DeleteProgram(program_id);
             }
         }
     }
 }
 
+pub struct PendingUniforms {
+    uniforms: Vec<(i32, UniformValue)>,
+}
+
+impl PendingUniforms {
+    pub fn is_empty(&self) -> bool {
+        self.uniforms.is_empty()
+    }
+
+    /// # Safety
+    ///
+    /// The program the uniforms were queued on must be in use, in a current
+    /// GL context.
+    pub unsafe fn apply(&self) {
+        for (location, value) in &self.uniforms {
+            value.upload(*location);
+        }
+    }
+}
+
 pub struct VariableHelper {
     program_id: u32,
 }
@@ -93,23 +303,121 @@ impl VariableHelper {
         &self,
         variable_names: Vec<&'static str>,
     ) -> Result<(), GlError> {
-        let stride = (3 * variable_names.len() * std::mem::size_of::<f32>()) as i32;
-        let mut offset = 0;
-        for variable_name in variable_names {
-            let attrib_name = CString::new(variable_name).map_err(|_| GlError::NullByte)?;
+        let layout = variable_names
+            .into_iter()
+            .fold(VertexLayout::new(), |layout, variable_name| {
+                layout.with_attribute(VertexAttribute::float(variable_name, 3))
+            });
+        self.resolve_layout(&layout)?.apply();
+        Ok(())
+    }
+
+    /// Looks up every attribute of `layout` in the program. The result can be
+    /// applied later, once the VAO and vertex buffer are bound.
+    ///
+    /// # Safety
+    ///
+    /// The GL context the program was linked in must be current.
+    pub unsafe fn resolve_layout(
+        &self,
+        layout: &VertexLayout,
+    ) -> Result<AttributeBindings, GlError> {
+        let mut attributes = Vec::with_capacity(layout.attributes().len());
+        for (attribute, offset) in layout.offsets() {
+            let attrib_name = CString::new(attribute.name).map_err(|_| GlError::NullByte)?;
             let variable_id = gl::GetAttribLocation(self.program_id, attrib_name.as_ptr());
             if variable_id == -1 {
-                return Err(GlError::NonexistantVariableName(variable_name));
+                return Err(GlError::NonexistantVariableName(attribute.name));
             }
-            gl::EnableVertexAttribArray(variable_id as u32);
-            let ptr = if offset == 0 {
-                std::ptr::null()
-            } else {
-                (offset * std::mem::size_of::<f32>()) as *const f32 as *const std::ffi::c_void
-            };
-            gl::VertexAttribPointer(variable_id as u32, 3, gl::FLOAT, gl::FALSE, stride, ptr);
-            offset += 3;
+            attributes.push((variable_id as u32, *attribute, offset));
         }
-        Ok(())
+        Ok(AttributeBindings {
+            stride: layout.stride() as i32,
+            attributes,
+        })
+    }
+}
+
+pub struct AttributeBindings {
+    stride: i32,
+    attributes: Vec<(u32, VertexAttribute, usize)>,
+}
+
+impl AttributeBindings {
+    /// Points the attributes at the vertex buffer.
+    ///
+    /// # Safety
+    ///
+    /// The GL context the program was linked in must be current, with the
+    /// target VAO bound and the vertex buffer bound to `GL_ARRAY_BUFFER`.
+    pub unsafe fn apply(&self) {
+        for (variable_id, attribute, offset) in &self.attributes {
+            gl::EnableVertexAttribArray(*variable_id);
+            gl::VertexAttribPointer(
+                *variable_id,
+                attribute.components as i32,
+                attribute.attribute_type.gl_type(),
+                if attribute.normalized {
+                    gl::TRUE
+                } else {
+                    gl::FALSE
+                },
+                self.stride,
+                *offset as *const std::ffi::c_void,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static VERTEX: Shader<Vertex> = Shader::create_vertex_shader("");
+    static FRAGMENT: Shader<Fragment> = Shader::create_fragment_shader("");
+
+    fn pending(program_wrapper: &ProgramWrapper) -> Vec<(i32, UniformValue)> {
+        program_wrapper.take_pending_uniforms().uniforms
+    }
+
+    #[test]
+    fn the_last_write_to_a_location_wins() {
+        let program_wrapper = ProgramWrapper::new(&VERTEX, &FRAGMENT);
+        program_wrapper.queue_uniform("exposure", 3, 1.0.into());
+        program_wrapper.queue_uniform("tint", 1, [1.0, 0.0, 0.0].into());
+        program_wrapper.queue_uniform("exposure", 3, 2.0.into());
+        program_wrapper.queue_uniform("tint", 1, [0.0, 1.0, 0.0].into());
+        assert_eq!(
+            pending(&program_wrapper),
+            [(3, 2.0.into()), (1, [0.0, 1.0, 0.0].into())]
+        );
+        assert!(program_wrapper.take_pending_uniforms().is_empty());
+
+        program_wrapper.queue_uniform("exposure", 3, 4.0.into());
+        assert_eq!(pending(&program_wrapper), [(3, 4.0.into())]);
+    }
+
+    #[test]
+    fn replaying_queues_the_latest_values_at_the_new_locations() {
+        let program_wrapper = ProgramWrapper::new(&VERTEX, &FRAGMENT);
+        program_wrapper.queue_uniform("exposure", 0, 1.0.into());
+        program_wrapper.queue_uniform("exposure", 0, 2.0.into());
+        program_wrapper.queue_uniform("removed", 1, 5.0.into());
+        program_wrapper.queue_uniform("tint", 2, [1.0, 1.0, 1.0].into());
+        pending(&program_wrapper);
+
+        program_wrapper.replay_uniforms(|name| match name {
+            "exposure" => Ok(7),
+            "tint" => Ok(4),
+            _ => Err(GlError::NonexistantUniformName(name)),
+        });
+        let mut replayed = pending(&program_wrapper);
+        replayed.sort_by_key(|(location, _)| *location);
+        assert_eq!(replayed, [(4, [1.0, 1.0, 1.0].into()), (7, 2.0.into())]);
+
+        program_wrapper.replay_uniforms(|name| Ok(if name == "tint" { 0 } else { 1 }));
+        let mut replayed = pending(&program_wrapper);
+        replayed.sort_by_key(|(location, _)| *location);
+        assert_eq!(replayed, [(0, [1.0, 1.0, 1.0].into()), (1, 2.0.into())]);
     }
 }
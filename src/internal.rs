@@ -1 +1,4 @@
+pub mod context_backend;
+#[cfg(free_unix)]
+pub mod headless;
 pub mod internal_game_loop;
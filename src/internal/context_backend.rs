@@ -0,0 +1,34 @@
+use crate::common::{errors::GlError, render_target::RenderTarget, rgba_image::RgbaImage};
+use bumpalo::Bump;
+use glutin::display::GlDisplay;
+use std::time::Duration;
+
+/// Requests from a context to the window, carried out between frames.
+pub enum Command {
+    Exit,
+    CursorGrab(bool),
+    CursorVisible(bool),
+}
+
+/// The side of a context that only the game loops use. Public so it can
+/// bound [`Game::Context`](crate::common::game::Game::Context), but not
+/// reachable from outside the crate.
+pub trait ContextBackend<'a>: Sized {
+    fn new<D>(gl_display: &D, bump: &'a Bump) -> Self
+    where
+        D: GlDisplay;
+
+    fn resize(&mut self, width: i32, height: i32);
+
+    /// Renders frames into `target` instead of the default framebuffer, for
+    /// contexts without a window.
+    fn set_output_target(&mut self, target: RenderTarget);
+
+    unsafe fn read_output_target(&self) -> Result<Option<RgbaImage>, GlError>;
+
+    fn tick(&mut self, now: Duration);
+
+    fn pop_all_commands(&mut self) -> Vec<Command, &'a Bump>;
+
+    unsafe fn draw(&mut self) -> Result<(), GlError>;
+}
@@ -0,0 +1,102 @@
+use super::{
+    context_backend::{Command, ContextBackend},
+    internal_game_loop::update_frame,
+};
+use crate::common::{
+    application_builder::ApplicationBuilder,
+    errors::{DuendeError, UnsupportedDevice},
+    fixed_timestep::FixedTimestep,
+    game::Game,
+    helpers::set_shader_hot_reload,
+    render_target::RenderTarget,
+    rgba_image::RgbaImage,
+};
+use bumpalo::Bump;
+use glutin::{
+    api::egl::{device::Device, display::Display},
+    config::{ConfigSurfaceTypes, ConfigTemplateBuilder},
+    context::{ContextApi, ContextAttributesBuilder, GlProfile, Version},
+    display::GlDisplay,
+};
+use std::time::Duration;
+
+/// Runs `game_loop` for `frames` frames on a surfaceless EGL context, with
+/// time advancing by `frame_interval` per frame, and returns every frame.
+pub(crate) fn run_headless<G>(
+    mut builder: ApplicationBuilder,
+    mut game_loop: G,
+    (width, height): (u32, u32),
+    frames: usize,
+    frame_interval: Duration,
+) -> Result<Vec<RgbaImage>, DuendeError>
+where
+    G: Game,
+{
+    let device = Device::query_devices()
+        .map_err(|e| DuendeError::InternalError(Box::new(e)))?
+        .next()
+        .ok_or(DuendeError::UnsupportedDevice(
+            UnsupportedDevice::HeadlessDevice,
+        ))?;
+    let display = unsafe { Display::with_device(&device, None) }
+        .map_err(|e| DuendeError::InternalError(Box::new(e)))?;
+    let template = ConfigTemplateBuilder::new()
+        .with_depth_size(24)
+        .with_surface_type(ConfigSurfaceTypes::empty())
+        .build();
+    let gl_config = unsafe { display.find_configs(template) }
+        .map_err(|e| DuendeError::InternalError(Box::new(e)))?
+        .next()
+        .ok_or(DuendeError::UnsupportedDevice(
+            UnsupportedDevice::HeadlessDevice,
+        ))?;
+    let context_attributes = ContextAttributesBuilder::new()
+        .with_context_api(ContextApi::OpenGl(Some(Version::new(3, 3))))
+        .with_profile(GlProfile::Compatibility)
+        .build(None);
+    let _gl_context = unsafe { display.create_context(&gl_config, &context_attributes) }
+        .and_then(|context| context.make_current_surfaceless())
+        .map_err(|e| DuendeError::InternalError(Box::new(e)))?;
+
+    set_shader_hot_reload(builder.hot_reload_shaders);
+    let bump = Bump::new();
+    let mut timestep = FixedTimestep::new(builder.fixed_update_rate)
+        .with_max_steps_per_frame(builder.max_fixed_updates_per_frame);
+    let mut context = G::Context::new(&display, &bump);
+    context.set_output_target(RenderTarget::new(width, height));
+    game_loop.setup(&mut context);
+    let mut images = Vec::with_capacity(frames);
+    let mut result = Ok(());
+    let mut now = Duration::ZERO;
+    for _ in 0..frames {
+        now += frame_interval;
+        update_frame(
+            &mut game_loop,
+            &mut context,
+            &mut timestep,
+            builder.gamepad_backend.as_mut(),
+            now,
+        );
+        let mut exit = false;
+        for command in context.pop_all_commands() {
+            match command {
+                Command::Exit => exit = true,
+                // There is no window, so there is no cursor to grab or hide.
+                Command::CursorGrab(_) | Command::CursorVisible(_) => {}
+            }
+        }
+        let image = unsafe { context.draw().and_then(|_| context.read_output_target()) };
+        match image {
+            Ok(image) => images.extend(image),
+            Err(e) => {
+                result = Err(DuendeError::GlError(e));
+                break;
+            }
+        }
+        if exit {
+            break;
+        }
+    }
+    game_loop.teardown(&mut context);
+    result.map(|_| images)
+}
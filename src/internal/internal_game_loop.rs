@@ -1,10 +1,11 @@
-use crate::{
-    common::{
-        application_builder::ApplicationBuilder,
-        errors::{DuendeError, UnsupportedDevice},
-        game::Game,
-    },
-    three_d::three_d_application_context::{Command, Event, ThreeDApplicationContext},
+use super::context_backend::{Command, ContextBackend};
+use crate::common::{
+    application_builder::ApplicationBuilder,
+    application_context::ApplicationContext,
+    errors::{DuendeError, UnsupportedDevice},
+    fixed_timestep::FixedTimestep,
+    game::Game,
+    input::gamepad::GamepadBackend,
 };
 use bumpalo::Bump;
 use glutin::{
@@ -18,26 +19,29 @@ use glutin::{
 };
 use glutin_winit::{DisplayBuilder, GlWindow};
 use raw_window_handle::HasWindowHandle;
-use std::num::NonZeroU32;
+use std::{num::NonZeroU32, time::Duration};
 use tracing::{error, info};
 use winit::{
     application::ApplicationHandler,
-    event::{KeyEvent, WindowEvent},
-    keyboard::Key,
+    event::{DeviceEvent, WindowEvent},
     window::{CursorGrabMode, Window, WindowAttributes},
 };
 
-pub(crate) struct InnerApplication<'a, G> {
+pub(crate) struct InnerApplication<'a, G>
+where
+    G: Game,
+{
     template: ConfigTemplateBuilder,
     display_builder: DisplayBuilder,
     game_loop: G,
-    context: Option<ThreeDApplicationContext<'a>>,
+    context: Option<G::Context<'a>>,
     window_attributes: WindowAttributes,
     not_current_gl_context: Option<NotCurrentContext>,
     state: Option<AppState>,
     builder: ApplicationBuilder,
     pub(crate) exit_state: Result<(), DuendeError>,
     bump: &'a Bump,
+    timestep: FixedTimestep,
 }
 
 impl<'a, G> InnerApplication<'a, G>
@@ -52,6 +56,8 @@ where
         builder: ApplicationBuilder,
         bump: &'a Bump,
     ) -> Self {
+        let timestep = FixedTimestep::new(builder.fixed_update_rate)
+            .with_max_steps_per_frame(builder.max_fixed_updates_per_frame);
         Self {
             template,
             display_builder,
@@ -63,6 +69,7 @@ where
             builder,
             exit_state: Ok(()),
             bump,
+            timestep,
         }
     }
 }
@@ -105,7 +112,7 @@ where
             let window_attributes = self.window_attributes.clone();
             glutin_winit::finalize_window(event_loop, window_attributes, &gl_config).unwrap()
         });
-        if self.builder.grab_mouse && window.set_cursor_grab(CursorGrabMode::None).is_err() {
+        if self.builder.grab_mouse && set_cursor_grab(&window, true).is_err() {
             self.exit_with_error(
                 event_loop,
                 DuendeError::UnsupportedDevice(UnsupportedDevice::CursorGrab),
@@ -124,8 +131,10 @@ where
                 .unwrap()
         };
         let gl_context = not_current_gl_context.make_current(&gl_surface).unwrap();
+        let size = window.inner_size();
         self.context
-            .get_or_insert_with(|| ThreeDApplicationContext::new(&gl_display, self.bump));
+            .get_or_insert_with(|| G::Context::new(&gl_display, self.bump))
+            .resize(size.width as i32, size.height as i32);
         if let Err(res) = gl_surface
             .set_swap_interval(&gl_context, SwapInterval::Wait(NonZeroU32::new(1).unwrap()))
         {
@@ -161,22 +170,43 @@ where
                         NonZeroU32::new(size.width).unwrap(),
                         NonZeroU32::new(size.height).unwrap(),
                     );
-                    let renderer = self.context.as_ref().unwrap();
+                    let renderer = self.context.as_mut().unwrap();
                     renderer.resize(size.width as i32, size.height as i32);
                 }
             }
-            WindowEvent::KeyboardInput {
-                event:
-                    KeyEvent {
-                        logical_key: Key::Named(key),
-                        ..
-                    },
-                ..
-            } => self
-                .context
-                .as_mut()
-                .unwrap()
-                .add_event(Event::KeyPress(key)),
+            WindowEvent::KeyboardInput { event, .. } => {
+                if let Some(context) = self.context.as_mut() {
+                    context.keyboard_mut().handle_key_event(&event);
+                }
+            }
+            WindowEvent::Focused(false) => {
+                if let Some(context) = self.context.as_mut() {
+                    context.keyboard_mut().release_all();
+                    context.mouse_mut().release_all();
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                if let Some(context) = self.context.as_mut() {
+                    context
+                        .mouse_mut()
+                        .handle_cursor_moved(position.x, position.y);
+                }
+            }
+            WindowEvent::CursorLeft { .. } => {
+                if let Some(context) = self.context.as_mut() {
+                    context.mouse_mut().handle_cursor_left();
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                if let Some(context) = self.context.as_mut() {
+                    context.mouse_mut().handle_button(button, state);
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                if let Some(context) = self.context.as_mut() {
+                    context.mouse_mut().handle_scroll(delta);
+                }
+            }
             WindowEvent::CloseRequested => {
                 self.exit(event_loop);
             }
@@ -184,6 +214,19 @@ where
         }
     }
 
+    fn device_event(
+        &mut self,
+        _event_loop: &winit::event_loop::ActiveEventLoop,
+        _device_id: winit::event::DeviceId,
+        event: DeviceEvent,
+    ) {
+        if let DeviceEvent::MouseMotion { delta: (dx, dy) } = event {
+            if let Some(context) = self.context.as_mut() {
+                context.mouse_mut().handle_motion(dx, dy);
+            }
+        }
+    }
+
     fn about_to_wait(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         if let Some(AppState {
             gl_context,
@@ -192,24 +235,21 @@ where
         }) = self.state.as_ref()
         {
             let context = self.context.as_mut().unwrap();
-            self.game_loop.game_loop(context);
+            let now = self.builder.clock.now();
+            update_frame(
+                &mut self.game_loop,
+                context,
+                &mut self.timestep,
+                self.builder.gamepad_backend.as_mut(),
+                now,
+            );
             let commands = context.pop_all_commands();
             let mut exit = false;
             let mut error = Ok(());
             for command in commands {
                 match command {
                     Command::CursorGrab(enable) => {
-                        let result = if enable {
-                            window
-                                .set_cursor_grab(CursorGrabMode::None)
-                                .map_err(|_| UnsupportedDevice::CursorGrab)
-                        } else {
-                            window
-                                .set_cursor_grab(CursorGrabMode::Confined)
-                                .or_else(|_e| window.set_cursor_grab(CursorGrabMode::Locked))
-                                .map_err(|_| UnsupportedDevice::CursorGrab)
-                        };
-                        if let Err(e) = result {
+                        if let Err(e) = set_cursor_grab(window, enable) {
                             error = Err(DuendeError::UnsupportedDevice(e));
                         }
                     }
@@ -262,12 +302,45 @@ where
     }
 }
 
+/// Runs the game logic of one frame: input polling, fixed updates, the
+/// variable update and render. Shared by the windowed and headless loops.
+pub(crate) fn update_frame<G>(
+    game_loop: &mut G,
+    context: &mut G::Context<'_>,
+    timestep: &mut FixedTimestep,
+    gamepad_backend: &mut dyn GamepadBackend,
+    now: Duration,
+) where
+    G: Game,
+{
+    context.tick(now);
+    context.gamepads_mut().poll(gamepad_backend);
+    let steps = timestep.advance(now);
+    let dt = timestep.step_seconds();
+    for _ in 0..steps {
+        game_loop.fixed_update(context, dt);
+    }
+    game_loop.game_loop(context);
+    game_loop.render(context, timestep.alpha());
+}
+
 struct AppState {
     gl_context: PossiblyCurrentContext,
     gl_surface: Surface<WindowSurface>,
     window: Window,
 }
 
+fn set_cursor_grab(window: &Window, enable: bool) -> Result<(), UnsupportedDevice> {
+    if enable {
+        window
+            .set_cursor_grab(CursorGrabMode::Confined)
+            .or_else(|_e| window.set_cursor_grab(CursorGrabMode::Locked))
+    } else {
+        window.set_cursor_grab(CursorGrabMode::None)
+    }
+    .map_err(|_| UnsupportedDevice::CursorGrab)
+}
+
 fn gl_config_picker(mut configs: Box<dyn Iterator<Item = Config> + '_>) -> Config {
     const DEFAULT_MSAA: u8 = 4;
 
@@ -1,10 +1,15 @@
 #![feature(allocator_api)]
 
 pub mod common;
+#[cfg(all(feature = "testing", free_unix))]
+pub mod testing;
 pub mod three_d;
 pub mod two_d;
 pub use nalgebra::*;
-pub use winit::keyboard::NamedKey;
+pub use winit::{
+    event::MouseButton,
+    keyboard::{KeyCode, NamedKey},
+};
 
 mod internal;
 mod utils;
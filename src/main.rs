@@ -1,5 +1,10 @@
 use duende::{
-    common::{application_builder::ApplicationBuilder, game::Game},
+    common::{
+        application_builder::ApplicationBuilder,
+        application_context::ApplicationContext,
+        game::Game,
+        input::input_map::{Binding, InputMap},
+    },
     three_d::{
         game_objects::test_game_object::TestGameObject,
         three_d_application_context::ThreeDApplicationContext,
@@ -36,8 +41,14 @@ impl TestGame {
 }
 
 impl Game for TestGame {
+    type Context<'a> = ThreeDApplicationContext<'a>;
+
+    fn setup(&mut self, context: &mut ThreeDApplicationContext) {
+        context.set_input_map(InputMap::new().with_binding("quit", Binding::key(NamedKey::Escape)));
+    }
+
     fn game_loop(&mut self, context: &mut ThreeDApplicationContext) {
-        if context.is_key_pressed(NamedKey::Escape) {
+        if context.action_pressed("quit") {
             context.exit();
         }
         context.draw_game_object(&self.object);
@@ -0,0 +1,232 @@
+//! Golden-image testing: render a [`Game`] headless and compare the last
+//! frame against a reference PNG.
+//!
+//! Set `DUENDE_UPDATE_GOLDEN=1` to (re)write the references instead of
+//! comparing against them.
+
+use crate::common::{
+    application_builder::ApplicationBuilder, errors::DuendeError, game::Game, rgba_image::RgbaImage,
+};
+use std::path::{Path, PathBuf};
+
+const UPDATE_VARIABLE: &str = "DUENDE_UPDATE_GOLDEN";
+
+#[derive(thiserror::Error, Debug)]
+pub enum GoldenError {
+    #[error("rendering failed: {0}")]
+    Render(#[from] DuendeError),
+
+    #[error("the game did not render any frame")]
+    NoFrame,
+
+    #[error("image error: {0}")]
+    Image(#[from] image::ImageError),
+
+    #[error(
+        "missing reference {}, the rendered frame was written to {}",
+        reference.display(),
+        actual.display()
+    )]
+    MissingReference { reference: PathBuf, actual: PathBuf },
+
+    #[error("reference is {expected:?} pixels but the frame is {actual:?}")]
+    SizeMismatch {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+
+    #[error(
+        "{mismatched_pixels} pixels differ by up to {max_difference}, see {}",
+        diff.display()
+    )]
+    Mismatch {
+        mismatched_pixels: usize,
+        max_difference: u8,
+        diff: PathBuf,
+    },
+}
+
+/// The outcome of comparing two images of the same size.
+#[derive(Clone, Debug)]
+pub struct ImageComparison {
+    pub mismatched_pixels: usize,
+    pub max_difference: u8,
+    /// The expected image, dimmed, with mismatching pixels in red.
+    pub diff: RgbaImage,
+}
+
+/// Compares two images channel by channel. A pixel mismatches when any of
+/// its channels differs by more than `tolerance`. Returns `None` when the
+/// sizes differ.
+pub fn compare_images(
+    expected: &RgbaImage,
+    actual: &RgbaImage,
+    tolerance: u8,
+) -> Option<ImageComparison> {
+    if (expected.width(), expected.height()) != (actual.width(), actual.height()) {
+        return None;
+    }
+    let mut diff = RgbaImage::new(expected.width(), expected.height());
+    let mut mismatched_pixels = 0;
+    let mut max_difference = 0;
+    for ((expected, actual), diff) in expected
+        .pixels()
+        .chunks_exact(4)
+        .zip(actual.pixels().chunks_exact(4))
+        .zip(diff.pixels_mut().chunks_exact_mut(4))
+    {
+        let difference = expected
+            .iter()
+            .zip(actual)
+            .map(|(expected, actual)| expected.abs_diff(*actual))
+            .max()
+            .unwrap_or(0);
+        max_difference = max_difference.max(difference);
+        if difference > tolerance {
+            mismatched_pixels += 1;
+            diff.copy_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let luma =
+                (expected[0] as u32 * 299 + expected[1] as u32 * 587 + expected[2] as u32 * 114)
+                    / 1000;
+            let dimmed = (luma / 3) as u8;
+            diff.copy_from_slice(&[dimmed, dimmed, dimmed, 255]);
+        }
+    }
+    Some(ImageComparison {
+        mismatched_pixels,
+        max_difference,
+        diff,
+    })
+}
+
+/// Renders a game headless for a number of frames and checks the last one
+/// against `<reference_dir>/<name>.png`. On failure the frame and a diff
+/// are written next to the reference as `<name>.actual.png` and
+/// `<name>.diff.png`.
+pub struct GoldenTest {
+    name: String,
+    builder: ApplicationBuilder,
+    size: (u32, u32),
+    frames: usize,
+    tolerance: u8,
+    max_mismatched_pixels: usize,
+    reference_dir: PathBuf,
+}
+
+impl GoldenTest {
+    pub fn new<T>(name: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            builder: ApplicationBuilder::new(),
+            size: (128, 96),
+            frames: 1,
+            tolerance: 2,
+            max_mismatched_pixels: 0,
+            reference_dir: PathBuf::from("tests/golden"),
+        }
+    }
+
+    /// Builder used to configure the application, e.g. its update rate.
+    pub fn builder(mut self, builder: ApplicationBuilder) -> Self {
+        self.builder = builder;
+        self
+    }
+
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = (width, height);
+        self
+    }
+
+    pub fn frames(mut self, frames: usize) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    /// Largest per-channel difference still counted as a match.
+    pub fn tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Number of pixels allowed to differ, to absorb rasterization
+    /// differences between drivers along edges.
+    pub fn max_mismatched_pixels(mut self, max_mismatched_pixels: usize) -> Self {
+        self.max_mismatched_pixels = max_mismatched_pixels;
+        self
+    }
+
+    pub fn reference_dir<P>(mut self, reference_dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.reference_dir = reference_dir.into();
+        self
+    }
+
+    pub fn run<G>(mut self, game_loop: G) -> Result<(), GoldenError>
+    where
+        G: Game,
+    {
+        let (width, height) = self.size;
+        let actual = std::mem::take(&mut self.builder)
+            .headless(width, height)
+            .frames(self.frames)
+            .render(game_loop)?
+            .pop()
+            .ok_or(GoldenError::NoFrame)?;
+        let reference = self.path("png");
+        if std::env::var_os(UPDATE_VARIABLE).is_some_and(|value| value != "0") {
+            create_parent_dir(&reference)?;
+            actual.save_png(&reference)?;
+            return Ok(());
+        }
+        if !reference.exists() {
+            let actual_path = self.save_actual(&actual)?;
+            return Err(GoldenError::MissingReference {
+                reference,
+                actual: actual_path,
+            });
+        }
+        let expected = RgbaImage::open(&reference)?;
+        let comparison = compare_images(&expected, &actual, self.tolerance).ok_or(
+            GoldenError::SizeMismatch {
+                expected: (expected.width(), expected.height()),
+                actual: (actual.width(), actual.height()),
+            },
+        )?;
+        if comparison.mismatched_pixels <= self.max_mismatched_pixels {
+            return Ok(());
+        }
+        self.save_actual(&actual)?;
+        let diff = self.path("diff.png");
+        comparison.diff.save_png(&diff)?;
+        Err(GoldenError::Mismatch {
+            mismatched_pixels: comparison.mismatched_pixels,
+            max_difference: comparison.max_difference,
+            diff,
+        })
+    }
+
+    fn path(&self, extension: &str) -> PathBuf {
+        self.reference_dir
+            .join(format!("{}.{extension}", self.name))
+    }
+
+    fn save_actual(&self, actual: &RgbaImage) -> Result<PathBuf, GoldenError> {
+        let path = self.path("actual.png");
+        create_parent_dir(&path)?;
+        actual.save_png(&path)?;
+        Ok(path)
+    }
+}
+
+fn create_parent_dir(path: &Path) -> Result<(), GoldenError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(image::ImageError::IoError)?;
+    }
+    Ok(())
+}
@@ -1,2 +1,7 @@
+pub mod camera;
+pub mod debug_draw;
 pub mod game_objects;
+pub mod post_process;
+pub mod scene_graph;
 pub mod three_d_application_context;
+pub mod transform;
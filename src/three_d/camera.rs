@@ -0,0 +1,242 @@
+use nalgebra::{
+    Isometry3, Matrix4, Orthographic3, Perspective3, Point3, Translation3, UnitQuaternion, Vector3,
+};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Projection {
+    /// Vertical field of view, in radians.
+    Perspective { fov_y: f32 },
+    /// Height of the visible area in world units; the width follows the
+    /// aspect ratio.
+    Orthographic { height: f32 },
+}
+
+/// A viewpoint looking down its local -Z axis, with +Y up.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Camera {
+    pub position: Point3<f32>,
+    pub orientation: UnitQuaternion<f32>,
+    pub projection: Projection,
+    pub near: f32,
+    pub far: f32,
+    aspect_ratio: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::perspective(std::f32::consts::FRAC_PI_4, 0.1, 100.0)
+    }
+}
+
+impl Camera {
+    pub fn perspective(fov_y: f32, near: f32, far: f32) -> Self {
+        Self {
+            position: Point3::origin(),
+            orientation: UnitQuaternion::identity(),
+            projection: Projection::Perspective { fov_y },
+            near,
+            far,
+            aspect_ratio: 1.0,
+        }
+    }
+
+    pub fn orthographic(height: f32, near: f32, far: f32) -> Self {
+        Self {
+            projection: Projection::Orthographic { height },
+            ..Self::perspective(0.0, near, far)
+        }
+    }
+
+    pub fn with_position(mut self, position: Point3<f32>) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Turns the camera towards `target`, keeping `up` as close to the
+    /// camera's +Y as possible. When `up` is parallel to the view direction,
+    /// the camera's current up is kept instead, or when that is parallel too,
+    /// it tilts like a pitch so its top ends up facing away from where it
+    /// looked before.
+    pub fn look_at(&mut self, target: Point3<f32>, up: Vector3<f32>) {
+        let Some(direction) = (target - self.position).try_normalize(f32::EPSILON) else {
+            return;
+        };
+        let Some(up) = [up, self.up(), -self.forward()]
+            .into_iter()
+            .filter_map(|up| up.try_normalize(f32::EPSILON))
+            .find(|up| direction.cross(up).norm_squared() > f32::EPSILON)
+        else {
+            return;
+        };
+        // `face_towards` aligns +Z with the direction, the camera looks down
+        // -Z, hence the negation.
+        self.orientation = UnitQuaternion::face_towards(&-direction, &up);
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.aspect_ratio
+    }
+
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: f32) {
+        if aspect_ratio.is_finite() && aspect_ratio > 0.0 {
+            self.aspect_ratio = aspect_ratio;
+        }
+    }
+
+    pub fn forward(&self) -> Vector3<f32> {
+        self.orientation * -Vector3::z()
+    }
+
+    pub fn right(&self) -> Vector3<f32> {
+        self.orientation * Vector3::x()
+    }
+
+    pub fn up(&self) -> Vector3<f32> {
+        self.orientation * Vector3::y()
+    }
+
+    pub fn view_matrix(&self) -> Matrix4<f32> {
+        Isometry3::from_parts(Translation3::from(self.position.coords), self.orientation)
+            .inverse()
+            .to_homogeneous()
+    }
+
+    pub fn projection_matrix(&self) -> Matrix4<f32> {
+        match self.projection {
+            Projection::Perspective { fov_y } => {
+                Perspective3::new(self.aspect_ratio, fov_y, self.near, self.far).to_homogeneous()
+            }
+            Projection::Orthographic { height } => {
+                let half_height = height / 2.0;
+                let half_width = half_height * self.aspect_ratio;
+                Orthographic3::new(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    self.near,
+                    self.far,
+                )
+                .to_homogeneous()
+            }
+        }
+    }
+
+    pub fn view_projection_matrix(&self) -> Matrix4<f32> {
+        self.projection_matrix() * self.view_matrix()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Vector3<f32>, expected: Vector3<f32>) {
+        assert!(
+            (actual - expected).norm() < 1e-5,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn view_matrix_moves_the_camera_to_the_origin() {
+        let mut camera = Camera::default().with_position(Point3::new(1.0, 2.0, 5.0));
+        let view = camera.view_matrix();
+        assert_close(
+            view.transform_point(&Point3::new(1.0, 2.0, 0.0)).coords,
+            Vector3::new(0.0, 0.0, -5.0),
+        );
+
+        camera.orientation =
+            UnitQuaternion::from_axis_angle(&Vector3::y_axis(), std::f32::consts::FRAC_PI_2);
+        assert_close(camera.forward(), -Vector3::x());
+        let view = camera.view_matrix();
+        assert_close(
+            view.transform_point(&Point3::new(-3.0, 2.0, 5.0)).coords,
+            Vector3::new(0.0, 0.0, -4.0),
+        );
+    }
+
+    #[test]
+    fn look_at_points_forward_at_the_target() {
+        let mut camera = Camera::default().with_position(Point3::new(4.0, 3.0, 4.0));
+        let target = Point3::new(0.0, 1.0, -2.0);
+        camera.look_at(target, Vector3::y());
+        assert_close(camera.forward(), (target - camera.position).normalize());
+        assert!(camera.right().y.abs() < 1e-5);
+        assert!(camera.up().y > 0.0);
+        let in_view = camera.view_matrix().transform_point(&target);
+        assert!(in_view.x.abs() < 1e-5 && in_view.y.abs() < 1e-5 && in_view.z < 0.0);
+    }
+
+    #[test]
+    fn look_at_along_up_keeps_a_valid_orientation() {
+        let mut camera = Camera::default();
+        camera.look_at(Point3::new(0.0, 10.0, 0.0), Vector3::y());
+        assert_close(camera.forward(), Vector3::y());
+        assert_close(camera.up(), Vector3::z());
+
+        camera.look_at(Point3::new(0.0, -10.0, 0.0), Vector3::y());
+        assert_close(camera.forward(), -Vector3::y());
+        assert!(camera.up().iter().all(|value| value.is_finite()));
+        assert!(camera.up().dot(&Vector3::y()).abs() < 1e-5);
+
+        let orientation = camera.orientation;
+        camera.look_at(camera.position, Vector3::y());
+        assert_eq!(camera.orientation, orientation);
+    }
+
+    #[test]
+    fn perspective_projection_maps_near_and_far_to_clip_depth() {
+        let camera = Camera::perspective(std::f32::consts::FRAC_PI_2, 0.5, 50.0);
+        let projection = camera.projection_matrix();
+        assert_close(
+            projection
+                .transform_point(&Point3::new(0.5, 0.5, -0.5))
+                .coords,
+            Vector3::new(1.0, 1.0, -1.0),
+        );
+        assert_close(
+            projection
+                .transform_point(&Point3::new(-50.0, 0.0, -50.0))
+                .coords,
+            Vector3::new(-1.0, 0.0, 1.0),
+        );
+    }
+
+    #[test]
+    fn orthographic_projection_maps_the_visible_area_to_clip_space() {
+        let mut camera = Camera::orthographic(4.0, 0.1, 10.0);
+        camera.set_aspect_ratio(2.0);
+        let projection = camera.projection_matrix();
+        assert_close(
+            projection
+                .transform_point(&Point3::new(4.0, 2.0, -0.1))
+                .coords,
+            Vector3::new(1.0, 1.0, -1.0),
+        );
+        assert_close(
+            projection
+                .transform_point(&Point3::new(-4.0, -2.0, -10.0))
+                .coords,
+            Vector3::new(-1.0, -1.0, 1.0),
+        );
+    }
+
+    #[test]
+    fn projection_follows_the_aspect_ratio() {
+        let mut camera = Camera::default();
+        let square = camera.projection_matrix();
+        // What a resize of the window to 1280x720 sets.
+        camera.set_aspect_ratio(1280.0 / 720.0);
+        let wide = camera.projection_matrix();
+        assert!((wide[(0, 0)] * 1280.0 / 720.0 - square[(0, 0)]).abs() < 1e-5);
+        assert_eq!(wide[(1, 1)], square[(1, 1)]);
+
+        // A minimized window reports a zero size.
+        camera.set_aspect_ratio(1280.0 / 0.0);
+        camera.set_aspect_ratio(f32::NAN);
+        camera.set_aspect_ratio(0.0);
+        assert_eq!(camera.aspect_ratio(), 1280.0 / 720.0);
+    }
+}
@@ -0,0 +1,224 @@
+use super::camera::Camera;
+use crate::common::{
+    color::Color,
+    drawables::RendererContext,
+    errors::GlError,
+    gl,
+    helpers::{Fragment, Shader, Vertex},
+    render_state::RenderState,
+    vertex_layout::{VertexAttribute, VertexLayout},
+    wrappers::{
+        buffer_wrapper::{BufferUsage, BufferWrapper},
+        program_wrapper::ProgramWrapper,
+    },
+};
+use nalgebra::{Matrix4, Point3, Vector3};
+use std::f32::consts::TAU;
+
+static DEBUG_VERTEX: Shader<Vertex> =
+    Shader::create_vertex_shader(include_str!("shaders/debug_vertex_shader.glsl"));
+
+static DEBUG_FRAGMENT: Shader<Fragment> =
+    Shader::create_fragment_shader(include_str!("shaders/debug_fragment_shader.glsl"));
+
+/// Without the `debug-draw` feature every call returns straight away.
+const ENABLED: bool = cfg!(feature = "debug-draw");
+
+const SPHERE_SEGMENTS: usize = 32;
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DebugVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl DebugVertex {
+    pub fn new(position: Point3<f32>, color: Color) -> Self {
+        Self {
+            position: [position.x, position.y, position.z],
+            color: color.to_array(),
+        }
+    }
+
+    pub fn layout() -> VertexLayout {
+        VertexLayout::new()
+            .with_attribute(VertexAttribute::float("position", 3))
+            .with_attribute(VertexAttribute::float("color", 4))
+    }
+}
+
+/// Lines collected during one frame and drawn in a single pass on top of
+/// the scene, ignoring depth.
+pub struct DebugDraw {
+    program_wrapper: ProgramWrapper,
+    layout: VertexLayout,
+    vertex_buffer: BufferWrapper<DebugVertex>,
+    vertices: Vec<DebugVertex>,
+}
+
+impl Default for DebugDraw {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DebugDraw {
+    pub fn new() -> Self {
+        Self {
+            program_wrapper: ProgramWrapper::new(&DEBUG_VERTEX, &DEBUG_FRAGMENT),
+            layout: DebugVertex::layout(),
+            vertex_buffer: BufferWrapper::vertex(Vec::new(), BufferUsage::Stream),
+            vertices: Vec::new(),
+        }
+    }
+
+    /// Whether the crate was built with the `debug-draw` feature.
+    pub const fn is_enabled() -> bool {
+        ENABLED
+    }
+
+    /// Number of lines waiting to be drawn.
+    pub fn len(&self) -> usize {
+        self.vertices.len() / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn line(&mut self, start: Point3<f32>, end: Point3<f32>, color: Color) {
+        if !ENABLED {
+            return;
+        }
+        self.vertices.push(DebugVertex::new(start, color));
+        self.vertices.push(DebugVertex::new(end, color));
+    }
+
+    /// The edges of the axis-aligned box between `min` and `max`.
+    pub fn aabb(&mut self, min: Point3<f32>, max: Point3<f32>, color: Color) {
+        if !ENABLED {
+            return;
+        }
+        let corners: [Point3<f32>; 8] = std::array::from_fn(|i| {
+            Point3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        });
+        self.box_edges(&corners, color);
+    }
+
+    /// Three circles around `center`, one in each axis plane.
+    pub fn sphere(&mut self, center: Point3<f32>, radius: f32, color: Color) {
+        if !ENABLED {
+            return;
+        }
+        let circle_point = |segment: usize, axes: (Vector3<f32>, Vector3<f32>)| {
+            let angle = TAU * segment as f32 / SPHERE_SEGMENTS as f32;
+            center + (axes.0 * angle.cos() + axes.1 * angle.sin()) * radius
+        };
+        for axes in [
+            (Vector3::x(), Vector3::y()),
+            (Vector3::y(), Vector3::z()),
+            (Vector3::z(), Vector3::x()),
+        ] {
+            for segment in 0..SPHERE_SEGMENTS {
+                self.line(
+                    circle_point(segment, axes),
+                    circle_point(segment + 1, axes),
+                    color,
+                );
+            }
+        }
+    }
+
+    /// The X, Y and Z axes of `transform` in red, green and blue, `length`
+    /// units long before scaling.
+    pub fn axes(&mut self, transform: &Matrix4<f32>, length: f32) {
+        if !ENABLED {
+            return;
+        }
+        let origin = transform.transform_point(&Point3::origin());
+        for (axis, color) in [
+            (Vector3::x(), Color::RED),
+            (Vector3::y(), Color::GREEN),
+            (Vector3::z(), Color::BLUE),
+        ] {
+            let end = transform.transform_point(&Point3::from(axis * length));
+            self.line(origin, end, color);
+        }
+    }
+
+    /// The volume `camera` sees, from its near to its far plane.
+    pub fn frustum(&mut self, camera: &Camera, color: Color) {
+        if !ENABLED {
+            return;
+        }
+        let Some(clip_to_world) = camera.view_projection_matrix().try_inverse() else {
+            return;
+        };
+        let corners: [Point3<f32>; 8] = std::array::from_fn(|i| {
+            clip_to_world.transform_point(&Point3::new(
+                if i & 1 == 0 { -1.0 } else { 1.0 },
+                if i & 2 == 0 { -1.0 } else { 1.0 },
+                if i & 4 == 0 { -1.0 } else { 1.0 },
+            ))
+        });
+        self.box_edges(&corners, color);
+    }
+
+    /// Corners are indexed by bits: 1 for +X, 2 for +Y and 4 for +Z.
+    fn box_edges(&mut self, corners: &[Point3<f32>; 8], color: Color) {
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(corners[i], corners[i | bit], color);
+                }
+            }
+        }
+    }
+
+    /// Queues one draw call for every line added since the last flush, using
+    /// the view and projection of `ctx`.
+    pub fn flush(&mut self, ctx: &mut RendererContext) -> Result<(), GlError> {
+        if self.vertices.is_empty() {
+            return Ok(());
+        }
+        let vertex_count = self.vertices.len() as i32;
+        self.vertex_buffer
+            .set_data(std::mem::take(&mut self.vertices));
+        unsafe {
+            let program_id = self.program_wrapper.get_program_id()?;
+            let vao_ref = self.program_wrapper.get_vao_ref();
+            let vbo_ref = self.vertex_buffer.get_buffer_ref();
+            let attribute_bindings = match self.program_wrapper.get_variable_helper() {
+                Some(variable_helper) => Some(variable_helper.resolve_layout(&self.layout)?),
+                None => None,
+            };
+            self.program_wrapper
+                .set_uniform("view", ctx.view_matrix())?;
+            self.program_wrapper
+                .set_uniform("projection", ctx.projection_matrix())?;
+            let uniforms = self.program_wrapper.take_pending_uniforms();
+            let vertex_upload = self.vertex_buffer.take_upload();
+            let gl_state = ctx.gl_state();
+            ctx.add_commands(move || {
+                gl_state.apply(&RenderState::overlay());
+                gl::UseProgram(program_id);
+                uniforms.apply();
+                gl::BindVertexArray(vao_ref);
+                gl::BindBuffer(gl::ARRAY_BUFFER, vbo_ref);
+                if let Some(ref vertex_upload) = vertex_upload {
+                    vertex_upload.apply();
+                }
+                if let Some(ref attribute_bindings) = attribute_bindings {
+                    attribute_bindings.apply();
+                }
+                gl::DrawArrays(gl::LINES, 0, vertex_count);
+            });
+        }
+        Ok(())
+    }
+}
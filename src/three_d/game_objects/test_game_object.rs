@@ -3,9 +3,14 @@ use crate::common::{
     errors::GlError,
     gl,
     helpers::{Fragment, Shader, Vertex},
-    wrappers::program_wrapper::ProgramWrapper,
+    render_state::RenderState,
+    wrappers::{
+        buffer_wrapper::{BufferUsage, BufferWrapper},
+        program_wrapper::ProgramWrapper,
+    },
 };
 use nalgebra::{Matrix3xX, Matrix6xX};
+use std::cell::{RefCell, RefMut};
 
 static FRAGMENT: Shader<Fragment> =
     Shader::create_fragment_shader(include_str!("shaders/fragment_shader.glsl"));
@@ -15,14 +20,23 @@ static VERTEX: Shader<Vertex> =
 
 pub struct TestGameObject {
     program_wrapper: ProgramWrapper,
-    vertices: Matrix6xX<f32>,
+    vertices: RefCell<BufferWrapper<f32>>,
+    /// The vertex data while [`get_data_as_mut`] lends it out as a matrix. It
+    /// moves back into `vertices` on the next draw.
+    ///
+    /// [`get_data_as_mut`]: TestGameObject::get_data_as_mut
+    lent_data: RefCell<Option<Matrix6xX<f32>>>,
 }
 
 impl TestGameObject {
     pub fn new(vertices: Matrix3xX<f32>, colors: Matrix3xX<f32>) -> Self {
         Self {
             program_wrapper: ProgramWrapper::new(&VERTEX, &FRAGMENT),
-            vertices: interleave_matrices(vertices, colors),
+            vertices: RefCell::new(BufferWrapper::vertex(
+                interleave_matrices(vertices, colors).data.into(),
+                BufferUsage::Static,
+            )),
+            lent_data: RefCell::new(None),
         }
     }
 }
@@ -30,28 +44,27 @@ impl TestGameObject {
 impl Drawable for TestGameObject {
     fn draw(&self, ctx: &mut RendererContext<'_>) -> Result<(), GlError> {
         unsafe {
+            let vertices = self.vertices();
             let program_id = self.program_wrapper.get_program_id()?;
             let vao_ref = self.program_wrapper.get_vao_ref();
-            let vbo_ref = self.program_wrapper.get_vbo_ref();
-            let vertices_len = self.vertices.len();
-            let vertices_ptr = self.vertices.as_slice().as_ptr();
-            let num_points = self.vertices.ncols();
+            let vbo_ref = vertices.get_buffer_ref();
+            let num_points = vertices.len() / 6;
             let variable_helper = self.program_wrapper.get_variable_helper();
+            let upload = vertices.take_upload();
+            let gl_state = ctx.gl_state();
             ctx.add_commands(move || {
+                gl_state.apply(&RenderState::default());
                 gl::UseProgram(program_id);
                 gl::BindBuffer(gl::ARRAY_BUFFER, vbo_ref);
                 gl::BindVertexArray(vao_ref);
+                if let Some(ref upload) = upload {
+                    upload.apply();
+                }
                 if let Some(ref var_helper) = variable_helper {
                     var_helper
                         .create_variables(vec!["position", "vertex_color"])
                         .unwrap();
                 }
-                gl::BufferData(
-                    gl::ARRAY_BUFFER,
-                    (vertices_len * std::mem::size_of::<f32>()) as isize,
-                    vertices_ptr as *const _,
-                    gl::STATIC_DRAW,
-                );
                 gl::DrawArrays(gl::TRIANGLE_STRIP, 0, num_points as i32);
             });
             Ok(())
@@ -60,8 +73,23 @@ impl Drawable for TestGameObject {
 }
 
 impl TestGameObject {
+    /// Every column is one vertex: position followed by color. The whole
+    /// buffer is uploaded again on the next draw.
     pub fn get_data_as_mut(&mut self) -> &mut Matrix6xX<f32> {
-        &mut self.vertices
+        let vertices = self.vertices.get_mut();
+        self.lent_data
+            .get_mut()
+            .get_or_insert_with(|| Matrix6xX::from_vec(vertices.take_data()))
+    }
+
+    /// The vertex buffer, with the data lent out by `get_data_as_mut` moved
+    /// back in.
+    fn vertices(&self) -> RefMut<'_, BufferWrapper<f32>> {
+        let mut vertices = self.vertices.borrow_mut();
+        if let Some(data) = self.lent_data.borrow_mut().take() {
+            vertices.set_data(data.data.into());
+        }
+        vertices
     }
 }
 
@@ -79,3 +107,28 @@ fn interleave_matrices(first: Matrix3xX<f32>, second: Matrix3xX<f32>) -> Matrix6
     }
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_through_the_matrix_end_up_in_the_vertex_buffer() {
+        let mut object = TestGameObject::new(
+            Matrix3xX::from_column_slice(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]),
+            Matrix3xX::from_column_slice(&[0.1, 0.2, 0.3, 0.4, 0.5, 0.6]),
+        );
+        assert_eq!(
+            object.vertices().data(),
+            [0.0, 1.0, 2.0, 0.1, 0.2, 0.3, 3.0, 4.0, 5.0, 0.4, 0.5, 0.6]
+        );
+        object.get_data_as_mut()[(0, 1)] = 9.0;
+        object.get_data_as_mut()[(5, 0)] = 1.0;
+        assert!(object.vertices.get_mut().is_empty());
+        assert_eq!(
+            object.vertices().data(),
+            [0.0, 1.0, 2.0, 0.1, 0.2, 1.0, 9.0, 4.0, 5.0, 0.4, 0.5, 0.6]
+        );
+        assert_eq!(object.get_data_as_mut().ncols(), 2);
+    }
+}
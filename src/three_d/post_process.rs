@@ -0,0 +1,319 @@
+use crate::common::{
+    errors::GlError,
+    gl,
+    helpers::{Fragment, Shader, Vertex},
+    render_state::{GlStateTracker, RenderState},
+    render_target::{RenderTarget, RenderTargetBinding},
+    texture::PixelFormat,
+    uniform::{Sampler, UniformValue},
+    wrappers::program_wrapper::ProgramWrapper,
+};
+use nalgebra::Vector2;
+
+static FULLSCREEN_VERTEX: Shader<Vertex> = Shader::create_vertex_shader(include_str!(
+    "post_process/shaders/fullscreen_vertex_shader.glsl"
+));
+
+static TONE_MAPPING: Shader<Fragment> =
+    Shader::create_fragment_shader(include_str!("post_process/shaders/tone_mapping.glsl"));
+
+static FXAA: Shader<Fragment> =
+    Shader::create_fragment_shader(include_str!("post_process/shaders/fxaa.glsl"));
+
+static VIGNETTE: Shader<Fragment> =
+    Shader::create_fragment_shader(include_str!("post_process/shaders/vignette.glsl"));
+
+static COLOR_GRADING: Shader<Fragment> =
+    Shader::create_fragment_shader(include_str!("post_process/shaders/color_grading.glsl"));
+
+static BLOOM: Shader<Fragment> =
+    Shader::create_fragment_shader(include_str!("post_process/shaders/bloom.glsl"));
+
+/// A full-screen effect. The fragment shader receives the previous image as
+/// `uniform sampler2D source`, the size of one of its pixels in UV units as
+/// `uniform vec2 texel_size` and the UV coordinates as `in vec2 uv`.
+pub struct PostProcessPass {
+    name: &'static str,
+    program_wrapper: ProgramWrapper,
+    uniforms: Vec<(&'static str, UniformValue)>,
+    enabled: bool,
+}
+
+impl PostProcessPass {
+    pub fn new(name: &'static str, fragment_shader: &'static Shader<Fragment>) -> Self {
+        Self {
+            name,
+            program_wrapper: ProgramWrapper::new(&FULLSCREEN_VERTEX, fragment_shader),
+            uniforms: Vec::new(),
+            enabled: true,
+        }
+    }
+
+    /// ACES filmic tone mapping, for scenes rendered with
+    /// [`PostProcessStack::with_hdr`].
+    pub fn tone_mapping(exposure: f32) -> Self {
+        Self::new("tone_mapping", &TONE_MAPPING).with_uniform("exposure", exposure)
+    }
+
+    pub fn fxaa() -> Self {
+        Self::new("fxaa", &FXAA)
+    }
+
+    /// Darkens the image outside of `radius` (in UV units) from the center.
+    pub fn vignette(intensity: f32, radius: f32) -> Self {
+        Self::new("vignette", &VIGNETTE)
+            .with_uniform("intensity", intensity)
+            .with_uniform("radius", radius)
+            .with_uniform("softness", 0.45)
+    }
+
+    /// `brightness` is added to every channel, `contrast` and `saturation`
+    /// are factors where `1.0` leaves the image untouched. The result is
+    /// multiplied by a `tint` uniform, white by default.
+    pub fn color_grading(brightness: f32, contrast: f32, saturation: f32) -> Self {
+        Self::new("color_grading", &COLOR_GRADING)
+            .with_uniform("brightness", brightness)
+            .with_uniform("contrast", contrast)
+            .with_uniform("saturation", saturation)
+            .with_uniform("tint", [1.0, 1.0, 1.0])
+    }
+
+    /// Makes everything brighter than `threshold` glow. The blur width is
+    /// controlled by a `radius` uniform, in pixels per tap.
+    pub fn bloom(threshold: f32, intensity: f32) -> Self {
+        Self::new("bloom", &BLOOM)
+            .with_uniform("threshold", threshold)
+            .with_uniform("intensity", intensity)
+            .with_uniform("radius", 2.0)
+    }
+
+    pub fn with_uniform<U>(mut self, name: &'static str, value: U) -> Self
+    where
+        U: Into<UniformValue>,
+    {
+        self.set_uniform(name, value);
+        self
+    }
+
+    pub fn set_uniform<U>(&mut self, name: &'static str, value: U)
+    where
+        U: Into<UniformValue>,
+    {
+        let value = value.into();
+        match self
+            .uniforms
+            .iter_mut()
+            .find(|(existing, _)| *existing == name)
+        {
+            Some((_, existing_value)) => *existing_value = value,
+            None => self.uniforms.push((name, value)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    unsafe fn run(&self, source: &RenderTarget) -> Result<(), GlError> {
+        let program_id = self.program_wrapper.get_program_id()?;
+        let texture = source.texture();
+        self.program_wrapper
+            .set_uniform_if_present("source", Sampler(0))?;
+        self.program_wrapper.set_uniform_if_present(
+            "texel_size",
+            Vector2::new(
+                1.0 / texture.width().max(1) as f32,
+                1.0 / texture.height().max(1) as f32,
+            ),
+        )?;
+        for (name, value) in &self.uniforms {
+            self.program_wrapper.set_uniform(name, value.clone())?;
+        }
+        gl::UseProgram(program_id);
+        self.program_wrapper.take_pending_uniforms().apply();
+        texture.bind(0);
+        gl::BindVertexArray(self.program_wrapper.get_vao_ref());
+        gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        Ok(())
+    }
+}
+
+/// Effects applied to the whole frame. While at least one pass is enabled,
+/// the scene is rendered into an offscreen target, then every enabled pass
+/// runs in order, ping-ponging between two more targets, and the result is
+/// blitted to the window.
+pub struct PostProcessStack {
+    passes: Vec<PostProcessPass>,
+    samples: u8,
+    color_format: PixelFormat,
+    targets: Option<PostProcessTargets>,
+}
+
+struct PostProcessTargets {
+    width: u32,
+    height: u32,
+    scene: RenderTarget,
+    ping_pong: [RenderTarget; 2],
+}
+
+impl Default for PostProcessStack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PostProcessStack {
+    pub fn new() -> Self {
+        Self {
+            passes: Vec::new(),
+            samples: 4,
+            color_format: PixelFormat::Rgba8,
+            targets: None,
+        }
+    }
+
+    pub fn with_pass(mut self, pass: PostProcessPass) -> Self {
+        self.push(pass);
+        self
+    }
+
+    /// MSAA samples used when rendering the scene, `4` by default.
+    pub fn with_samples(mut self, samples: u8) -> Self {
+        self.samples = samples;
+        self.targets = None;
+        self
+    }
+
+    /// Renders the scene to floating point targets so values above `1.0`
+    /// survive until tone mapping.
+    pub fn with_hdr(mut self, hdr: bool) -> Self {
+        self.color_format = match hdr {
+            true => PixelFormat::Rgba32F,
+            false => PixelFormat::Rgba8,
+        };
+        self.targets = None;
+        self
+    }
+
+    pub fn push(&mut self, pass: PostProcessPass) {
+        self.passes.push(pass);
+    }
+
+    pub fn insert(&mut self, index: usize, pass: PostProcessPass) {
+        self.passes.insert(index, pass);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<PostProcessPass> {
+        let index = self.passes.iter().position(|pass| pass.name == name)?;
+        Some(self.passes.remove(index))
+    }
+
+    pub fn clear(&mut self) {
+        self.passes.clear();
+    }
+
+    pub fn pass(&self, name: &str) -> Option<&PostProcessPass> {
+        self.passes.iter().find(|pass| pass.name == name)
+    }
+
+    pub fn pass_mut(&mut self, name: &str) -> Option<&mut PostProcessPass> {
+        self.passes.iter_mut().find(|pass| pass.name == name)
+    }
+
+    pub fn passes(&self) -> &[PostProcessPass] {
+        &self.passes
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.passes.iter().any(PostProcessPass::is_enabled)
+    }
+
+    /// Binds the scene target, (re)creating the targets to match the window
+    /// size. Returns `None` when no pass is enabled and the scene should go
+    /// straight to the window.
+    pub(crate) unsafe fn begin_frame(
+        &mut self,
+        width: i32,
+        height: i32,
+    ) -> Result<Option<RenderTargetBinding>, GlError> {
+        if !self.is_active() || width <= 0 || height <= 0 {
+            return Ok(None);
+        }
+        let (width, height) = (width as u32, height as u32);
+        if !matches!(&self.targets, Some(targets) if targets.width == width && targets.height == height)
+        {
+            let target = || {
+                RenderTarget::new(width, height)
+                    .with_color_format(self.color_format)
+                    .with_depth(false)
+            };
+            self.targets = Some(PostProcessTargets {
+                width,
+                height,
+                scene: RenderTarget::new(width, height)
+                    .with_color_format(self.color_format)
+                    .with_samples(self.samples),
+                ping_pong: [target(), target()],
+            });
+        }
+        let binding = self.targets.as_ref().unwrap().scene.bind()?;
+        binding.apply();
+        Ok(Some(binding))
+    }
+
+    /// Resolves the scene, runs the passes and blits the result to `output`,
+    /// or the window when it is `None`.
+    pub(crate) unsafe fn end_frame(
+        &self,
+        scene: RenderTargetBinding,
+        output: Option<RenderTargetBinding>,
+        gl_state: &GlStateTracker,
+    ) -> Result<(), GlError> {
+        let Some(targets) = &self.targets else {
+            return Ok(());
+        };
+        scene.resolve();
+        gl_state.apply(&RenderState::opaque().with_depth_test(false));
+        let mut source = &targets.scene;
+        for (index, pass) in self.passes.iter().filter(|pass| pass.enabled).enumerate() {
+            let destination = &targets.ping_pong[index % 2];
+            destination.bind()?.apply();
+            pass.run(source)?;
+            source = destination;
+        }
+        let (width, height) = (targets.width as i32, targets.height as i32);
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, source.get_framebuffer_ref()?);
+        gl::BindFramebuffer(
+            gl::DRAW_FRAMEBUFFER,
+            output.map_or(0, |output| output.draw_framebuffer()),
+        );
+        gl::BlitFramebuffer(
+            0,
+            0,
+            width,
+            height,
+            0,
+            0,
+            width,
+            height,
+            gl::COLOR_BUFFER_BIT,
+            gl::NEAREST,
+        );
+        match output {
+            Some(output) => output.apply(),
+            None => {
+                gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                gl::Viewport(0, 0, width, height);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+#version 330 core
+
+in vec2 uv;
+out vec4 frag_color;
+
+uniform sampler2D source;
+uniform vec2 texel_size;
+uniform float threshold;
+uniform float intensity;
+uniform float radius;
+
+const int TAPS = 4;
+
+void main()
+{
+    vec4 color = texture(source, uv);
+    vec3 bloom = vec3(0.0);
+    float total_weight = 0.0;
+    for (int x = -TAPS; x <= TAPS; x++) {
+        for (int y = -TAPS; y <= TAPS; y++) {
+            vec2 offset = vec2(x, y) * texel_size * radius;
+            vec3 sample_color = texture(source, uv + offset).rgb;
+            float brightness = max(sample_color.r, max(sample_color.g, sample_color.b));
+            float contribution = max(brightness - threshold, 0.0) / max(brightness, 0.0001);
+            float weight = exp(-float(x * x + y * y) / float(TAPS * TAPS));
+            bloom += sample_color * contribution * weight;
+            total_weight += weight;
+        }
+    }
+    frag_color = vec4(color.rgb + bloom / total_weight * intensity, color.a);
+}
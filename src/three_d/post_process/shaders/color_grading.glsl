@@ -0,0 +1,20 @@
+#version 330 core
+
+in vec2 uv;
+out vec4 frag_color;
+
+uniform sampler2D source;
+uniform float brightness;
+uniform float contrast;
+uniform float saturation;
+uniform vec3 tint;
+
+void main()
+{
+    vec4 color = texture(source, uv);
+    vec3 graded = color.rgb + brightness;
+    graded = (graded - 0.5) * contrast + 0.5;
+    float luma = dot(graded, vec3(0.2126, 0.7152, 0.0722));
+    graded = mix(vec3(luma), graded, saturation) * tint;
+    frag_color = vec4(max(graded, 0.0), color.a);
+}
@@ -0,0 +1,54 @@
+#version 330 core
+
+in vec2 uv;
+out vec4 frag_color;
+
+uniform sampler2D source;
+uniform vec2 texel_size;
+
+const float SPAN_MAX = 8.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float REDUCE_MIN = 1.0 / 128.0;
+
+float luma(vec3 color)
+{
+    return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+void main()
+{
+    vec4 center = texture(source, uv);
+    float luma_nw = luma(texture(source, uv + vec2(-1.0, -1.0) * texel_size).rgb);
+    float luma_ne = luma(texture(source, uv + vec2(1.0, -1.0) * texel_size).rgb);
+    float luma_sw = luma(texture(source, uv + vec2(-1.0, 1.0) * texel_size).rgb);
+    float luma_se = luma(texture(source, uv + vec2(1.0, 1.0) * texel_size).rgb);
+    float luma_m = luma(center.rgb);
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    vec2 direction = vec2(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se)
+    );
+    float direction_reduce = max(
+        (luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL,
+        REDUCE_MIN
+    );
+    float inverse_direction_min = 1.0 / (min(abs(direction.x), abs(direction.y)) + direction_reduce);
+    direction = clamp(direction * inverse_direction_min, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * texel_size;
+
+    vec3 color_a = 0.5 * (
+        texture(source, uv + direction * (1.0 / 3.0 - 0.5)).rgb +
+        texture(source, uv + direction * (2.0 / 3.0 - 0.5)).rgb
+    );
+    vec3 color_b = color_a * 0.5 + 0.25 * (
+        texture(source, uv + direction * -0.5).rgb +
+        texture(source, uv + direction * 0.5).rgb
+    );
+    float luma_b = luma(color_b);
+    if (luma_b < luma_min || luma_b > luma_max) {
+        frag_color = vec4(color_a, center.a);
+    } else {
+        frag_color = vec4(color_b, center.a);
+    }
+}
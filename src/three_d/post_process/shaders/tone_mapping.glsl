@@ -0,0 +1,24 @@
+#version 330 core
+
+in vec2 uv;
+out vec4 frag_color;
+
+uniform sampler2D source;
+uniform float exposure;
+
+// Narkowicz's fit of the ACES filmic curve.
+vec3 aces(vec3 x)
+{
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), 0.0, 1.0);
+}
+
+void main()
+{
+    vec4 color = texture(source, uv);
+    frag_color = vec4(aces(color.rgb * exposure), color.a);
+}
@@ -0,0 +1,16 @@
+#version 330 core
+
+in vec2 uv;
+out vec4 frag_color;
+
+uniform sampler2D source;
+uniform float intensity;
+uniform float radius;
+uniform float softness;
+
+void main()
+{
+    vec4 color = texture(source, uv);
+    float falloff = 1.0 - smoothstep(radius - softness, radius, distance(uv, vec2(0.5)));
+    frag_color = vec4(color.rgb * mix(1.0, falloff, intensity), color.a);
+}
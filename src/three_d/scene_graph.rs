@@ -0,0 +1,437 @@
+use super::transform::Transform;
+use crate::common::{drawables::Drawable, errors::GlError};
+use nalgebra::Matrix4;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct NodeId {
+    index: usize,
+    generation: u32,
+}
+
+struct Node {
+    transform: Transform,
+    world_matrix: Matrix4<f32>,
+    dirty: bool,
+    visible: bool,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    drawable: Option<Box<dyn Drawable>>,
+}
+
+struct Slot {
+    generation: u32,
+    node: Option<Node>,
+}
+
+/// A hierarchy of nodes, each with a local [`Transform`] and optionally a
+/// drawable. World matrices are only recomputed for nodes whose transform, or
+/// an ancestor's transform, changed since the last update.
+#[derive(Default)]
+pub struct SceneGraph {
+    slots: Vec<Slot>,
+    free_slots: Vec<usize>,
+    roots: Vec<NodeId>,
+}
+
+impl SceneGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_node(&mut self, transform: Transform) -> NodeId {
+        let id = self.allocate(transform, None);
+        self.roots.push(id);
+        id
+    }
+
+    /// Returns `None` if `parent` no longer exists.
+    pub fn add_child(&mut self, parent: NodeId, transform: Transform) -> Option<NodeId> {
+        if !self.contains(parent) {
+            return None;
+        }
+        let id = self.allocate(transform, Some(parent));
+        self.node_mut(parent).children.push(id);
+        Some(id)
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Removes the node along with all of its descendants.
+    pub fn remove(&mut self, id: NodeId) {
+        let Some(parent) = self.get(id).map(|node| node.parent) else {
+            return;
+        };
+        self.detach(id, parent);
+        let mut pending = vec![id];
+        while let Some(id) = pending.pop() {
+            let slot = &mut self.slots[id.index];
+            if let Some(node) = slot.node.take() {
+                pending.extend(node.children);
+                slot.generation += 1;
+                self.free_slots.push(id.index);
+            }
+        }
+    }
+
+    /// Moves `id` under `parent`, or to the top level when `parent` is
+    /// `None`. Returns `false`, leaving the graph untouched, if either node no
+    /// longer exists or if that would make a node its own ancestor.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> bool {
+        if !self.contains(id) || parent.is_some_and(|parent| !self.contains(parent)) {
+            return false;
+        }
+        let mut ancestor = parent;
+        while let Some(ancestor_id) = ancestor {
+            if ancestor_id == id {
+                return false;
+            }
+            ancestor = self.parent(ancestor_id);
+        }
+        let old_parent = self.node(id).parent;
+        self.detach(id, old_parent);
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+        let node = self.node_mut(id);
+        node.parent = parent;
+        node.dirty = true;
+        true
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.get(id).and_then(|node| node.parent)
+    }
+
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        self.get(id).map_or(&[], |node| node.children.as_slice())
+    }
+
+    pub fn transform(&self, id: NodeId) -> Option<&Transform> {
+        self.get(id).map(|node| &node.transform)
+    }
+
+    pub fn transform_mut(&mut self, id: NodeId) -> Option<&mut Transform> {
+        self.get_mut(id).map(|node| {
+            node.dirty = true;
+            &mut node.transform
+        })
+    }
+
+    pub fn set_transform(&mut self, id: NodeId, transform: Transform) {
+        if let Some(node_transform) = self.transform_mut(id) {
+            *node_transform = transform;
+        }
+    }
+
+    /// Returns `false`, dropping `drawable`, if the node no longer exists.
+    pub fn set_drawable<D>(&mut self, id: NodeId, drawable: D) -> bool
+    where
+        D: Drawable + 'static,
+    {
+        self.get_mut(id)
+            .map(|node| node.drawable = Some(Box::new(drawable)))
+            .is_some()
+    }
+
+    pub fn take_drawable(&mut self, id: NodeId) -> Option<Box<dyn Drawable>> {
+        self.get_mut(id).and_then(|node| node.drawable.take())
+    }
+
+    pub fn drawable(&self, id: NodeId) -> Option<&dyn Drawable> {
+        self.get(id).and_then(|node| node.drawable.as_deref())
+    }
+
+    /// Hidden nodes are skipped when drawing, together with their children.
+    /// Returns `false` if the node no longer exists.
+    pub fn set_visible(&mut self, id: NodeId, visible: bool) -> bool {
+        self.get_mut(id)
+            .map(|node| node.visible = visible)
+            .is_some()
+    }
+
+    /// The node's world matrix as of the last [`update_world_transforms`].
+    ///
+    /// [`update_world_transforms`]: SceneGraph::update_world_transforms
+    pub fn world_matrix(&self, id: NodeId) -> Option<Matrix4<f32>> {
+        self.get(id).map(|node| node.world_matrix)
+    }
+
+    pub fn update_world_transforms(&mut self) {
+        let mut pending: Vec<(NodeId, Matrix4<f32>, bool)> = self
+            .roots
+            .iter()
+            .rev()
+            .map(|id| (*id, Matrix4::identity(), false))
+            .collect();
+        while let Some((id, parent_matrix, parent_changed)) = pending.pop() {
+            let node = self.node_mut(id);
+            let changed = node.dirty || parent_changed;
+            if changed {
+                node.world_matrix = parent_matrix * node.transform.to_matrix();
+                node.dirty = false;
+            }
+            let world_matrix = node.world_matrix;
+            pending.extend(
+                node.children
+                    .iter()
+                    .rev()
+                    .map(|child| (*child, world_matrix, changed)),
+            );
+        }
+    }
+
+    /// Visits every visible drawable depth first, parents before children and
+    /// siblings in insertion order, with its world matrix.
+    pub fn for_each_drawable<F>(&self, mut visit: F) -> Result<(), GlError>
+    where
+        F: FnMut(&Matrix4<f32>, &dyn Drawable) -> Result<(), GlError>,
+    {
+        let mut pending: Vec<NodeId> = self.roots.iter().rev().copied().collect();
+        while let Some(id) = pending.pop() {
+            let node = self.node(id);
+            if !node.visible {
+                continue;
+            }
+            if let Some(drawable) = node.drawable.as_deref() {
+                visit(&node.world_matrix, drawable)?;
+            }
+            pending.extend(node.children.iter().rev());
+        }
+        Ok(())
+    }
+
+    fn allocate(&mut self, transform: Transform, parent: Option<NodeId>) -> NodeId {
+        let node = Node {
+            transform,
+            world_matrix: Matrix4::identity(),
+            dirty: true,
+            visible: true,
+            parent,
+            children: Vec::new(),
+            drawable: None,
+        };
+        match self.free_slots.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.node = Some(node);
+                NodeId {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    node: Some(node),
+                });
+                NodeId {
+                    index: self.slots.len() - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    fn detach(&mut self, id: NodeId, parent: Option<NodeId>) {
+        let siblings = match parent {
+            Some(parent) => &mut self.node_mut(parent).children,
+            None => &mut self.roots,
+        };
+        siblings.retain(|sibling| *sibling != id);
+    }
+
+    fn get(&self, id: NodeId) -> Option<&Node> {
+        self.slots
+            .get(id.index)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_ref())
+    }
+
+    fn get_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        self.slots
+            .get_mut(id.index)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_mut())
+    }
+
+    fn node(&self, id: NodeId) -> &Node {
+        self.get(id).expect("invalid scene node")
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        self.get_mut(id).expect("invalid scene node")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::drawables::RendererContext;
+    use nalgebra::{Point3, UnitQuaternion, Vector3};
+
+    struct Marker;
+
+    fn world_origin(graph: &SceneGraph, id: NodeId) -> Point3<f32> {
+        graph
+            .world_matrix(id)
+            .unwrap()
+            .transform_point(&Point3::origin())
+    }
+
+    fn assert_close(actual: Point3<f32>, expected: Point3<f32>) {
+        assert!(
+            (actual - expected).norm() < 1e-5,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    impl Drawable for Marker {
+        fn draw(&self, _ctx: &mut RendererContext) -> Result<(), GlError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn setters_ignore_removed_nodes() {
+        let mut graph = SceneGraph::new();
+        let parent = graph.add_node(Transform::default());
+        let child = graph.add_child(parent, Transform::default()).unwrap();
+        assert!(graph.set_drawable(child, Marker));
+        assert!(graph.set_visible(child, false));
+        graph.remove(parent);
+        assert!(!graph.set_drawable(child, Marker));
+        assert!(!graph.set_visible(child, true));
+        assert!(graph.drawable(child).is_none());
+    }
+
+    #[test]
+    fn stale_ids_do_not_reach_reused_slots() {
+        let mut graph = SceneGraph::new();
+        let removed = graph.add_node(Transform::default());
+        graph.remove(removed);
+        let reused = graph.add_node(Transform::default());
+        assert!(graph.set_drawable(reused, Marker));
+        assert!(!graph.contains(removed));
+        assert!(!graph.set_visible(removed, false));
+        assert!(graph.take_drawable(removed).is_none());
+        let mut visited = 0;
+        graph
+            .for_each_drawable(|_, _| {
+                visited += 1;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(visited, 1);
+    }
+
+    #[test]
+    fn world_matrices_combine_parent_and_child_transforms() {
+        let mut graph = SceneGraph::new();
+        let parent = graph.add_node(
+            Transform::from_translation(Vector3::new(10.0, 0.0, 0.0))
+                .with_rotation(UnitQuaternion::from_axis_angle(
+                    &Vector3::z_axis(),
+                    std::f32::consts::FRAC_PI_2,
+                ))
+                .with_uniform_scale(2.0),
+        );
+        let child = graph
+            .add_child(
+                parent,
+                Transform::from_translation(Vector3::new(1.0, 0.0, 0.0)),
+            )
+            .unwrap();
+        let grandchild = graph
+            .add_child(
+                child,
+                Transform::from_translation(Vector3::new(0.0, 0.0, 3.0)),
+            )
+            .unwrap();
+        graph.update_world_transforms();
+        assert_close(world_origin(&graph, parent), Point3::new(10.0, 0.0, 0.0));
+        assert_close(world_origin(&graph, child), Point3::new(10.0, 2.0, 0.0));
+        assert_close(
+            world_origin(&graph, grandchild),
+            Point3::new(10.0, 2.0, 6.0),
+        );
+    }
+
+    #[test]
+    fn changing_a_transform_updates_the_node_and_its_descendants() {
+        let mut graph = SceneGraph::new();
+        let parent = graph.add_node(Transform::default());
+        let child = graph
+            .add_child(
+                parent,
+                Transform::from_translation(Vector3::new(0.0, 1.0, 0.0)),
+            )
+            .unwrap();
+        let sibling = graph.add_node(Transform::from_translation(Vector3::new(5.0, 0.0, 0.0)));
+        graph.update_world_transforms();
+
+        graph
+            .transform_mut(parent)
+            .unwrap()
+            .translate(Vector3::new(2.0, 0.0, 0.0));
+        assert_close(world_origin(&graph, child), Point3::new(0.0, 1.0, 0.0));
+        graph.update_world_transforms();
+        assert_close(world_origin(&graph, parent), Point3::new(2.0, 0.0, 0.0));
+        assert_close(world_origin(&graph, child), Point3::new(2.0, 1.0, 0.0));
+        assert_close(world_origin(&graph, sibling), Point3::new(5.0, 0.0, 0.0));
+
+        graph
+            .transform_mut(child)
+            .unwrap()
+            .translate(Vector3::new(0.0, 0.0, 4.0));
+        graph.update_world_transforms();
+        assert_close(world_origin(&graph, parent), Point3::new(2.0, 0.0, 0.0));
+        assert_close(world_origin(&graph, child), Point3::new(2.0, 1.0, 4.0));
+    }
+
+    #[test]
+    fn reparenting_recomputes_the_world_matrix() {
+        let mut graph = SceneGraph::new();
+        let left = graph.add_node(Transform::from_translation(Vector3::new(-3.0, 0.0, 0.0)));
+        let right = graph.add_node(Transform::from_translation(Vector3::new(3.0, 0.0, 0.0)));
+        let node = graph
+            .add_child(
+                left,
+                Transform::from_translation(Vector3::new(0.0, 1.0, 0.0)),
+            )
+            .unwrap();
+        graph.update_world_transforms();
+        assert_close(world_origin(&graph, node), Point3::new(-3.0, 1.0, 0.0));
+
+        assert!(graph.set_parent(node, Some(right)));
+        graph.update_world_transforms();
+        assert_close(world_origin(&graph, node), Point3::new(3.0, 1.0, 0.0));
+        assert_eq!(graph.parent(node), Some(right));
+        assert!(graph.children(left).is_empty());
+        assert_eq!(graph.children(right), &[node]);
+
+        assert!(graph.set_parent(node, None));
+        graph.update_world_transforms();
+        assert_close(world_origin(&graph, node), Point3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn set_parent_rejects_cycles_and_stale_ids() {
+        let mut graph = SceneGraph::new();
+        let root = graph.add_node(Transform::default());
+        let child = graph.add_child(root, Transform::default()).unwrap();
+        let removed = graph.add_node(Transform::default());
+        graph.remove(removed);
+
+        assert!(!graph.set_parent(root, Some(child)));
+        assert!(!graph.set_parent(root, Some(root)));
+        assert!(!graph.set_parent(child, Some(removed)));
+        assert!(!graph.set_parent(removed, Some(root)));
+        assert!(!graph.set_parent(removed, None));
+        assert_eq!(graph.parent(child), Some(root));
+        assert_eq!(graph.children(root), &[child]);
+        assert!(graph.add_child(removed, Transform::default()).is_none());
+    }
+}
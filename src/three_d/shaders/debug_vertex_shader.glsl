@@ -0,0 +1,15 @@
+#version 330 core
+
+in vec3 position;
+in vec4 color;
+
+out vec4 line_color;
+
+uniform mat4 view;
+uniform mat4 projection;
+
+void main()
+{
+    gl_Position = projection * view * vec4(position, 1.0);
+    line_color = color;
+}
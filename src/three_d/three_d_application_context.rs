@@ -1,139 +1,421 @@
+use super::{
+    camera::Camera, debug_draw::DebugDraw, post_process::PostProcessStack, scene_graph::SceneGraph,
+};
 use crate::{
     common::{
+        application_context::{ApplicationContext, ContextCore},
+        color::Color,
         drawables::{Drawable, RendererContext},
         errors::GlError,
+        frame_capture::{FrameCapture, FrameCapturer, FrameRecording},
         gl,
+        helpers::load_gl,
+        input::{
+            gamepad::GamepadState, input_map::InputMap, keyboard::KeyboardState, mouse::MouseState,
+        },
+        render_state::RenderState,
+        render_target::{RenderTarget, RenderTargetBinding},
+        rgba_image::RgbaImage,
+        text::{
+            glyph_atlas::GlyphAtlas,
+            layout::{TextLayout, TextStyle},
+        },
     },
-    utils::mut_cell::MutCell,
+    internal::context_backend::{Command, ContextBackend},
+    two_d::{camera::Camera2D, sprite_batch::SpriteBatch},
 };
 use bumpalo::Bump;
-use fnv::FnvHashSet;
 use glutin::prelude::GlDisplay;
-use std::ffi::{CStr, CString};
-use tracing::info;
-use winit::keyboard::NamedKey;
+use nalgebra::{Matrix4, Point2};
+use std::time::Duration;
 
 pub struct ThreeDApplicationContext<'a> {
-    input_events: FnvHashSet<Event>,
-    output_commands: Vec<Command, &'a Bump>,
-    background_color: MutCell<InternalColor>,
+    core: ContextCore<'a>,
+    camera: Camera,
+    post_process: PostProcessStack,
+    output_target: Option<RenderTarget>,
+    frame_capturer: FrameCapturer,
+    debug_draw: DebugDraw,
+    hud_batch: SpriteBatch,
+    glyph_atlas: GlyphAtlas,
     renderer_context: RendererContext<'a>,
     exit_status: Result<(), GlError>,
 }
 
-impl<'a> ThreeDApplicationContext<'a> {
-    pub(crate) fn new<D>(gl_display: &D, bump: &'a Bump) -> Self
+impl ThreeDApplicationContext<'_> {
+    /// Captures this frame once it has been drawn.
+    pub fn capture_frame(&mut self) -> FrameCapture {
+        self.frame_capturer.capture()
+    }
+
+    /// Saves this frame as a PNG once it has been drawn. Encoding happens on
+    /// a background thread.
+    #[cfg(feature = "image")]
+    pub fn save_screenshot<P>(&mut self, path: P)
     where
-        D: GlDisplay,
+        P: Into<std::path::PathBuf>,
     {
-        gl::load_with(|symbol| {
-            let symbol = CString::new(symbol).unwrap();
-            gl_display.get_proc_address(symbol.as_c_str()).cast()
-        });
-        if let Some(renderer) = get_gl_string(gl::RENDERER) {
-            info!("Running on {}", renderer.to_string_lossy());
-        }
-        if let Some(version) = get_gl_string(gl::VERSION) {
-            info!("OpenGL Version {}", version.to_string_lossy());
-        }
-        if let Some(shaders_version) = get_gl_string(gl::SHADING_LANGUAGE_VERSION) {
-            info!("Shaders version on {}", shaders_version.to_string_lossy());
-        }
-        Self {
-            input_events: FnvHashSet::default(),
-            output_commands: Vec::new_in(bump),
-            background_color: MutCell::new(InternalColor::default()),
-            renderer_context: RendererContext::new(bump),
-            exit_status: Ok(()),
-        }
+        self.frame_capturer.capture_to_file(path.into());
     }
 
-    pub(crate) fn pop_all_commands(&mut self) -> Vec<Command, &'a Bump> {
-        let mut output = Vec::new_in(self.renderer_context.bump);
-        std::mem::swap(&mut self.output_commands, &mut output);
-        output
+    /// Reads captures back through a pixel buffer object, so the GPU does
+    /// not stall. Captured frames are then delivered one frame later.
+    pub fn set_capture_through_pixel_buffer(&mut self, enable: bool) {
+        self.frame_capturer.set_use_pixel_buffer(enable);
     }
 
-    pub fn exit(&mut self) {
-        self.output_commands.push(Command::Exit);
+    /// Starts recording, replacing any recording in progress.
+    pub fn start_recording(&mut self, recording: FrameRecording) {
+        self.frame_capturer.start_recording(recording);
     }
 
-    pub(crate) fn resize(&self, width: i32, height: i32) {
-        unsafe {
-            gl::Viewport(0, 0, width, height);
-        }
+    pub fn stop_recording(&mut self) -> Option<FrameRecording> {
+        self.frame_capturer.stop_recording()
+    }
+
+    pub fn recording(&self) -> Option<&FrameRecording> {
+        self.frame_capturer.recording()
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    /// Replaces the camera, keeping the aspect ratio of the window.
+    pub fn set_camera(&mut self, mut camera: Camera) {
+        camera.set_aspect_ratio(self.camera.aspect_ratio());
+        self.camera = camera;
+    }
+
+    pub fn post_process(&self) -> &PostProcessStack {
+        &self.post_process
+    }
+
+    pub fn post_process_mut(&mut self) -> &mut PostProcessStack {
+        &mut self.post_process
     }
 
-    pub fn set_cursor_grab(&mut self, enable: bool) {
-        self.output_commands.push(Command::CursorGrab(enable));
+    pub fn set_post_process(&mut self, post_process: PostProcessStack) {
+        self.post_process = post_process;
     }
 
-    pub fn set_background_color(&mut self, red: u8, green: u8, blue: u8, alpha: u8) {
-        self.background_color.set(InternalColor(
-            red as f32 / u8::MAX as f32,
-            green as f32 / u8::MAX as f32,
-            blue as f32 / u8::MAX as f32,
-            alpha as f32 / u8::MAX as f32,
-        ));
+    /// Lines, boxes and other shapes drawn for this frame only, on top of
+    /// the scene. Does nothing unless the `debug-draw` feature is enabled.
+    pub fn debug(&mut self) -> &mut DebugDraw {
+        &mut self.debug_draw
     }
 
-    pub fn set_cursor_visible(&mut self, enable: bool) {
-        self.output_commands.push(Command::CursorVisible(enable));
+    /// Draws `text` on top of the frame, after post-processing, with the
+    /// top-left corner of its layout at `position` in window pixels.
+    pub fn draw_text(&mut self, text: &str, position: Point2<f32>, style: &TextStyle) {
+        self.draw_text_layout(&TextLayout::new(text, style), position, style);
     }
 
-    pub(crate) fn add_event(&mut self, event: Event) {
-        self.input_events.insert(event);
+    /// Like [`draw_text`], reusing a layout computed beforehand, e.g. to
+    /// measure it.
+    ///
+    /// [`draw_text`]: ThreeDApplicationContext::draw_text
+    pub fn draw_text_layout(
+        &mut self,
+        layout: &TextLayout,
+        position: Point2<f32>,
+        style: &TextStyle,
+    ) {
+        let quads = unsafe { self.glyph_atlas.quads(layout, position) };
+        self.glyph_atlas.queue_uploads(&mut self.renderer_context);
+        self.hud_batch.push_glyphs(style.layer, &quads, style.color);
     }
 
     pub fn draw_game_object<D>(&mut self, object: &D)
     where
         D: Drawable,
     {
+        if self.exit_status.is_err() {
+            return;
+        }
+        self.renderer_context.view_matrix = self.camera.view_matrix();
+        self.renderer_context.projection_matrix = self.camera.projection_matrix();
+        self.renderer_context.model_matrix = Matrix4::identity();
         self.exit_status = object.draw(&mut self.renderer_context);
     }
 
-    pub(crate) unsafe fn draw(&mut self) -> Result<(), GlError> {
+    /// Draws every visible node of `scene`, parents before children, after
+    /// bringing its world matrices up to date.
+    pub fn draw_scene(&mut self, scene: &mut SceneGraph) {
+        if self.exit_status.is_err() {
+            return;
+        }
+        scene.update_world_transforms();
+        let renderer_context = &mut self.renderer_context;
+        renderer_context.view_matrix = self.camera.view_matrix();
+        renderer_context.projection_matrix = self.camera.projection_matrix();
+        self.exit_status = scene.for_each_drawable(|world_matrix, drawable| {
+            renderer_context.model_matrix = *world_matrix;
+            drawable.draw(renderer_context)
+        });
+        renderer_context.model_matrix = Matrix4::identity();
+    }
+
+    /// Runs `draw` with its output going to `target` instead of the window.
+    /// The target is cleared first and, if multisampled, resolved into its
+    /// texture afterwards. Calls can be nested.
+    pub fn render_to<F>(&mut self, target: &RenderTarget, draw: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        if self.exit_status.is_err() {
+            return;
+        }
+        let binding = match unsafe { target.bind() } {
+            Ok(binding) => binding,
+            Err(e) => {
+                self.exit_status = Err(e);
+                return;
+            }
+        };
+        let previous_target = self.renderer_context.current_target.replace(binding);
+        let gl_state = self.renderer_context.gl_state();
+        self.renderer_context
+            .add_commands(move || unsafe { binding.begin(&gl_state) });
+        draw(self);
+        self.renderer_context.current_target = previous_target;
+        let (width, height) = self.renderer_context.viewport;
+        let frame_target = self.renderer_context.frame_target.clone();
+        self.renderer_context.add_commands(move || unsafe {
+            binding.resolve();
+            match previous_target.or(frame_target.get()) {
+                Some(previous_target) => previous_target.apply(),
+                None => {
+                    gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                    gl::Viewport(0, 0, width, height);
+                }
+            }
+        });
+    }
+}
+
+impl<'a> ContextBackend<'a> for ThreeDApplicationContext<'a> {
+    fn new<D>(gl_display: &D, bump: &'a Bump) -> Self
+    where
+        D: GlDisplay,
+    {
+        load_gl(gl_display);
+        Self {
+            core: ContextCore::new(bump),
+            camera: Camera::default(),
+            post_process: PostProcessStack::new(),
+            output_target: None,
+            frame_capturer: FrameCapturer::new(),
+            debug_draw: DebugDraw::new(),
+            hud_batch: SpriteBatch::new(),
+            glyph_atlas: GlyphAtlas::new(),
+            renderer_context: RendererContext::new(bump),
+            exit_status: Ok(()),
+        }
+    }
+
+    fn resize(&mut self, width: i32, height: i32) {
+        self.core.mouse.set_window_size(width as u32, height as u32);
+        if height > 0 {
+            self.camera.set_aspect_ratio(width as f32 / height as f32);
+        }
+        self.renderer_context.viewport = (width, height);
+        unsafe {
+            gl::Viewport(0, 0, width, height);
+        }
+    }
+
+    fn set_output_target(&mut self, target: RenderTarget) {
+        self.resize(target.width() as i32, target.height() as i32);
+        self.output_target = Some(target);
+    }
+
+    unsafe fn read_output_target(&self) -> Result<Option<RgbaImage>, GlError> {
+        self.output_target
+            .as_ref()
+            .map(|target| target.read_pixels())
+            .transpose()
+    }
+
+    fn tick(&mut self, now: Duration) {
+        self.core.timer.tick(now);
+    }
+
+    fn pop_all_commands(&mut self) -> Vec<Command, &'a Bump> {
+        self.core.pop_all_commands()
+    }
+
+    unsafe fn draw(&mut self) -> Result<(), GlError> {
         if let Err(e) = &self.exit_status {
             return Err(e.clone());
         }
-        self.background_color.execute_on_change(|new_value| {
-            gl::ClearColor(new_value.0, new_value.1, new_value.2, new_value.3);
-        });
+        self.core.apply_background_color();
+        // The depth buffer is only cleared while depth writes are enabled.
+        self.renderer_context
+            .gl_state
+            .apply(&RenderState::default());
+        let (width, height) = self.renderer_context.viewport;
+        let output_target = match &self.output_target {
+            Some(output_target) => Some(output_target.bind()?),
+            None => None,
+        };
+        if let Some(output_target) = output_target {
+            output_target.apply();
+        }
+        let scene_target = self.post_process.begin_frame(width, height)?;
+        self.renderer_context
+            .frame_target
+            .set(scene_target.or(output_target));
         gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-        while let Some(commands) = self.renderer_context.command_queue.pop() {
+        self.renderer_context.view_matrix = self.camera.view_matrix();
+        self.renderer_context.projection_matrix = self.camera.projection_matrix();
+        for commands in self.renderer_context.command_queue.drain(..) {
             (commands)();
         }
-        self.input_events.clear();
+        if let Some(scene_target) = scene_target {
+            self.post_process.end_frame(
+                scene_target,
+                output_target,
+                &self.renderer_context.gl_state,
+            )?;
+        }
+        self.draw_debug(output_target)?;
+        self.draw_hud(output_target)?;
+        self.frame_capturer.capture_framebuffer(
+            output_target.map_or(0, |output_target| output_target.draw_framebuffer()),
+            width,
+            height,
+        )?;
+        self.core.end_frame();
         Ok(())
     }
+}
 
-    pub fn is_key_pressed(&self, key: NamedKey) -> bool {
-        self.input_events.contains(&Event::KeyPress(key))
+impl ThreeDApplicationContext<'_> {
+    /// Draws the debug lines straight into the output, after post-processing,
+    /// so effects such as bloom leave them untouched.
+    unsafe fn draw_debug(
+        &mut self,
+        output_target: Option<RenderTargetBinding>,
+    ) -> Result<(), GlError> {
+        if self.debug_draw.is_empty() {
+            return Ok(());
+        }
+        self.bind_output(output_target);
+        self.renderer_context.view_matrix = self.camera.view_matrix();
+        self.renderer_context.projection_matrix = self.camera.projection_matrix();
+        self.debug_draw.flush(&mut self.renderer_context)?;
+        for commands in self.renderer_context.command_queue.drain(..) {
+            (commands)();
+        }
+        Ok(())
     }
-}
 
-#[derive(PartialEq, Eq, Hash)]
-pub(crate) enum Event {
-    KeyPress(NamedKey),
-}
+    /// Draws the text batched during the frame straight into the output, in
+    /// window pixels.
+    unsafe fn draw_hud(
+        &mut self,
+        output_target: Option<RenderTargetBinding>,
+    ) -> Result<(), GlError> {
+        if self.hud_batch.is_empty() {
+            self.hud_batch.end_frame();
+            self.glyph_atlas.end_frame();
+            return Ok(());
+        }
+        self.bind_output(output_target);
+        let (width, height) = self.renderer_context.viewport;
+        let mut camera = Camera2D::new();
+        camera.set_viewport(width as f32, height as f32);
+        self.renderer_context.view_matrix = camera.view_matrix();
+        self.renderer_context.projection_matrix = camera.projection_matrix();
+        self.hud_batch.flush(&mut self.renderer_context)?;
+        self.hud_batch.end_frame();
+        self.glyph_atlas.end_frame();
+        for commands in self.renderer_context.command_queue.drain(..) {
+            (commands)();
+        }
+        Ok(())
+    }
 
-pub(crate) enum Command {
-    Exit,
-    CursorGrab(bool),
-    CursorVisible(bool),
+    unsafe fn bind_output(&self, output_target: Option<RenderTargetBinding>) {
+        match output_target {
+            Some(output_target) => output_target.apply(),
+            None => {
+                let (width, height) = self.renderer_context.viewport;
+                gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                gl::Viewport(0, 0, width, height);
+            }
+        }
+    }
 }
 
-struct InternalColor(f32, f32, f32, f32);
+impl ApplicationContext for ThreeDApplicationContext<'_> {
+    fn exit(&mut self) {
+        self.core.push_command(Command::Exit);
+    }
 
-impl Default for InternalColor {
-    fn default() -> Self {
-        InternalColor(0.1, 0.1, 0.1, 1.0)
+    fn set_cursor_grab(&mut self, enable: bool) {
+        self.core.push_command(Command::CursorGrab(enable));
+    }
+
+    fn set_cursor_visible(&mut self, enable: bool) {
+        self.core.push_command(Command::CursorVisible(enable));
+    }
+
+    fn set_background_color(&mut self, red: u8, green: u8, blue: u8, alpha: u8) {
+        self.core
+            .set_background_color(Color::from_rgba8(red, green, blue, alpha));
+    }
+
+    fn keyboard(&self) -> &KeyboardState {
+        &self.core.keyboard
+    }
+
+    fn keyboard_mut(&mut self) -> &mut KeyboardState {
+        &mut self.core.keyboard
+    }
+
+    fn mouse(&self) -> &MouseState {
+        &self.core.mouse
+    }
+
+    fn mouse_mut(&mut self) -> &mut MouseState {
+        &mut self.core.mouse
+    }
+
+    fn gamepads(&self) -> &GamepadState {
+        &self.core.gamepads
+    }
+
+    fn gamepads_mut(&mut self) -> &mut GamepadState {
+        &mut self.core.gamepads
+    }
+
+    fn input_map(&self) -> &InputMap {
+        &self.core.input_map
+    }
+
+    fn input_map_mut(&mut self) -> &mut InputMap {
+        &mut self.core.input_map
+    }
+
+    fn delta_time(&self) -> Duration {
+        self.core.timer.delta_time()
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.core.timer.elapsed()
+    }
+
+    fn frame_index(&self) -> u64 {
+        self.core.timer.frame_index()
     }
-}
 
-fn get_gl_string(variant: gl::types::GLenum) -> Option<&'static CStr> {
-    unsafe {
-        let s = gl::GetString(variant);
-        (!s.is_null()).then(|| CStr::from_ptr(s.cast()))
+    fn fps(&self) -> f32 {
+        self.core.timer.fps()
     }
 }
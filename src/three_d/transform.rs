@@ -0,0 +1,69 @@
+use nalgebra::{Matrix4, Point3, Translation3, UnitQuaternion, Vector3};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Transform {
+    pub translation: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            translation: Vector3::zeros(),
+            rotation: UnitQuaternion::identity(),
+            scale: Vector3::repeat(1.0),
+        }
+    }
+}
+
+impl Transform {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_translation(translation: Vector3<f32>) -> Self {
+        Self {
+            translation,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_translation(mut self, translation: Vector3<f32>) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: UnitQuaternion<f32>) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vector3<f32>) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_uniform_scale(self, scale: f32) -> Self {
+        self.with_scale(Vector3::repeat(scale))
+    }
+
+    pub fn translate(&mut self, offset: Vector3<f32>) {
+        self.translation += offset;
+    }
+
+    pub fn rotate(&mut self, rotation: UnitQuaternion<f32>) {
+        self.rotation = rotation * self.rotation;
+    }
+
+    /// Scale, then rotate, then translate.
+    pub fn to_matrix(&self) -> Matrix4<f32> {
+        Translation3::from(self.translation).to_homogeneous()
+            * self.rotation.to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&self.scale)
+    }
+
+    pub fn transform_point(&self, point: &Point3<f32>) -> Point3<f32> {
+        Point3::from(self.rotation * point.coords.component_mul(&self.scale) + self.translation)
+    }
+}
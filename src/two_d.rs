@@ -0,0 +1,6 @@
+pub mod camera;
+pub mod shape;
+pub mod sprite;
+pub mod sprite_batch;
+pub mod tessellation;
+pub mod two_d_application_context;
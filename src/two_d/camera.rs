@@ -0,0 +1,183 @@
+use nalgebra::{Matrix4, Orthographic3, Point2, Rotation2, Vector2, Vector3};
+
+/// A camera in pixel space: at zoom `1` without rotation, one world unit is
+/// one pixel, `position` is at the top-left corner of the window and y grows
+/// downwards. Zoom and rotation are around the center of the view.
+#[derive(Clone, Debug)]
+pub struct Camera2D {
+    pub position: Vector2<f32>,
+    pub zoom: f32,
+    pub rotation: f32,
+    viewport: Vector2<f32>,
+}
+
+impl Default for Camera2D {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Camera2D {
+    pub fn new() -> Self {
+        Self {
+            position: Vector2::zeros(),
+            zoom: 1.0,
+            rotation: 0.0,
+            viewport: Vector2::new(1.0, 1.0),
+        }
+    }
+
+    pub fn with_position(mut self, x: f32, y: f32) -> Self {
+        self.position = Vector2::new(x, y);
+        self
+    }
+
+    pub fn with_zoom(mut self, zoom: f32) -> Self {
+        self.zoom = zoom;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn viewport(&self) -> Vector2<f32> {
+        self.viewport
+    }
+
+    pub fn set_viewport(&mut self, width: f32, height: f32) {
+        self.viewport = Vector2::new(width.max(1.0), height.max(1.0));
+    }
+
+    /// The world point shown at the center of the view.
+    pub fn center(&self) -> Point2<f32> {
+        Point2::from(self.position + self.viewport / 2.0)
+    }
+
+    pub fn view_matrix(&self) -> Matrix4<f32> {
+        let half_viewport = self.viewport / 2.0;
+        Matrix4::new_translation(&Vector3::new(half_viewport.x, half_viewport.y, 0.0))
+            * Matrix4::new_rotation(Vector3::z() * -self.rotation)
+            * Matrix4::new_scaling(self.zoom)
+            * Matrix4::new_translation(&-Vector3::new(
+                self.position.x + half_viewport.x,
+                self.position.y + half_viewport.y,
+                0.0,
+            ))
+    }
+
+    pub fn projection_matrix(&self) -> Matrix4<f32> {
+        Orthographic3::new(0.0, self.viewport.x, self.viewport.y, 0.0, -1.0, 1.0).to_homogeneous()
+    }
+
+    pub fn view_projection_matrix(&self) -> Matrix4<f32> {
+        self.projection_matrix() * self.view_matrix()
+    }
+
+    /// Converts a position in window pixels, e.g. from the mouse, to world
+    /// coordinates.
+    pub fn screen_to_world(&self, screen: Point2<f32>) -> Point2<f32> {
+        let half_viewport = self.viewport / 2.0;
+        let offset = Rotation2::new(self.rotation) * (screen.coords - half_viewport) / self.zoom;
+        Point2::from(self.position + half_viewport + offset)
+    }
+
+    pub fn world_to_screen(&self, world: Point2<f32>) -> Point2<f32> {
+        let half_viewport = self.viewport / 2.0;
+        let offset = world.coords - self.position - half_viewport;
+        Point2::from(Rotation2::new(-self.rotation) * offset * self.zoom + half_viewport)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Point3;
+
+    fn assert_close(actual: Point2<f32>, expected: Point2<f32>) {
+        assert!(
+            (actual - expected).norm() < 1e-3,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    fn cameras() -> Vec<Camera2D> {
+        let mut cameras = vec![
+            Camera2D::new(),
+            Camera2D::new().with_position(-40.0, 25.0),
+            Camera2D::new().with_zoom(2.5),
+            Camera2D::new().with_rotation(0.7),
+            Camera2D::new()
+                .with_position(120.0, -30.0)
+                .with_zoom(0.4)
+                .with_rotation(-2.1),
+        ];
+        for camera in &mut cameras {
+            camera.set_viewport(320.0, 200.0);
+        }
+        cameras
+    }
+
+    #[test]
+    fn screen_and_world_conversions_undo_each_other() {
+        let points = [
+            Point2::new(0.0, 0.0),
+            Point2::new(320.0, 200.0),
+            Point2::new(17.0, 150.0),
+            Point2::new(-50.0, 400.0),
+        ];
+        for camera in cameras() {
+            for point in points {
+                assert_close(camera.world_to_screen(camera.screen_to_world(point)), point);
+                assert_close(camera.screen_to_world(camera.world_to_screen(point)), point);
+            }
+        }
+    }
+
+    #[test]
+    fn conversions_match_the_view_matrix() {
+        for camera in cameras() {
+            let world = Point2::new(60.0, -20.0);
+            let view = camera
+                .view_matrix()
+                .transform_point(&Point3::new(world.x, world.y, 0.0));
+            assert_close(camera.world_to_screen(world), Point2::new(view.x, view.y));
+        }
+    }
+
+    #[test]
+    fn the_view_center_stays_put_under_zoom_and_rotation() {
+        for camera in cameras() {
+            assert_close(
+                camera.screen_to_world(Point2::new(160.0, 100.0)),
+                camera.center(),
+            );
+        }
+    }
+
+    #[test]
+    fn zoom_scales_distances_on_screen() {
+        let mut camera = Camera2D::new().with_zoom(2.0);
+        camera.set_viewport(100.0, 100.0);
+        assert_close(
+            camera.world_to_screen(Point2::new(60.0, 50.0)),
+            Point2::new(70.0, 50.0),
+        );
+        assert_close(
+            camera.screen_to_world(Point2::new(100.0, 100.0)),
+            Point2::new(75.0, 75.0),
+        );
+    }
+
+    #[test]
+    fn projection_maps_the_viewport_to_clip_space_with_y_down() {
+        let mut camera = Camera2D::new();
+        camera.set_viewport(320.0, 200.0);
+        let projection = camera.projection_matrix();
+        let top_left = projection.transform_point(&Point3::new(0.0, 0.0, 0.0));
+        let bottom_right = projection.transform_point(&Point3::new(320.0, 200.0, 0.0));
+        assert!((top_left.x + 1.0).abs() < 1e-6 && (top_left.y - 1.0).abs() < 1e-6);
+        assert!((bottom_right.x - 1.0).abs() < 1e-6 && (bottom_right.y + 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,12 @@
+#version 330 core
+
+in vec2 sprite_uv;
+in vec4 tint;
+out vec4 frag_color;
+
+uniform sampler2D sprite_texture;
+
+void main()
+{
+    frag_color = texture(sprite_texture, sprite_uv) * tint;
+}
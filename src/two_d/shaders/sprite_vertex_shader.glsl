@@ -0,0 +1,18 @@
+#version 330 core
+
+in vec2 position;
+in vec2 uv;
+in vec4 color;
+
+out vec2 sprite_uv;
+out vec4 tint;
+
+uniform mat4 view;
+uniform mat4 projection;
+
+void main()
+{
+    gl_Position = projection * view * vec4(position, 0.0, 1.0);
+    sprite_uv = uv;
+    tint = color;
+}
@@ -0,0 +1,179 @@
+use super::{
+    sprite::Rect,
+    tessellation::{self, arc_segments, ellipse_points, Stroke, Triangles},
+};
+use crate::common::color::Color;
+use nalgebra::{Point2, Vector2};
+use std::f32::consts::TAU;
+
+#[derive(Clone, PartialEq, Debug)]
+enum Geometry {
+    Rect(Rect),
+    Ellipse {
+        center: Point2<f32>,
+        radii: Vector2<f32>,
+        segments: Option<u32>,
+    },
+    Polyline {
+        points: Vec<Point2<f32>>,
+        closed: bool,
+    },
+    Polygon(Vec<Point2<f32>>),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ShapeStyle {
+    Fill,
+    Stroke(Stroke),
+}
+
+/// An untextured 2D primitive in world coordinates. Shapes go through the
+/// same batch as sprites and are layered the same way.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Shape {
+    geometry: Geometry,
+    pub style: ShapeStyle,
+    pub color: Color,
+    pub layer: i32,
+}
+
+impl Shape {
+    fn new(geometry: Geometry, style: ShapeStyle) -> Self {
+        Self {
+            geometry,
+            style,
+            color: Color::WHITE,
+            layer: 0,
+        }
+    }
+
+    /// A filled rectangle.
+    pub fn rect(rect: Rect) -> Self {
+        Self::new(Geometry::Rect(rect), ShapeStyle::Fill)
+    }
+
+    /// A filled circle, with a number of segments fitting its radius.
+    pub fn circle(center: Point2<f32>, radius: f32) -> Self {
+        Self::ellipse(center, Vector2::new(radius, radius))
+    }
+
+    pub fn ellipse(center: Point2<f32>, radii: Vector2<f32>) -> Self {
+        Self::new(
+            Geometry::Ellipse {
+                center,
+                radii,
+                segments: None,
+            },
+            ShapeStyle::Fill,
+        )
+    }
+
+    /// A filled polygon, convex or concave, that must not intersect itself.
+    pub fn polygon(points: Vec<Point2<f32>>) -> Self {
+        Self::new(Geometry::Polygon(points), ShapeStyle::Fill)
+    }
+
+    /// A one pixel wide line.
+    pub fn line(start: Point2<f32>, end: Point2<f32>) -> Self {
+        Self::polyline(vec![start, end])
+    }
+
+    /// A one pixel wide line through `points`. Lines are always stroked.
+    pub fn polyline(points: Vec<Point2<f32>>) -> Self {
+        Self::new(
+            Geometry::Polyline {
+                points,
+                closed: false,
+            },
+            ShapeStyle::Stroke(Stroke::default()),
+        )
+    }
+
+    /// Like [`Shape::polyline`], with the last point joined to the first.
+    pub fn closed_polyline(points: Vec<Point2<f32>>) -> Self {
+        Self::new(
+            Geometry::Polyline {
+                points,
+                closed: true,
+            },
+            ShapeStyle::Stroke(Stroke::default()),
+        )
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    /// Draws the outline instead of filling the shape.
+    pub fn with_stroke(mut self, stroke: Stroke) -> Self {
+        self.style = ShapeStyle::Stroke(stroke);
+        self
+    }
+
+    /// Segments used for the outline of ellipses and circles.
+    pub fn with_segments(mut self, segments: u32) -> Self {
+        if let Geometry::Ellipse {
+            segments: ref mut current,
+            ..
+        } = self.geometry
+        {
+            *current = Some(segments.max(3));
+        }
+        self
+    }
+
+    pub fn tessellate(&self) -> Triangles {
+        match (&self.geometry, &self.style) {
+            (Geometry::Rect(rect), ShapeStyle::Fill) => {
+                tessellation::fill_polygon(&rect_corners(rect))
+            }
+            (Geometry::Rect(rect), ShapeStyle::Stroke(stroke)) => {
+                tessellation::stroke_polyline(&rect_corners(rect), true, stroke)
+            }
+            (
+                Geometry::Ellipse {
+                    center,
+                    radii,
+                    segments,
+                },
+                style,
+            ) => {
+                let segments = segments.unwrap_or_else(|| arc_segments(radii.max(), TAU));
+                match style {
+                    ShapeStyle::Fill => tessellation::fill_ellipse(*center, *radii, segments),
+                    ShapeStyle::Stroke(stroke) => tessellation::stroke_polyline(
+                        &ellipse_points(*center, *radii, segments),
+                        true,
+                        stroke,
+                    ),
+                }
+            }
+            (Geometry::Polyline { points, closed }, ShapeStyle::Stroke(stroke)) => {
+                tessellation::stroke_polyline(points, *closed, stroke)
+            }
+            (Geometry::Polyline { points, closed }, ShapeStyle::Fill) => {
+                tessellation::stroke_polyline(points, *closed, &Stroke::default())
+            }
+            (Geometry::Polygon(points), ShapeStyle::Fill) => tessellation::fill_polygon(points),
+            (Geometry::Polygon(points), ShapeStyle::Stroke(stroke)) => {
+                tessellation::stroke_polyline(points, true, stroke)
+            }
+        }
+    }
+}
+
+fn rect_corners(rect: &Rect) -> [Point2<f32>; 4] {
+    let (min, max) = (rect.min(), rect.max());
+    [
+        min,
+        Point2::new(max.x, min.y),
+        max,
+        Point2::new(min.x, max.y),
+    ]
+}
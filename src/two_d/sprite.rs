@@ -0,0 +1,209 @@
+use crate::common::{color::Color, texture::Texture2D};
+use nalgebra::{Point2, Rotation2, Vector2};
+use std::rc::Rc;
+
+/// An axis-aligned rectangle.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub const fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The whole texture, in UV coordinates.
+    pub const fn unit() -> Self {
+        Self::new(0.0, 0.0, 1.0, 1.0)
+    }
+
+    pub fn min(&self) -> Point2<f32> {
+        Point2::new(self.x, self.y)
+    }
+
+    pub fn max(&self) -> Point2<f32> {
+        Point2::new(self.x + self.width, self.y + self.height)
+    }
+
+    pub fn contains(&self, point: Point2<f32>) -> bool {
+        point.x >= self.x
+            && point.y >= self.y
+            && point.x <= self.x + self.width
+            && point.y <= self.y + self.height
+    }
+}
+
+/// A textured, tinted quad. Sprites on higher layers are drawn on top;
+/// within a layer, in the order they were submitted.
+#[derive(Clone)]
+pub struct Sprite {
+    pub texture: Rc<Texture2D>,
+    pub position: Point2<f32>,
+    pub size: Vector2<f32>,
+    /// Pivot for positioning and rotation, relative to the size: `(0, 0)`
+    /// is the top-left corner, `(0.5, 0.5)` the center.
+    pub origin: Vector2<f32>,
+    pub rotation: f32,
+    pub tint: Color,
+    /// The part of the texture shown, in UV coordinates.
+    pub source: Rect,
+    pub layer: i32,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+impl Sprite {
+    /// A sprite showing the whole texture at its size in pixels.
+    pub fn new(texture: Rc<Texture2D>) -> Self {
+        let size = Vector2::new(texture.width() as f32, texture.height() as f32);
+        Self {
+            texture,
+            position: Point2::origin(),
+            size,
+            origin: Vector2::new(0.5, 0.5),
+            rotation: 0.0,
+            tint: Color::WHITE,
+            source: Rect::unit(),
+            layer: 0,
+            flip_x: false,
+            flip_y: false,
+        }
+    }
+
+    pub fn with_position(mut self, x: f32, y: f32) -> Self {
+        self.position = Point2::new(x, y);
+        self
+    }
+
+    pub fn with_size(mut self, width: f32, height: f32) -> Self {
+        self.size = Vector2::new(width, height);
+        self
+    }
+
+    pub fn with_origin(mut self, x: f32, y: f32) -> Self {
+        self.origin = Vector2::new(x, y);
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_tint(mut self, tint: Color) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    /// Shows only `source` of the texture, in pixels, e.g. a frame of a
+    /// sprite sheet. The sprite takes the size of the region.
+    pub fn with_source_pixels(mut self, source: Rect) -> Self {
+        let width = self.texture.width().max(1) as f32;
+        let height = self.texture.height().max(1) as f32;
+        self.source = Rect::new(
+            source.x / width,
+            source.y / height,
+            source.width / width,
+            source.height / height,
+        );
+        self.size = Vector2::new(source.width, source.height);
+        self
+    }
+
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub fn with_flip(mut self, flip_x: bool, flip_y: bool) -> Self {
+        self.flip_x = flip_x;
+        self.flip_y = flip_y;
+        self
+    }
+
+    /// Corners in world space, clockwise from the top-left.
+    pub fn corners(&self) -> [Point2<f32>; 4] {
+        let rotation = Rotation2::new(self.rotation);
+        let offset = self.origin.component_mul(&self.size);
+        [
+            Vector2::new(0.0, 0.0),
+            Vector2::new(self.size.x, 0.0),
+            self.size,
+            Vector2::new(0.0, self.size.y),
+        ]
+        .map(|corner| self.position + rotation * (corner - offset))
+    }
+
+    /// UV coordinates matching [`Sprite::corners`].
+    pub fn uvs(&self) -> [[f32; 2]; 4] {
+        let (mut left, mut right) = (self.source.x, self.source.x + self.source.width);
+        let (mut top, mut bottom) = (self.source.y, self.source.y + self.source.height);
+        if self.flip_x {
+            std::mem::swap(&mut left, &mut right);
+        }
+        if self.flip_y {
+            std::mem::swap(&mut top, &mut bottom);
+        }
+        [[left, top], [right, top], [right, bottom], [left, bottom]]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sheet() -> Rc<Texture2D> {
+        Rc::new(Texture2D::from_rgba8(64, 32, vec![0; 64 * 32 * 4]).unwrap())
+    }
+
+    #[test]
+    fn source_pixels_become_uvs_and_size() {
+        let sprite = Sprite::new(sheet()).with_source_pixels(Rect::new(16.0, 8.0, 16.0, 24.0));
+        assert_eq!(sprite.source, Rect::new(0.25, 0.25, 0.25, 0.75));
+        assert_eq!(sprite.size, Vector2::new(16.0, 24.0));
+        assert_eq!(
+            sprite.uvs(),
+            [[0.25, 0.25], [0.5, 0.25], [0.5, 1.0], [0.25, 1.0]]
+        );
+    }
+
+    #[test]
+    fn flipping_swaps_the_uvs() {
+        let sprite = Sprite::new(sheet()).with_flip(true, false);
+        assert_eq!(
+            sprite.uvs(),
+            [[1.0, 0.0], [0.0, 0.0], [0.0, 1.0], [1.0, 1.0]]
+        );
+        let sprite = Sprite::new(sheet()).with_flip(false, true);
+        assert_eq!(
+            sprite.uvs(),
+            [[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn corners_rotate_around_the_origin() {
+        let sprite = Sprite::new(sheet())
+            .with_size(4.0, 2.0)
+            .with_position(10.0, 10.0)
+            .with_rotation(std::f32::consts::FRAC_PI_2);
+        let expected = [(11.0, 8.0), (11.0, 12.0), (9.0, 12.0), (9.0, 8.0)];
+        for (corner, (x, y)) in sprite.corners().into_iter().zip(expected) {
+            assert!((corner - Point2::new(x, y)).norm() < 1e-5, "{corner:?}");
+        }
+        let sprite = Sprite::new(sheet())
+            .with_size(4.0, 2.0)
+            .with_origin(0.0, 0.0);
+        assert_eq!(sprite.corners()[0], Point2::origin());
+        assert_eq!(sprite.corners()[2], Point2::new(4.0, 2.0));
+    }
+}
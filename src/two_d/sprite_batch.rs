@@ -0,0 +1,416 @@
+use super::{shape::Shape, sprite::Sprite};
+use crate::common::{
+    color::Color,
+    drawables::RendererContext,
+    errors::GlError,
+    gl,
+    helpers::{Fragment, Shader, Vertex},
+    render_state::RenderState,
+    text::glyph_atlas::GlyphQuad,
+    texture::{Texture2D, TextureBinding},
+    uniform::Sampler,
+    vertex_layout::{VertexAttribute, VertexLayout},
+    wrappers::{
+        buffer_wrapper::{BufferUsage, BufferWrapper},
+        program_wrapper::ProgramWrapper,
+    },
+};
+use nalgebra::Point2;
+use std::rc::Rc;
+
+static SPRITE_VERTEX: Shader<Vertex> =
+    Shader::create_vertex_shader(include_str!("shaders/sprite_vertex_shader.glsl"));
+
+static SPRITE_FRAGMENT: Shader<Fragment> =
+    Shader::create_fragment_shader(include_str!("shaders/sprite_fragment_shader.glsl"));
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SpriteVertex {
+    pub position: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl SpriteVertex {
+    pub fn new(position: Point2<f32>, uv: [f32; 2], color: Color) -> Self {
+        Self {
+            position: [position.x, position.y],
+            uv,
+            color: color.to_array(),
+        }
+    }
+
+    pub fn layout() -> VertexLayout {
+        VertexLayout::new()
+            .with_attribute(VertexAttribute::float("position", 2))
+            .with_attribute(VertexAttribute::float("uv", 2))
+            .with_attribute(VertexAttribute::float("color", 4))
+    }
+}
+
+struct BatchItem {
+    layer: i32,
+    texture: Rc<Texture2D>,
+    vertex_start: usize,
+    vertex_count: usize,
+    index_start: usize,
+    index_count: usize,
+}
+
+struct DrawCall {
+    texture: Rc<Texture2D>,
+    index_start: usize,
+    index_count: usize,
+}
+
+/// Collects textured triangles and draws them with as few draw calls as
+/// possible: items are ordered by layer, keeping submission order within a
+/// layer, and consecutive items sharing a texture are drawn together.
+pub struct SpriteBatch {
+    program_wrapper: ProgramWrapper,
+    layout: VertexLayout,
+    vertex_buffer: BufferWrapper<SpriteVertex>,
+    index_buffer: BufferWrapper<u32>,
+    white_texture: Rc<Texture2D>,
+    items: Vec<BatchItem>,
+    vertices: Vec<SpriteVertex>,
+    indices: Vec<u32>,
+    frame_draw_calls: usize,
+    draw_calls: usize,
+}
+
+impl Default for SpriteBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpriteBatch {
+    pub fn new() -> Self {
+        Self {
+            program_wrapper: ProgramWrapper::new(&SPRITE_VERTEX, &SPRITE_FRAGMENT),
+            layout: SpriteVertex::layout(),
+            vertex_buffer: BufferWrapper::vertex(Vec::new(), BufferUsage::Stream),
+            index_buffer: BufferWrapper::index(Vec::new(), BufferUsage::Stream),
+            white_texture: Rc::new(
+                Texture2D::from_rgba8(1, 1, vec![u8::MAX; 4])
+                    .expect("a single pixel is four bytes"),
+            ),
+            items: Vec::new(),
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            frame_draw_calls: 0,
+            draw_calls: 0,
+        }
+    }
+
+    /// A 1x1 white texture, for untextured geometry tinted by vertex colors.
+    pub fn white_texture(&self) -> &Rc<Texture2D> {
+        &self.white_texture
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Number of items waiting for the next flush.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Draw calls issued during the last frame.
+    pub fn draw_calls(&self) -> usize {
+        self.draw_calls
+    }
+
+    pub fn push_sprite(&mut self, sprite: &Sprite) {
+        let corners = sprite.corners();
+        let uvs = sprite.uvs();
+        let vertices: [SpriteVertex; 4] =
+            std::array::from_fn(|i| SpriteVertex::new(corners[i], uvs[i], sprite.tint));
+        self.push_triangles(
+            sprite.layer,
+            sprite.texture.clone(),
+            &vertices,
+            &[0, 1, 2, 0, 2, 3],
+        );
+    }
+
+    pub fn push_shape(&mut self, shape: &Shape) {
+        let triangles = shape.tessellate();
+        let vertices = triangles
+            .points
+            .iter()
+            .map(|point| SpriteVertex::new(*point, [0.5, 0.5], shape.color))
+            .collect::<Vec<_>>();
+        self.push_colored_triangles(shape.layer, &vertices, &triangles.indices);
+    }
+
+    pub fn push_glyphs(&mut self, layer: i32, quads: &[GlyphQuad], color: Color) {
+        for quad in quads {
+            let corners = [
+                quad.min,
+                Point2::new(quad.max.x, quad.min.y),
+                quad.max,
+                Point2::new(quad.min.x, quad.max.y),
+            ];
+            let uvs = [
+                quad.uv_min,
+                [quad.uv_max[0], quad.uv_min[1]],
+                quad.uv_max,
+                [quad.uv_min[0], quad.uv_max[1]],
+            ];
+            let vertices: [SpriteVertex; 4] =
+                std::array::from_fn(|i| SpriteVertex::new(corners[i], uvs[i], color));
+            self.push_triangles(layer, quad.texture.clone(), &vertices, &[0, 1, 2, 0, 2, 3]);
+        }
+    }
+
+    /// Adds indexed triangles; `indices` are relative to `vertices`.
+    pub fn push_triangles(
+        &mut self,
+        layer: i32,
+        texture: Rc<Texture2D>,
+        vertices: &[SpriteVertex],
+        indices: &[u32],
+    ) {
+        if indices.is_empty() {
+            return;
+        }
+        self.items.push(BatchItem {
+            layer,
+            texture,
+            vertex_start: self.vertices.len(),
+            vertex_count: vertices.len(),
+            index_start: self.indices.len(),
+            index_count: indices.len(),
+        });
+        self.vertices.extend_from_slice(vertices);
+        self.indices.extend_from_slice(indices);
+    }
+
+    /// Like [`SpriteBatch::push_triangles`] with the white texture.
+    pub fn push_colored_triangles(
+        &mut self,
+        layer: i32,
+        vertices: &[SpriteVertex],
+        indices: &[u32],
+    ) {
+        self.push_triangles(layer, self.white_texture.clone(), vertices, indices);
+    }
+
+    pub(crate) fn end_frame(&mut self) {
+        self.draw_calls = std::mem::take(&mut self.frame_draw_calls);
+    }
+
+    /// Queues the draw calls for everything pushed so far, using the view and
+    /// projection of `ctx`, and empties the batch.
+    pub fn flush(&mut self, ctx: &mut RendererContext) -> Result<(), GlError> {
+        if self.items.is_empty() {
+            return Ok(());
+        }
+        let (vertices, indices, draw_calls) = self.build_draw_calls();
+        self.vertex_buffer.set_data(vertices);
+        self.index_buffer.set_data(indices);
+        self.frame_draw_calls += draw_calls.len();
+        unsafe { self.queue_draw_calls(ctx, draw_calls) }
+    }
+
+    /// Orders the items by layer and merges consecutive items with the same
+    /// texture into one draw call. Indices are rebased onto the returned
+    /// vertices.
+    fn build_draw_calls(&mut self) -> (Vec<SpriteVertex>, Vec<u32>, Vec<DrawCall>) {
+        // A stable sort, so items within a layer keep their submission order.
+        self.items.sort_by_key(|item| item.layer);
+        let mut vertices = Vec::with_capacity(self.vertices.len());
+        let mut indices = Vec::with_capacity(self.indices.len());
+        let mut draw_calls: Vec<DrawCall> = Vec::new();
+        for item in self.items.drain(..) {
+            let base = vertices.len() as u32;
+            vertices.extend_from_slice(
+                &self.vertices[item.vertex_start..item.vertex_start + item.vertex_count],
+            );
+            let index_start = indices.len();
+            indices.extend(
+                self.indices[item.index_start..item.index_start + item.index_count]
+                    .iter()
+                    .map(|index| index + base),
+            );
+            match draw_calls.last_mut() {
+                Some(draw_call) if Rc::ptr_eq(&draw_call.texture, &item.texture) => {
+                    draw_call.index_count += item.index_count;
+                }
+                _ => draw_calls.push(DrawCall {
+                    texture: item.texture,
+                    index_start,
+                    index_count: item.index_count,
+                }),
+            }
+        }
+        self.vertices.clear();
+        self.indices.clear();
+        (vertices, indices, draw_calls)
+    }
+
+    unsafe fn queue_draw_calls(
+        &self,
+        ctx: &mut RendererContext,
+        draw_calls: Vec<DrawCall>,
+    ) -> Result<(), GlError> {
+        let program_id = self.program_wrapper.get_program_id()?;
+        let vao_ref = self.program_wrapper.get_vao_ref();
+        let vbo_ref = self.vertex_buffer.get_buffer_ref();
+        let ebo_ref = self.index_buffer.get_buffer_ref();
+        let attribute_bindings = match self.program_wrapper.get_variable_helper() {
+            Some(variable_helper) => Some(variable_helper.resolve_layout(&self.layout)?),
+            None => None,
+        };
+        self.program_wrapper
+            .set_uniform("view", ctx.view_matrix())?;
+        self.program_wrapper
+            .set_uniform("projection", ctx.projection_matrix())?;
+        self.program_wrapper
+            .set_uniform("sprite_texture", Sampler(0))?;
+        let uniforms = self.program_wrapper.take_pending_uniforms();
+        let vertex_upload = self.vertex_buffer.take_upload();
+        let index_upload = self.index_buffer.take_upload();
+        let draw_calls = draw_calls
+            .into_iter()
+            .map(|draw_call| {
+                (
+                    TextureBinding {
+                        unit: 0,
+                        texture_id: draw_call.texture.get_texture_id(),
+                    },
+                    draw_call,
+                )
+            })
+            .collect::<Vec<_>>();
+        let gl_state = ctx.gl_state();
+        ctx.add_commands(move || {
+            gl_state.apply(&RenderState::overlay());
+            gl::UseProgram(program_id);
+            uniforms.apply();
+            gl::BindVertexArray(vao_ref);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo_ref);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo_ref);
+            if let Some(ref vertex_upload) = vertex_upload {
+                vertex_upload.apply();
+            }
+            if let Some(ref index_upload) = index_upload {
+                index_upload.apply();
+            }
+            if let Some(ref attribute_bindings) = attribute_bindings {
+                attribute_bindings.apply();
+            }
+            // The textures stay alive until their draw calls have run.
+            for (texture_binding, draw_call) in &draw_calls {
+                texture_binding.apply();
+                gl::DrawElements(
+                    gl::TRIANGLES,
+                    draw_call.index_count as i32,
+                    gl::UNSIGNED_INT,
+                    (draw_call.index_start * std::mem::size_of::<u32>()) as *const _,
+                );
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texture() -> Rc<Texture2D> {
+        Rc::new(Texture2D::from_rgba8(1, 1, vec![u8::MAX; 4]).unwrap())
+    }
+
+    /// A triangle whose x coordinates identify it in the built vertices.
+    fn push(batch: &mut SpriteBatch, layer: i32, texture: &Rc<Texture2D>, id: f32) {
+        let vertices = [0.0, 1.0, 2.0]
+            .map(|y| SpriteVertex::new(Point2::new(id, y), [0.0, 0.0], Color::WHITE));
+        batch.push_triangles(layer, texture.clone(), &vertices, &[0, 1, 2]);
+    }
+
+    fn order(vertices: &[SpriteVertex]) -> Vec<f32> {
+        vertices
+            .chunks(3)
+            .map(|triangle| triangle[0].position[0])
+            .collect()
+    }
+
+    fn calls(draw_calls: &[DrawCall]) -> Vec<(usize, usize)> {
+        draw_calls
+            .iter()
+            .map(|draw_call| (draw_call.index_start, draw_call.index_count))
+            .collect()
+    }
+
+    #[test]
+    fn items_are_sorted_by_layer_keeping_submission_order() {
+        let mut batch = SpriteBatch::new();
+        let texture = texture();
+        push(&mut batch, 2, &texture, 0.0);
+        push(&mut batch, 0, &texture, 1.0);
+        push(&mut batch, 1, &texture, 2.0);
+        push(&mut batch, 0, &texture, 3.0);
+        push(&mut batch, 2, &texture, 4.0);
+        let (vertices, indices, draw_calls) = batch.build_draw_calls();
+        assert_eq!(order(&vertices), [1.0, 3.0, 2.0, 0.0, 4.0]);
+        assert_eq!(indices, (0..15).collect::<Vec<u32>>());
+        assert_eq!(calls(&draw_calls), [(0, 15)]);
+        assert!(batch.is_empty());
+    }
+
+    #[test]
+    fn runs_of_a_texture_merge_and_changes_split() {
+        let mut batch = SpriteBatch::new();
+        let first = texture();
+        let second = texture();
+        push(&mut batch, 0, &first, 0.0);
+        push(&mut batch, 0, &first, 1.0);
+        push(&mut batch, 0, &second, 2.0);
+        push(&mut batch, 0, &first, 3.0);
+        let (_, _, draw_calls) = batch.build_draw_calls();
+        assert_eq!(calls(&draw_calls), [(0, 6), (6, 3), (9, 3)]);
+        assert!(Rc::ptr_eq(&draw_calls[0].texture, &first));
+        assert!(Rc::ptr_eq(&draw_calls[1].texture, &second));
+        assert!(Rc::ptr_eq(&draw_calls[2].texture, &first));
+    }
+
+    #[test]
+    fn sorting_by_layer_can_join_runs_of_a_texture() {
+        let mut batch = SpriteBatch::new();
+        let first = texture();
+        let second = texture();
+        push(&mut batch, 0, &first, 0.0);
+        push(&mut batch, 1, &second, 1.0);
+        push(&mut batch, 0, &first, 2.0);
+        let (vertices, _, draw_calls) = batch.build_draw_calls();
+        assert_eq!(order(&vertices), [0.0, 2.0, 1.0]);
+        assert_eq!(calls(&draw_calls), [(0, 6), (6, 3)]);
+    }
+
+    #[test]
+    fn indices_are_rebased_onto_the_sorted_vertices() {
+        let mut batch = SpriteBatch::new();
+        let texture = texture();
+        let quad = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]
+            .map(|(x, y)| SpriteVertex::new(Point2::new(x, y), [x, y], Color::WHITE));
+        batch.push_triangles(1, texture.clone(), &quad, &[0, 1, 2, 0, 2, 3]);
+        push(&mut batch, 0, &texture, 5.0);
+        let (vertices, indices, _) = batch.build_draw_calls();
+        assert_eq!(vertices.len(), 7);
+        assert_eq!(indices, [0, 1, 2, 3, 4, 5, 3, 5, 6]);
+        assert_eq!(vertices[3], quad[0]);
+    }
+
+    #[test]
+    fn empty_geometry_is_skipped() {
+        let mut batch = SpriteBatch::new();
+        batch.push_colored_triangles(0, &[], &[]);
+        assert!(batch.is_empty());
+    }
+}
@@ -0,0 +1,593 @@
+use nalgebra::{Point2, Vector2};
+use std::f32::consts::{PI, TAU};
+
+const EPSILON: f32 = 1e-6;
+
+/// Triangles as points and indices into them.
+#[derive(Clone, Debug, Default)]
+pub struct Triangles {
+    pub points: Vec<Point2<f32>>,
+    pub indices: Vec<u32>,
+}
+
+impl Triangles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    fn push_point(&mut self, point: Point2<f32>) -> u32 {
+        self.points.push(point);
+        self.points.len() as u32 - 1
+    }
+
+    fn push_triangle(&mut self, a: Point2<f32>, b: Point2<f32>, c: Point2<f32>) {
+        let a = self.push_point(a);
+        let b = self.push_point(b);
+        let c = self.push_point(c);
+        self.indices.extend([a, b, c]);
+    }
+
+    fn push_quad(&mut self, corners: [Point2<f32>; 4]) {
+        let first = self.points.len() as u32;
+        self.points.extend(corners);
+        self.indices
+            .extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+
+    /// A fan around `center` through `outline`, which must be convex.
+    fn push_fan(&mut self, center: Point2<f32>, outline: impl IntoIterator<Item = Point2<f32>>) {
+        let center = self.push_point(center);
+        let first = self.points.len() as u32;
+        self.points.extend(outline);
+        let last = self.points.len() as u32;
+        for index in first..last.saturating_sub(1) {
+            self.indices.extend([center, index, index + 1]);
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum LineJoin {
+    /// Segments are extended until their edges meet, falling back to
+    /// [`LineJoin::Bevel`] past the miter limit.
+    #[default]
+    Miter,
+    Bevel,
+    Round,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum LineCap {
+    /// The line ends exactly at its end points.
+    #[default]
+    Butt,
+    /// The line extends half its width past its end points.
+    Square,
+    Round,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Stroke {
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    /// Longest miter allowed, as a multiple of the width, like SVG's
+    /// `stroke-miterlimit`.
+    pub miter_limit: f32,
+}
+
+impl Default for Stroke {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl Stroke {
+    pub const fn new(width: f32) -> Self {
+        Self {
+            width,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: 4.0,
+        }
+    }
+
+    pub const fn with_join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        self
+    }
+
+    pub const fn with_cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        self
+    }
+
+    pub const fn with_miter_limit(mut self, miter_limit: f32) -> Self {
+        self.miter_limit = miter_limit;
+        self
+    }
+}
+
+/// Segments needed for a smooth arc of `radius` pixels spanning `angle`.
+pub fn arc_segments(radius: f32, angle: f32) -> u32 {
+    let full_circle = (radius.abs().sqrt() * 4.0).clamp(12.0, 128.0);
+    ((full_circle * angle.abs() / TAU).ceil() as u32).max(1)
+}
+
+/// Points on an ellipse, without repeating the first one at the end.
+pub fn ellipse_points(center: Point2<f32>, radii: Vector2<f32>, segments: u32) -> Vec<Point2<f32>> {
+    let segments = segments.max(3);
+    (0..segments)
+        .map(|segment| {
+            let angle = TAU * segment as f32 / segments as f32;
+            center + Vector2::new(angle.cos() * radii.x, angle.sin() * radii.y)
+        })
+        .collect()
+}
+
+pub fn fill_ellipse(center: Point2<f32>, radii: Vector2<f32>, segments: u32) -> Triangles {
+    let mut points = ellipse_points(center, radii, segments);
+    points.push(points[0]);
+    let mut triangles = Triangles::new();
+    triangles.push_fan(center, points);
+    triangles
+}
+
+/// Triangulates a simple polygon, convex or concave, in either winding
+/// order, by ear clipping. Self-intersecting polygons are filled as far as
+/// the algorithm gets, the rest with a fan.
+pub fn fill_polygon(points: &[Point2<f32>]) -> Triangles {
+    let points = dedup_points(points, true);
+    let mut triangles = Triangles {
+        points: points.clone(),
+        indices: Vec::new(),
+    };
+    if points.len() < 3 {
+        return triangles;
+    }
+    let mut remaining: Vec<u32> = (0..points.len() as u32).collect();
+    if signed_area(&points) < 0.0 {
+        remaining.reverse();
+    }
+    let point = |index: u32| points[index as usize];
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find_map(|i| {
+            let (previous, current, next) = (
+                remaining[(i + count - 1) % count],
+                remaining[i],
+                remaining[(i + 1) % count],
+            );
+            let turn = cross(
+                point(current) - point(previous),
+                point(next) - point(current),
+            );
+            if turn.abs() <= EPSILON {
+                return Some((i, None));
+            }
+            if turn < 0.0 {
+                return None;
+            }
+            let contains_other = remaining.iter().any(|&other| {
+                other != previous
+                    && other != current
+                    && other != next
+                    && point(other) != point(previous)
+                    && point(other) != point(current)
+                    && point(other) != point(next)
+                    && in_triangle(point(other), point(previous), point(current), point(next))
+            });
+            (!contains_other).then_some((i, Some([previous, current, next])))
+        });
+        match ear {
+            Some((i, triangle)) => {
+                triangles.indices.extend(triangle.into_iter().flatten());
+                remaining.remove(i);
+            }
+            None => break,
+        }
+    }
+    for i in 1..remaining.len().saturating_sub(1) {
+        triangles
+            .indices
+            .extend([remaining[0], remaining[i], remaining[i + 1]]);
+    }
+    triangles
+}
+
+/// A line of `stroke.width` through `points`. Closed lines join their last
+/// point back to the first and have no caps.
+pub fn stroke_polyline(points: &[Point2<f32>], closed: bool, stroke: &Stroke) -> Triangles {
+    let mut triangles = Triangles::new();
+    let points = dedup_points(points, closed);
+    let half_width = stroke.width / 2.0;
+    if half_width <= 0.0 || points.is_empty() {
+        return triangles;
+    }
+    if points.len() == 1 {
+        push_dot(&mut triangles, points[0], half_width, stroke.cap);
+        return triangles;
+    }
+    let closed = closed && points.len() > 2;
+    let segment_count = if closed {
+        points.len()
+    } else {
+        points.len() - 1
+    };
+    let segment = |i: usize| (points[i], points[(i + 1) % points.len()]);
+    for i in 0..segment_count {
+        let (mut start, mut end) = segment(i);
+        let direction = (end - start).normalize();
+        if !closed && stroke.cap == LineCap::Square {
+            if i == 0 {
+                start -= direction * half_width;
+            }
+            if i == segment_count - 1 {
+                end += direction * half_width;
+            }
+        }
+        let normal = perpendicular(direction) * half_width;
+        triangles.push_quad([start + normal, end + normal, end - normal, start - normal]);
+    }
+    let joints = if closed {
+        0..points.len()
+    } else {
+        1..points.len() - 1
+    };
+    for i in joints {
+        let (previous, _) = segment((i + points.len() - 1) % points.len());
+        let (current, next) = segment(i);
+        push_join(&mut triangles, previous, current, next, half_width, stroke);
+    }
+    if !closed && stroke.cap == LineCap::Round {
+        let (first, second) = segment(0);
+        push_round_cap(&mut triangles, first, first - second, half_width);
+        let (second_to_last, last) = segment(segment_count - 1);
+        push_round_cap(&mut triangles, last, last - second_to_last, half_width);
+    }
+    triangles
+}
+
+fn push_dot(triangles: &mut Triangles, center: Point2<f32>, half_width: f32, cap: LineCap) {
+    match cap {
+        LineCap::Butt => {}
+        LineCap::Square => {
+            let offset = Vector2::new(half_width, half_width);
+            let (min, max) = (center - offset, center + offset);
+            triangles.push_quad([
+                min,
+                Point2::new(max.x, min.y),
+                max,
+                Point2::new(min.x, max.y),
+            ]);
+        }
+        LineCap::Round => {
+            let radii = Vector2::new(half_width, half_width);
+            let dot = fill_ellipse(center, radii, arc_segments(half_width, TAU));
+            append(triangles, dot);
+        }
+    }
+}
+
+/// Fills the gap on the outer side of the corner at `current`.
+fn push_join(
+    triangles: &mut Triangles,
+    previous: Point2<f32>,
+    current: Point2<f32>,
+    next: Point2<f32>,
+    half_width: f32,
+    stroke: &Stroke,
+) {
+    let incoming = (current - previous).normalize();
+    let outgoing = (next - current).normalize();
+    let turn = cross(incoming, outgoing);
+    if turn.abs() <= EPSILON && incoming.dot(&outgoing) > 0.0 {
+        return;
+    }
+    let side = if turn > 0.0 { -1.0 } else { 1.0 };
+    let from = perpendicular(incoming) * half_width * side;
+    let to = perpendicular(outgoing) * half_width * side;
+    match stroke.join {
+        LineJoin::Miter => {
+            let bisector = from + to;
+            let cosine = bisector.norm() / (2.0 * half_width);
+            let miter_length = half_width / cosine.max(EPSILON);
+            if bisector.norm() > EPSILON && 2.0 * miter_length <= stroke.miter_limit * stroke.width
+            {
+                let tip = current + bisector.normalize() * miter_length;
+                triangles.push_quad([current, current + from, tip, current + to]);
+            } else {
+                triangles.push_triangle(current, current + from, current + to);
+            }
+        }
+        LineJoin::Bevel => triangles.push_triangle(current, current + from, current + to),
+        LineJoin::Round => {
+            let start = from.y.atan2(from.x);
+            // A line doubling back on itself goes around the front.
+            let sweep = if turn.abs() <= EPSILON {
+                -PI * side
+            } else {
+                cross(from, to).atan2(from.dot(&to))
+            };
+            push_arc(triangles, current, half_width, start, sweep);
+        }
+    }
+}
+
+/// A half circle around `end`, bulging towards `outward`.
+fn push_round_cap(
+    triangles: &mut Triangles,
+    end: Point2<f32>,
+    outward: Vector2<f32>,
+    half_width: f32,
+) {
+    let normal = perpendicular(outward.normalize());
+    let start = normal.y.atan2(normal.x);
+    push_arc(triangles, end, half_width, start, -PI);
+}
+
+fn push_arc(triangles: &mut Triangles, center: Point2<f32>, radius: f32, start: f32, sweep: f32) {
+    let segments = arc_segments(radius, sweep);
+    triangles.push_fan(
+        center,
+        (0..=segments).map(|segment| {
+            let angle = start + sweep * segment as f32 / segments as f32;
+            center + Vector2::new(angle.cos(), angle.sin()) * radius
+        }),
+    );
+}
+
+fn append(triangles: &mut Triangles, other: Triangles) {
+    let base = triangles.points.len() as u32;
+    triangles.points.extend(other.points);
+    triangles
+        .indices
+        .extend(other.indices.into_iter().map(|index| index + base));
+}
+
+/// Drops repeated consecutive points, which have no direction.
+fn dedup_points(points: &[Point2<f32>], closed: bool) -> Vec<Point2<f32>> {
+    let mut result: Vec<Point2<f32>> = Vec::with_capacity(points.len());
+    for &point in points {
+        if result
+            .last()
+            .is_none_or(|last| (point - last).norm() > EPSILON)
+        {
+            result.push(point);
+        }
+    }
+    if closed && result.len() > 1 && (result[0] - result[result.len() - 1]).norm() <= EPSILON {
+        result.pop();
+    }
+    result
+}
+
+fn signed_area(points: &[Point2<f32>]) -> f32 {
+    let count = points.len();
+    (0..count)
+        .map(|i| cross(points[i].coords, points[(i + 1) % count].coords))
+        .sum::<f32>()
+        / 2.0
+}
+
+fn in_triangle(point: Point2<f32>, a: Point2<f32>, b: Point2<f32>, c: Point2<f32>) -> bool {
+    cross(b - a, point - a) >= 0.0
+        && cross(c - b, point - b) >= 0.0
+        && cross(a - c, point - c) >= 0.0
+}
+
+fn cross(a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+fn perpendicular(vector: Vector2<f32>) -> Vector2<f32> {
+    Vector2::new(-vector.y, vector.x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(coordinates: &[(f32, f32)]) -> Vec<Point2<f32>> {
+        coordinates
+            .iter()
+            .map(|&(x, y)| Point2::new(x, y))
+            .collect()
+    }
+
+    fn triangle_areas(triangles: &Triangles) -> Vec<f32> {
+        triangles
+            .indices
+            .chunks(3)
+            .map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| triangles.points[triangle[i] as usize]);
+                cross(b - a, c - a) / 2.0
+            })
+            .collect()
+    }
+
+    /// Checks that the triangles all wind the same way and exactly cover
+    /// `area`, which overlapping or flipped triangles would not.
+    fn assert_covers(triangles: &Triangles, area: f32) {
+        let areas = triangle_areas(triangles);
+        assert!(
+            areas.iter().all(|&area| area > EPSILON) || areas.iter().all(|&area| area < -EPSILON),
+            "inconsistent or degenerate triangles: {areas:?}"
+        );
+        let total = areas.iter().sum::<f32>().abs();
+        assert!(
+            (total - area).abs() < 1e-3,
+            "covers {total} instead of {area}"
+        );
+    }
+
+    fn total_area(triangles: &Triangles) -> f32 {
+        triangle_areas(triangles)
+            .iter()
+            .map(|area| area.abs())
+            .sum()
+    }
+
+    const SQUARE: [(f32, f32); 4] = [(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)];
+    const L_SHAPE: [(f32, f32); 6] = [
+        (0.0, 0.0),
+        (2.0, 0.0),
+        (2.0, 1.0),
+        (1.0, 1.0),
+        (1.0, 2.0),
+        (0.0, 2.0),
+    ];
+
+    #[test]
+    fn convex_polygons_are_covered() {
+        let triangles = fill_polygon(&points(&SQUARE));
+        assert_eq!(triangles.indices.len(), 6);
+        assert_covers(&triangles, 4.0);
+        let hexagon = ellipse_points(Point2::origin(), Vector2::new(1.0, 1.0), 6);
+        let triangles = fill_polygon(&hexagon);
+        assert_eq!(triangles.indices.len(), 12);
+        assert_covers(&triangles, signed_area(&hexagon).abs());
+    }
+
+    #[test]
+    fn concave_polygons_are_covered() {
+        let triangles = fill_polygon(&points(&L_SHAPE));
+        assert_eq!(triangles.indices.len(), 12);
+        assert_covers(&triangles, 3.0);
+        let star: Vec<Point2<f32>> = (0..10)
+            .map(|i| {
+                let angle = TAU * i as f32 / 10.0;
+                let radius = if i % 2 == 0 { 2.0 } else { 0.8 };
+                Point2::new(angle.cos(), angle.sin()) * radius
+            })
+            .collect();
+        let triangles = fill_polygon(&star);
+        assert_eq!(triangles.indices.len(), 24);
+        assert_covers(&triangles, signed_area(&star).abs());
+    }
+
+    #[test]
+    fn winding_order_does_not_matter() {
+        let mut clockwise = points(&L_SHAPE);
+        clockwise.reverse();
+        assert!(signed_area(&clockwise) < 0.0);
+        let triangles = fill_polygon(&clockwise);
+        assert_eq!(triangles.indices.len(), 12);
+        assert_covers(&triangles, 3.0);
+    }
+
+    #[test]
+    fn collinear_and_duplicate_points_are_skipped() {
+        let square = points(&[
+            (0.0, 0.0),
+            (1.0, 0.0),
+            (1.0, 0.0),
+            (2.0, 0.0),
+            (2.0, 2.0),
+            (2.0, 2.0),
+            (0.0, 2.0),
+            (0.0, 1.0),
+            (0.0, 0.0),
+        ]);
+        let triangles = fill_polygon(&square);
+        assert_eq!(triangles.points.len(), 6);
+        assert_covers(&triangles, 4.0);
+    }
+
+    #[test]
+    fn degenerate_polygons_are_empty() {
+        assert!(fill_polygon(&[]).is_empty());
+        assert!(fill_polygon(&points(&[(0.0, 0.0), (1.0, 1.0)])).is_empty());
+        assert!(fill_polygon(&points(&[(0.0, 0.0), (0.0, 0.0), (0.0, 0.0)])).is_empty());
+    }
+
+    #[test]
+    fn dedup_points_drops_repeats() {
+        let line = points(&[(0.0, 0.0), (0.0, 0.0), (1.0, 0.0), (1.0, 0.0), (0.0, 0.0)]);
+        assert_eq!(
+            dedup_points(&line, false),
+            points(&[(0.0, 0.0), (1.0, 0.0), (0.0, 0.0)])
+        );
+        assert_eq!(dedup_points(&line, true), points(&[(0.0, 0.0), (1.0, 0.0)]));
+        assert_eq!(dedup_points(&points(&[(3.0, 3.0)]), true).len(), 1);
+    }
+
+    #[test]
+    fn caps_extend_the_line() {
+        let line = points(&[(0.0, 0.0), (10.0, 0.0)]);
+        let butt = stroke_polyline(&line, false, &Stroke::new(2.0));
+        assert_eq!(butt.points.len(), 4);
+        assert!((total_area(&butt) - 20.0).abs() < 1e-3);
+        let square = stroke_polyline(&line, false, &Stroke::new(2.0).with_cap(LineCap::Square));
+        assert!((total_area(&square) - 24.0).abs() < 1e-3);
+        let round = stroke_polyline(&line, false, &Stroke::new(20.0).with_cap(LineCap::Round));
+        // Two half circles of radius 10, approximated from the inside.
+        let caps = total_area(&round) - 200.0;
+        let circle = PI * 100.0;
+        assert!(caps < circle && caps > circle * 0.95, "caps cover {caps}");
+    }
+
+    #[test]
+    fn joins_fill_the_outer_corner() {
+        let corner = points(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)]);
+        let segments_area = 40.0;
+        let miter = stroke_polyline(&corner, false, &Stroke::new(2.0));
+        assert!((total_area(&miter) - segments_area - 1.0).abs() < 1e-3);
+        let tip = miter
+            .points
+            .iter()
+            .any(|point| (point - Point2::new(11.0, -1.0)).norm() < 1e-4);
+        assert!(tip, "the miter reaches the outer corner");
+        let bevel = stroke_polyline(&corner, false, &Stroke::new(2.0).with_join(LineJoin::Bevel));
+        assert!((total_area(&bevel) - segments_area - 0.5).abs() < 1e-3);
+        let round = stroke_polyline(&corner, false, &Stroke::new(2.0).with_join(LineJoin::Round));
+        let join = total_area(&round) - segments_area;
+        assert!(join > 0.5 && join < PI / 4.0, "round join covers {join}");
+    }
+
+    #[test]
+    fn sharp_miters_fall_back_to_bevels() {
+        let spike = points(&[(0.0, 0.0), (10.0, 0.0), (0.0, 1.0)]);
+        let limited = stroke_polyline(&spike, false, &Stroke::new(2.0));
+        let bevel = stroke_polyline(&spike, false, &Stroke::new(2.0).with_join(LineJoin::Bevel));
+        assert_eq!(limited.points, bevel.points);
+        let unlimited = stroke_polyline(&spike, false, &Stroke::new(2.0).with_miter_limit(100.0));
+        assert_eq!(unlimited.points.len(), 12);
+        assert!(total_area(&unlimited) > total_area(&bevel));
+    }
+
+    #[test]
+    fn straight_joints_add_nothing() {
+        let line = points(&[(0.0, 0.0), (5.0, 0.0), (10.0, 0.0)]);
+        let triangles = stroke_polyline(&line, false, &Stroke::new(2.0));
+        assert_eq!(triangles.points.len(), 8);
+        assert!((total_area(&triangles) - 20.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn closed_lines_join_every_corner_and_have_no_caps() {
+        let square = points(&SQUARE);
+        let stroke = Stroke::new(0.5).with_cap(LineCap::Square);
+        let triangles = stroke_polyline(&square, true, &stroke);
+        // Four segments and four mitered corners.
+        assert_eq!(triangles.points.len(), 4 * 4 + 4 * 4);
+        assert!((total_area(&triangles) - (4.0 * 1.0 + 4.0 * 0.0625)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn degenerate_strokes() {
+        let line = points(&[(0.0, 0.0), (10.0, 0.0)]);
+        assert!(stroke_polyline(&line, false, &Stroke::new(0.0)).is_empty());
+        assert!(stroke_polyline(&[], false, &Stroke::new(1.0)).is_empty());
+        let dot = points(&[(1.0, 1.0), (1.0, 1.0)]);
+        assert!(stroke_polyline(&dot, false, &Stroke::new(2.0)).is_empty());
+        let square = stroke_polyline(&dot, false, &Stroke::new(2.0).with_cap(LineCap::Square));
+        assert!((total_area(&square) - 4.0).abs() < 1e-3);
+        let round = stroke_polyline(&dot, false, &Stroke::new(2.0).with_cap(LineCap::Round));
+        assert!(total_area(&round) > 0.9 * PI && total_area(&round) < PI);
+    }
+}
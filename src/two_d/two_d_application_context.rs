@@ -0,0 +1,290 @@
+use super::{
+    camera::Camera2D,
+    shape::Shape,
+    sprite::{Rect, Sprite},
+    sprite_batch::SpriteBatch,
+    tessellation::Stroke,
+};
+use crate::{
+    common::{
+        application_context::{ApplicationContext, ContextCore},
+        color::Color,
+        drawables::{Drawable, RendererContext},
+        errors::GlError,
+        gl,
+        helpers::load_gl,
+        input::{
+            gamepad::GamepadState, input_map::InputMap, keyboard::KeyboardState, mouse::MouseState,
+        },
+        render_state::RenderState,
+        render_target::RenderTarget,
+        rgba_image::RgbaImage,
+        text::{
+            glyph_atlas::GlyphAtlas,
+            layout::{TextLayout, TextStyle},
+        },
+    },
+    internal::context_backend::{Command, ContextBackend},
+};
+use bumpalo::Bump;
+use glutin::prelude::GlDisplay;
+use nalgebra::{Matrix4, Point2};
+use std::time::Duration;
+
+pub struct TwoDApplicationContext<'a> {
+    core: ContextCore<'a>,
+    camera: Camera2D,
+    sprite_batch: SpriteBatch,
+    glyph_atlas: GlyphAtlas,
+    output_target: Option<RenderTarget>,
+    renderer_context: RendererContext<'a>,
+    exit_status: Result<(), GlError>,
+}
+
+impl TwoDApplicationContext<'_> {
+    pub fn camera(&self) -> &Camera2D {
+        &self.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera2D {
+        &mut self.camera
+    }
+
+    /// Replaces the camera, keeping the viewport of the window.
+    pub fn set_camera(&mut self, mut camera: Camera2D) {
+        let viewport = self.camera.viewport();
+        camera.set_viewport(viewport.x, viewport.y);
+        self.camera = camera;
+    }
+
+    /// The mouse position in world coordinates.
+    pub fn mouse_world_position(&self) -> Point2<f32> {
+        self.camera.screen_to_world(self.core.mouse.position())
+    }
+
+    pub fn sprite_batch(&self) -> &SpriteBatch {
+        &self.sprite_batch
+    }
+
+    pub fn sprite_batch_mut(&mut self) -> &mut SpriteBatch {
+        &mut self.sprite_batch
+    }
+
+    /// Adds `sprite` to the batch. Sprites are drawn when the batch is
+    /// flushed, at the end of the frame or before the next game object.
+    pub fn draw_sprite(&mut self, sprite: &Sprite) {
+        self.sprite_batch.push_sprite(sprite);
+    }
+
+    /// Adds `shape` to the batch, like [`draw_sprite`].
+    ///
+    /// [`draw_sprite`]: TwoDApplicationContext::draw_sprite
+    pub fn draw_shape(&mut self, shape: &Shape) {
+        self.sprite_batch.push_shape(shape);
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, color: Color) {
+        self.draw_shape(&Shape::rect(rect).with_color(color));
+    }
+
+    pub fn stroke_rect(&mut self, rect: Rect, width: f32, color: Color) {
+        self.draw_shape(
+            &Shape::rect(rect)
+                .with_stroke(Stroke::new(width))
+                .with_color(color),
+        );
+    }
+
+    pub fn fill_circle(&mut self, center: Point2<f32>, radius: f32, color: Color) {
+        self.draw_shape(&Shape::circle(center, radius).with_color(color));
+    }
+
+    pub fn draw_line(&mut self, start: Point2<f32>, end: Point2<f32>, width: f32, color: Color) {
+        self.draw_shape(
+            &Shape::line(start, end)
+                .with_stroke(Stroke::new(width))
+                .with_color(color),
+        );
+    }
+
+    /// Draws `text` in world coordinates, with the top-left corner of its
+    /// layout at `position`. Text is batched like sprites.
+    pub fn draw_text(&mut self, text: &str, position: Point2<f32>, style: &TextStyle) {
+        self.draw_text_layout(&TextLayout::new(text, style), position, style);
+    }
+
+    /// Like [`draw_text`], reusing a layout computed beforehand, e.g. to
+    /// measure it.
+    ///
+    /// [`draw_text`]: TwoDApplicationContext::draw_text
+    pub fn draw_text_layout(
+        &mut self,
+        layout: &TextLayout,
+        position: Point2<f32>,
+        style: &TextStyle,
+    ) {
+        let quads = unsafe { self.glyph_atlas.quads(layout, position) };
+        self.glyph_atlas.queue_uploads(&mut self.renderer_context);
+        self.sprite_batch
+            .push_glyphs(style.layer, &quads, style.color);
+    }
+
+    /// Draws `object` with the camera matrices, after the sprites submitted
+    /// before it.
+    pub fn draw_game_object<D>(&mut self, object: &D)
+    where
+        D: Drawable,
+    {
+        self.flush_sprites();
+        if self.exit_status.is_err() {
+            return;
+        }
+        self.renderer_context.model_matrix = Matrix4::identity();
+        self.exit_status = object.draw(&mut self.renderer_context);
+    }
+
+    fn flush_sprites(&mut self) {
+        self.renderer_context.view_matrix = self.camera.view_matrix();
+        self.renderer_context.projection_matrix = self.camera.projection_matrix();
+        if let Err(e) = self.sprite_batch.flush(&mut self.renderer_context) {
+            self.exit_status = Err(e);
+        }
+    }
+}
+
+impl<'a> ContextBackend<'a> for TwoDApplicationContext<'a> {
+    fn new<D>(gl_display: &D, bump: &'a Bump) -> Self
+    where
+        D: GlDisplay,
+    {
+        load_gl(gl_display);
+        Self {
+            core: ContextCore::new(bump),
+            camera: Camera2D::new(),
+            sprite_batch: SpriteBatch::new(),
+            glyph_atlas: GlyphAtlas::new(),
+            output_target: None,
+            renderer_context: RendererContext::new(bump),
+            exit_status: Ok(()),
+        }
+    }
+
+    fn resize(&mut self, width: i32, height: i32) {
+        self.core.mouse.set_window_size(width as u32, height as u32);
+        self.camera.set_viewport(width as f32, height as f32);
+        self.renderer_context.viewport = (width, height);
+        unsafe {
+            gl::Viewport(0, 0, width, height);
+        }
+    }
+
+    fn set_output_target(&mut self, target: RenderTarget) {
+        self.resize(target.width() as i32, target.height() as i32);
+        self.output_target = Some(target);
+    }
+
+    unsafe fn read_output_target(&self) -> Result<Option<RgbaImage>, GlError> {
+        self.output_target
+            .as_ref()
+            .map(|target| target.read_pixels())
+            .transpose()
+    }
+
+    fn tick(&mut self, now: Duration) {
+        self.core.timer.tick(now);
+    }
+
+    fn pop_all_commands(&mut self) -> Vec<Command, &'a Bump> {
+        self.core.pop_all_commands()
+    }
+
+    unsafe fn draw(&mut self) -> Result<(), GlError> {
+        self.flush_sprites();
+        if let Err(e) = &self.exit_status {
+            return Err(e.clone());
+        }
+        self.sprite_batch.end_frame();
+        self.glyph_atlas.end_frame();
+        self.core.apply_background_color();
+        // The depth buffer is only cleared while depth writes are enabled.
+        self.renderer_context
+            .gl_state
+            .apply(&RenderState::default());
+        if let Some(output_target) = &self.output_target {
+            output_target.bind()?.apply();
+        }
+        gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        for commands in self.renderer_context.command_queue.drain(..) {
+            (commands)();
+        }
+        self.core.end_frame();
+        Ok(())
+    }
+}
+
+impl ApplicationContext for TwoDApplicationContext<'_> {
+    fn exit(&mut self) {
+        self.core.push_command(Command::Exit);
+    }
+
+    fn set_cursor_grab(&mut self, enable: bool) {
+        self.core.push_command(Command::CursorGrab(enable));
+    }
+
+    fn set_cursor_visible(&mut self, enable: bool) {
+        self.core.push_command(Command::CursorVisible(enable));
+    }
+
+    fn set_background_color(&mut self, red: u8, green: u8, blue: u8, alpha: u8) {
+        self.core
+            .set_background_color(Color::from_rgba8(red, green, blue, alpha));
+    }
+
+    fn keyboard(&self) -> &KeyboardState {
+        &self.core.keyboard
+    }
+
+    fn keyboard_mut(&mut self) -> &mut KeyboardState {
+        &mut self.core.keyboard
+    }
+
+    fn mouse(&self) -> &MouseState {
+        &self.core.mouse
+    }
+
+    fn mouse_mut(&mut self) -> &mut MouseState {
+        &mut self.core.mouse
+    }
+
+    fn gamepads(&self) -> &GamepadState {
+        &self.core.gamepads
+    }
+
+    fn gamepads_mut(&mut self) -> &mut GamepadState {
+        &mut self.core.gamepads
+    }
+
+    fn input_map(&self) -> &InputMap {
+        &self.core.input_map
+    }
+
+    fn input_map_mut(&mut self) -> &mut InputMap {
+        &mut self.core.input_map
+    }
+
+    fn delta_time(&self) -> Duration {
+        self.core.timer.delta_time()
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.core.timer.elapsed()
+    }
+
+    fn frame_index(&self) -> u64 {
+        self.core.timer.frame_index()
+    }
+
+    fn fps(&self) -> f32 {
+        self.core.timer.fps()
+    }
+}
@@ -0,0 +1,131 @@
+#!/usr/bin/env python3
+"""Writes boxes.ttf, a font for tests whose glyphs are plain rectangles.
+
+Metrics, in font units with 1000 units per em:
+- ascent 800, descent -200, no line gap, so a size of 100 px scales by 0.1;
+- advance 250 for the space, 200 for "i", 800 for "M" and "W", 500 otherwise;
+- kerning of -100 between "A" and "V", in both orders;
+- capitals and digits are 700 units high, other glyphs 500.
+"""
+
+import struct
+from pathlib import Path
+
+FIRST, LAST = 32, 126
+ASCENT, DESCENT = 800, -200
+KERNING = {("A", "V"): -100, ("V", "A"): -100}
+
+
+def advance(character):
+    return {" ": 250, "i": 200, "M": 800, "W": 800}.get(character, 500)
+
+
+def glyph_id(character):
+    return ord(character) - FIRST + 1
+
+
+def rectangle(x_min, y_min, x_max, y_max):
+    # One clockwise contour of four on-curve points.
+    points = [(x_min, y_min), (x_min, y_max), (x_max, y_max), (x_max, y_min)]
+    data = struct.pack(">hhhhh", 1, x_min, y_min, x_max, y_max)
+    data += struct.pack(">HH", 3, 0) + bytes([1] * 4)
+    previous = (0, 0)
+    deltas = []
+    for point in points:
+        deltas.append((point[0] - previous[0], point[1] - previous[1]))
+        previous = point
+    data += b"".join(struct.pack(">h", dx) for dx, _ in deltas)
+    data += b"".join(struct.pack(">h", dy) for _, dy in deltas)
+    return data
+
+
+def glyphs():
+    """(advance, outline) for .notdef and every printable ASCII character."""
+    result = [(500, rectangle(50, 0, 450, 700))]
+    for code in range(FIRST, LAST + 1):
+        character = chr(code)
+        width = advance(character)
+        if character == " ":
+            outline = b""
+        else:
+            top = 700 if character.isupper() or character.isdigit() else 500
+            outline = rectangle(50, 0, width - 50, top)
+        result.append((width, outline))
+    return result
+
+
+def cmap():
+    # One segment mapping the printable characters to consecutive glyphs and
+    # the final segment the format requires.
+    end_codes = [LAST, 0xFFFF]
+    start_codes = [FIRST, 0xFFFF]
+    deltas = [(1 - FIRST) & 0xFFFF, 1]
+    seg_count = len(end_codes)
+    body = b"".join(struct.pack(">H", code) for code in end_codes) + struct.pack(">H", 0)
+    body += b"".join(struct.pack(">H", code) for code in start_codes)
+    body += b"".join(struct.pack(">H", delta) for delta in deltas)
+    body += b"".join(struct.pack(">H", 0) for _ in range(seg_count))
+    header = struct.pack(">HHHHHHH", 4, 14 + len(body), 0, seg_count * 2, 4, 1, 0)
+    return struct.pack(">HHHHI", 0, 1, 3, 1, 12) + header + body
+
+
+def kern():
+    pairs = sorted((glyph_id(a), glyph_id(b), value) for (a, b), value in KERNING.items())
+    body = struct.pack(">HHHH", len(pairs), 12, 1, len(pairs) * 6 - 12)
+    body += b"".join(struct.pack(">HHh", *pair) for pair in pairs)
+    subtable = struct.pack(">HHH", 0, 6 + len(body), 0x0001) + body
+    return struct.pack(">HH", 0, 1) + subtable
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(f">{len(data) // 4}I", data)) & 0xFFFFFFFF
+
+
+def font():
+    all_glyphs = glyphs()
+    glyf, loca = b"", []
+    for _, outline in all_glyphs:
+        loca.append(len(glyf))
+        glyf += outline + b"\0" * (-len(outline) % 4)
+    loca.append(len(glyf))
+    max_advance = max(width for width, _ in all_glyphs)
+    tables = {
+        b"cmap": cmap(),
+        b"glyf": glyf,
+        b"head": struct.pack(
+            ">IIIIHHqqhhhhHHhhh",
+            0x00010000, 0x00010000, 0, 0x5F0F3CF5, 0x000B, 1000, 0, 0,
+            0, DESCENT, 750, ASCENT, 0, 8, 2, 1, 0,
+        ),
+        b"hhea": struct.pack(
+            ">IhhhHhhhhhhhhhhhH",
+            0x00010000, ASCENT, DESCENT, 0, max_advance, 0, 50, 750,
+            1, 0, 0, 0, 0, 0, 0, 0, len(all_glyphs),
+        ),
+        b"hmtx": b"".join(
+            struct.pack(">Hh", width, 50 if outline else 0) for width, outline in all_glyphs
+        ),
+        b"kern": kern(),
+        b"loca": b"".join(struct.pack(">I", offset) for offset in loca),
+        b"maxp": struct.pack(
+            ">IHHHHHHHHHHHHHH",
+            0x00010000, len(all_glyphs), 4, 1, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0,
+        ),
+    }
+    count = len(tables)
+    directory = struct.pack(">IHHHH", 0x00010000, count, 128, 3, count * 16 - 128)
+    offset = len(directory) + count * 16
+    records, data = b"", b""
+    for tag, table in sorted(tables.items()):
+        records += tag + struct.pack(">III", checksum(table), offset + len(data), len(table))
+        data += table + b"\0" * (-len(table) % 4)
+    result = bytearray(directory + records + data)
+    head_offset = offset + data.index(tables[b"head"])
+    adjustment = (0xB1B0AFBA - checksum(bytes(result))) & 0xFFFFFFFF
+    result[head_offset + 8:head_offset + 12] = struct.pack(">I", adjustment)
+    return bytes(result)
+
+
+if __name__ == "__main__":
+    Path(__file__).with_name("boxes.ttf").write_bytes(font())
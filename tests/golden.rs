@@ -0,0 +1,279 @@
+use duende::{
+    common::{
+        application_context::ApplicationContext,
+        color::Color,
+        game::Game,
+        text::{
+            font::Font,
+            layout::{TextAlign, TextLayout, TextStyle},
+        },
+        texture::{Texture2D, TextureOptions},
+    },
+    testing::GoldenTest,
+    three_d::{
+        game_objects::test_game_object::TestGameObject,
+        post_process::{PostProcessPass, PostProcessStack},
+        three_d_application_context::ThreeDApplicationContext,
+    },
+    two_d::{
+        shape::Shape,
+        sprite::{Rect, Sprite},
+        tessellation::{LineCap, LineJoin, Stroke},
+        two_d_application_context::TwoDApplicationContext,
+    },
+    Matrix3xX, Point2,
+};
+use std::rc::Rc;
+
+struct TriangleStrip {
+    object: TestGameObject,
+}
+
+impl TriangleStrip {
+    fn new() -> Self {
+        Self {
+            object: TestGameObject::new(
+                Matrix3xX::from_column_slice(&[
+                    0.0, -0.9, 0.0, -0.6, 0.8, 0.0, 0.9, -0.2, 0.0, -0.9, -0.2, 0.0, 0.6, 0.8, 0.0,
+                ]),
+                Matrix3xX::from_column_slice(&[
+                    1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0, 1.0, 0.0,
+                ]),
+            ),
+        }
+    }
+}
+
+impl Game for TriangleStrip {
+    type Context<'a> = ThreeDApplicationContext<'a>;
+
+    fn game_loop(&mut self, context: &mut ThreeDApplicationContext) {
+        context.draw_game_object(&self.object);
+    }
+}
+
+/// The triangle strip through every built-in pass, in HDR so tone mapping
+/// and bloom have values above `1.0` to work with.
+struct PostProcessed {
+    strip: TriangleStrip,
+}
+
+impl Game for PostProcessed {
+    type Context<'a> = ThreeDApplicationContext<'a>;
+
+    fn setup(&mut self, context: &mut ThreeDApplicationContext) {
+        context.set_background_color(96, 96, 96, 255);
+        context.set_post_process(
+            PostProcessStack::new()
+                .with_hdr(true)
+                .with_pass(PostProcessPass::bloom(0.8, 1.5))
+                .with_pass(PostProcessPass::tone_mapping(2.0))
+                .with_pass(PostProcessPass::fxaa())
+                .with_pass(PostProcessPass::vignette(1.0, 0.6))
+                .with_pass(PostProcessPass::color_grading(0.0, 1.2, 0.5)),
+        );
+    }
+
+    fn game_loop(&mut self, context: &mut ThreeDApplicationContext) {
+        self.strip.game_loop(context);
+    }
+}
+
+struct Shapes;
+
+impl Game for Shapes {
+    type Context<'a> = TwoDApplicationContext<'a>;
+
+    fn setup(&mut self, context: &mut TwoDApplicationContext) {
+        context.set_background_color(0, 0, 0, 255);
+    }
+
+    fn game_loop(&mut self, context: &mut TwoDApplicationContext) {
+        context.fill_rect(Rect::new(8.0, 8.0, 32.0, 20.0), Color::RED);
+        context.stroke_rect(Rect::new(48.0, 8.0, 32.0, 20.0), 3.0, Color::GREEN);
+        context.fill_circle(Point2::new(104.0, 18.0), 12.0, Color::BLUE);
+        let star = (0..10)
+            .map(|i| {
+                let angle = std::f32::consts::TAU * i as f32 / 10.0;
+                let radius = if i % 2 == 0 { 24.0 } else { 10.0 };
+                Point2::new(32.0 + angle.sin() * radius, 64.0 - angle.cos() * radius)
+            })
+            .collect();
+        context.draw_shape(&Shape::polygon(star).with_color(Color::YELLOW));
+        context.draw_shape(
+            &Shape::polyline(vec![
+                Point2::new(72.0, 48.0),
+                Point2::new(112.0, 56.0),
+                Point2::new(84.0, 84.0),
+            ])
+            .with_stroke(
+                Stroke::new(6.0)
+                    .with_join(LineJoin::Round)
+                    .with_cap(LineCap::Round),
+            )
+            .with_color(Color::CYAN)
+            .with_layer(-1),
+        );
+    }
+}
+
+/// Sprites cut from a 16x16 sheet with a red, green, blue and white
+/// quadrant.
+struct Sprites {
+    sheet: Rc<Texture2D>,
+}
+
+impl Sprites {
+    fn new() -> Self {
+        let quadrant = |x: usize, y: usize| match (x < 8, y < 8) {
+            (true, true) => [255, 0, 0, 255],
+            (false, true) => [0, 255, 0, 255],
+            (true, false) => [0, 0, 255, 255],
+            (false, false) => [255, 255, 255, 255],
+        };
+        let pixels = (0..16 * 16)
+            .flat_map(|i| quadrant(i % 16, i / 16))
+            .collect();
+        Self {
+            sheet: Rc::new(
+                Texture2D::from_rgba8(16, 16, pixels)
+                    .unwrap()
+                    .with_options(TextureOptions::pixelated()),
+            ),
+        }
+    }
+}
+
+impl Game for Sprites {
+    type Context<'a> = TwoDApplicationContext<'a>;
+
+    fn setup(&mut self, context: &mut TwoDApplicationContext) {
+        context.set_background_color(0, 0, 0, 255);
+    }
+
+    fn game_loop(&mut self, context: &mut TwoDApplicationContext) {
+        let sprite = || Sprite::new(self.sheet.clone()).with_size(24.0, 24.0);
+        // Only the green quadrant.
+        context.draw_sprite(
+            &Sprite::new(self.sheet.clone())
+                .with_source_pixels(Rect::new(8.0, 0.0, 8.0, 8.0))
+                .with_size(24.0, 24.0)
+                .with_position(20.0, 20.0),
+        );
+        // Mirrored: green on the left, red on the right.
+        context.draw_sprite(&sprite().with_position(56.0, 20.0).with_flip(true, false));
+        context.draw_sprite(
+            &sprite()
+                .with_position(100.0, 24.0)
+                .with_rotation(std::f32::consts::FRAC_PI_4),
+        );
+        // Submitted top layer first; the layers decide the stacking.
+        context.draw_sprite(
+            &Sprite::new(self.sheet.clone())
+                .with_source_pixels(Rect::new(0.0, 0.0, 8.0, 8.0))
+                .with_size(28.0, 28.0)
+                .with_position(48.0, 68.0)
+                .with_layer(2),
+        );
+        context.draw_sprite(
+            &Sprite::new(self.sheet.clone())
+                .with_source_pixels(Rect::new(0.0, 8.0, 8.0, 8.0))
+                .with_size(28.0, 28.0)
+                .with_position(28.0, 64.0)
+                .with_layer(0),
+        );
+        context.draw_sprite(
+            &Sprite::new(self.sheet.clone())
+                .with_source_pixels(Rect::new(8.0, 8.0, 8.0, 8.0))
+                .with_size(28.0, 28.0)
+                .with_position(38.0, 72.0)
+                .with_tint(Color::YELLOW)
+                .with_layer(1),
+        );
+    }
+}
+
+struct Text {
+    font: Font,
+}
+
+impl Text {
+    fn new() -> Self {
+        Self {
+            font: Font::from_static(include_bytes!("fonts/boxes.ttf")).unwrap(),
+        }
+    }
+}
+
+impl Game for Text {
+    type Context<'a> = TwoDApplicationContext<'a>;
+
+    fn setup(&mut self, context: &mut TwoDApplicationContext) {
+        context.set_background_color(0, 0, 0, 255);
+    }
+
+    fn game_loop(&mut self, context: &mut TwoDApplicationContext) {
+        let title = TextStyle::new(self.font.clone(), 12.0).with_color(Color::YELLOW);
+        context.draw_text("Hello, AVA!", Point2::new(4.0, 4.0), &title);
+        let paragraph = TextStyle::new(self.font.clone(), 10.0)
+            .with_max_width(72.0)
+            .with_align(TextAlign::Center);
+        let layout = TextLayout::new("The quick brown fox jumps over the lazy dog", &paragraph);
+        let bounds = layout.bounds();
+        context.fill_rect(
+            Rect::new(4.0, 24.0, bounds.x, bounds.y),
+            Color::rgb(0.2, 0.2, 0.5),
+        );
+        context.draw_text_layout(&layout, Point2::new(4.0, 24.0), &paragraph);
+        let column = TextStyle::new(self.font.clone(), 8.0)
+            .with_max_width(36.0)
+            .with_align(TextAlign::Right)
+            .with_color(Color::GREEN)
+            .with_line_spacing(1.5);
+        context.draw_text("right\naligned\ni\tMW", Point2::new(86.0, 24.0), &column);
+    }
+}
+
+#[test]
+fn test_game_object_triangle_strip() {
+    GoldenTest::new("test_game_object_triangle_strip")
+        .frames(2)
+        .max_mismatched_pixels(64)
+        .run(TriangleStrip::new())
+        .unwrap();
+}
+
+#[test]
+fn three_d_post_process() {
+    GoldenTest::new("three_d_post_process")
+        .frames(2)
+        .max_mismatched_pixels(64)
+        .run(PostProcessed {
+            strip: TriangleStrip::new(),
+        })
+        .unwrap();
+}
+
+#[test]
+fn two_d_shapes() {
+    GoldenTest::new("two_d_shapes")
+        .max_mismatched_pixels(64)
+        .run(Shapes)
+        .unwrap();
+}
+
+#[test]
+fn two_d_text() {
+    GoldenTest::new("two_d_text")
+        .max_mismatched_pixels(64)
+        .run(Text::new())
+        .unwrap();
+}
+
+#[test]
+fn two_d_sprites() {
+    GoldenTest::new("two_d_sprites")
+        .max_mismatched_pixels(64)
+        .run(Sprites::new())
+        .unwrap();
+}
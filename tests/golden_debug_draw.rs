@@ -0,0 +1,67 @@
+use duende::{
+    common::{application_context::ApplicationContext, color::Color, game::Game},
+    testing::GoldenTest,
+    three_d::{
+        camera::Camera,
+        game_objects::test_game_object::TestGameObject,
+        post_process::{PostProcessPass, PostProcessStack},
+        three_d_application_context::ThreeDApplicationContext,
+    },
+    Matrix3xX, Matrix4, Point3,
+};
+
+/// Debug lines over a scene that post-processing turns grey. The lines are
+/// drawn after post-processing, so they keep their colors.
+struct DebugLines {
+    object: TestGameObject,
+}
+
+impl DebugLines {
+    fn new() -> Self {
+        Self {
+            object: TestGameObject::new(
+                Matrix3xX::from_column_slice(&[
+                    0.0, -0.9, 0.0, -0.6, 0.8, 0.0, 0.9, -0.2, 0.0, -0.9, -0.2, 0.0, 0.6, 0.8, 0.0,
+                ]),
+                Matrix3xX::from_column_slice(&[
+                    1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0, 1.0, 0.0,
+                ]),
+            ),
+        }
+    }
+}
+
+impl Game for DebugLines {
+    type Context<'a> = ThreeDApplicationContext<'a>;
+
+    fn setup(&mut self, context: &mut ThreeDApplicationContext) {
+        context.set_background_color(0, 0, 96, 255);
+        context.set_camera(
+            Camera::orthographic(2.0, 0.1, 100.0).with_position(Point3::new(0.0, 0.0, 5.0)),
+        );
+        context.set_post_process(
+            PostProcessStack::new().with_pass(PostProcessPass::color_grading(0.0, 1.0, 0.0)),
+        );
+    }
+
+    fn game_loop(&mut self, context: &mut ThreeDApplicationContext) {
+        context.draw_game_object(&self.object);
+        let debug = context.debug();
+        debug.aabb(
+            Point3::new(-0.8, -0.8, -0.5),
+            Point3::new(0.8, 0.8, 0.5),
+            Color::RED,
+        );
+        debug.sphere(Point3::origin(), 0.5, Color::YELLOW);
+        debug.axes(&Matrix4::identity(), 0.6);
+    }
+}
+
+#[test]
+fn three_d_debug_lines() {
+    GoldenTest::new("three_d_debug_lines")
+        .frames(2)
+        .max_mismatched_pixels(64)
+        .run(DebugLines::new())
+        .unwrap();
+}